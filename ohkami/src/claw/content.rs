@@ -10,6 +10,7 @@
 //! - `Text`: `text/plain` request/response body
 //! - `Html`: `text/html` response body
 //! - `Multipart`: `multipart/form-data` request body
+//! - `BodyStream`: request body read lazily as a byte stream
 //!
 //! ## Example
 //!
@@ -198,3 +199,6 @@ pub use text::Text;
 
 mod html;
 pub use html::Html;
+
+mod stream;
+pub use stream::BodyStream;
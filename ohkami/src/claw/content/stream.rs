@@ -0,0 +1,146 @@
+use crate::{FromRequest, Request};
+use std::io;
+use std::task::{Context, Poll};
+
+#[cfg(feature = "openapi")]
+use crate::openapi;
+
+/// # streaming request body
+///
+/// Reads the request payload lazily from the connection, instead of
+/// loading the whole payload into memory before the handler is called.
+/// This is useful for large uploads to be stored into a file or relayed to
+/// another service.
+///
+/// - `Transfer-Encoding: chunked` is decoded and its trailer fields are discarded.
+/// - The payload is limited by `request_payload_limit` of `Config` as usual.
+/// - This implements `AsyncRead` of the runtime, so can be used with its
+///   utilities like `tokio::io::copy`.
+///
+/// ### note
+///
+/// - When a handler takes `BodyStream`, the payload is *not* loaded into
+///   `Request::payload`, so other payload claws like `Json` in the same handler,
+///   or fangs, can't see it.
+/// - If the handler returns without reading the payload to the end, the connection
+///   is closed after the response.
+/// - On `rt_worker` and `rt_lambda`, this just reads the already loaded payload.
+///
+/// ### example
+///
+/// ```
+/// use ohkami::claw::content::BodyStream;
+/// use ohkami::Response;
+///
+/// async fn upload(mut body: BodyStream<'_>) -> Result<String, Response> {
+///     let mut size = 0;
+///     let mut buf = [0; 8192];
+///     loop {
+///         match body.read(&mut buf).await {
+///             Ok(0) => break,
+///             Ok(n) => size += n, // store `buf[..n]` somewhere
+///             Err(_) => return Err(Response::BadRequest()),
+///         }
+///     }
+///     Ok(format!("received {size} bytes"))
+/// }
+/// ```
+pub struct BodyStream<'req>(Inner<'req>);
+
+enum Inner<'req> {
+    Loaded(&'req [u8]),
+    #[cfg(feature = "__rt_native__")]
+    Unread(crate::request::payload::Reader<'req>),
+}
+
+impl BodyStream<'_> {
+    /// Read some bytes of the payload into `buf`, returning how many bytes
+    /// were read. `0` means the end of the payload.
+    pub async fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        std::future::poll_fn(|cx| self.poll_read_bytes(cx, buf)).await
+    }
+
+    /// Read all the rest of the payload and append it to `buf`,
+    /// returning how many bytes were read.
+    pub async fn read_to_end(&mut self, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let mut chunk = [0; 1 << 13];
+        let mut size = 0;
+        loop {
+            match self.read(&mut chunk).await? {
+                0 => return Ok(size),
+                n => {
+                    buf.extend_from_slice(&chunk[..n]);
+                    size += n;
+                }
+            }
+        }
+    }
+
+    fn poll_read_bytes(
+        &mut self,
+        _cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        match &mut self.0 {
+            Inner::Loaded(payload) => {
+                let n = payload.len().min(buf.len());
+                buf[..n].copy_from_slice(&payload[..n]);
+                *payload = &payload[n..];
+                Poll::Ready(Ok(n))
+            }
+            #[cfg(feature = "__rt_native__")]
+            Inner::Unread(reader) => reader.poll_read(_cx, buf),
+        }
+    }
+}
+
+impl<'req> FromRequest<'req> for BodyStream<'req> {
+    type Error = std::convert::Infallible;
+
+    fn from_request(req: &'req Request) -> Option<Result<Self, Self::Error>> {
+        #[cfg(feature = "__rt_native__")]
+        if let Some(reader) = req.__unread__.stream() {
+            return Some(Ok(Self(Inner::Unread(reader))));
+        }
+        Some(Ok(Self(Inner::Loaded(req.payload().unwrap_or_default()))))
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi_inbound() -> openapi::Inbound {
+        openapi::Inbound::Body(openapi::RequestBody::of(
+            "application/octet-stream",
+            openapi::string().format("binary"),
+        ))
+    }
+
+    fn streams_payload() -> bool {
+        true
+    }
+}
+
+#[cfg(feature = "__io_tokio__")]
+impl tokio::io::AsyncRead for BodyStream<'_> {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = std::task::ready!(
+            self.get_mut()
+                .poll_read_bytes(cx, buf.initialize_unfilled())
+        )?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "__io_futures__")]
+impl futures_util::AsyncRead for BodyStream<'_> {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_bytes(cx, buf)
+    }
+}
@@ -39,13 +39,30 @@
 /// ```
 #[derive(Clone, Copy)]
 pub struct Config {
-    /// [bytes] size of the internal buffer used to read requests.
+    /// [bytes] initial size of the internal buffer used to read request heads.
+    /// The buffer grows up to `max_request_head_size` for a larger head.
     ///
     /// - default: 2048 (2 KiB)
     /// - env: `OHKAMI_REQUEST_BUFSIZE`
     #[cfg(feature = "__rt_native__")]
     pub request_bufsize: usize,
 
+    /// [bytes] maximum size of a request head ( request line and headers ).
+    /// Requests having a larger head are rejected with `431 Request Header Fields Too Large`.
+    ///
+    /// - default: 65536 (64 KiB)
+    /// - env: `OHKAMI_MAX_REQUEST_HEAD_SIZE`
+    #[cfg(feature = "__rt_native__")]
+    pub max_request_head_size: usize,
+
+    /// maximum number of header fields in a request.
+    /// Requests having more fields are rejected with `431 Request Header Fields Too Large`.
+    ///
+    /// - default: 100
+    /// - env: `OHKAMI_MAX_REQUEST_HEADERS`
+    #[cfg(feature = "__rt_native__")]
+    pub max_request_headers: usize,
+
     /// [bytes] maximum size of the request payload.
     ///
     /// - default: 4294967296 (4 GiB)
@@ -53,14 +70,115 @@ pub struct Config {
     #[cfg(feature = "__rt_native__")]
     pub request_payload_limit: usize,
 
-    /// [secs] duration of the keep-alive timeout.
+    /// [secs] duration of the keep-alive timeout, waiting for the first byte
+    /// of the next request.
     ///
     /// - default: 30 (30 seconds)
     /// - env: `OHKAMI_KEEPALIVE_TIMEOUT`
     #[cfg(feature = "__rt_native__")]
     pub keepalive_timeout: u64,
 
-    /// [secs] duration of the WebSocket session timeout.
+    /// [secs] deadline to receive a whole request head since its first byte
+    /// arrives. Requests not completing the head in time are rejected with
+    /// `408 Request Timeout`.
+    ///
+    /// - default: 10 (10 seconds)
+    /// - env: `OHKAMI_REQUEST_HEAD_TIMEOUT`
+    #[cfg(feature = "__rt_native__")]
+    pub request_head_timeout: u64,
+
+    /// [secs] deadline to receive a whole request payload loaded before handling
+    /// ( not applied to `BodyStream` ). Requests not completing the payload in
+    /// time are rejected with `408 Request Timeout`. `0` for no timeout.
+    ///
+    /// This bounds the whole payload, not each read, so set it with the largest
+    /// upload over the slowest network in mind not to cut off legitimate ones.
+    ///
+    /// - default: 0 (no timeout)
+    /// - env: `OHKAMI_REQUEST_BODY_TIMEOUT`
+    #[cfg(feature = "__rt_native__")]
+    pub request_body_timeout: u64,
+
+    /// maximum number of concurrent connections on a listener. When reached,
+    /// new connections are not accepted and wait in the backlog until
+    /// some of the current connections are closed.
+    ///
+    /// - default: 18446744073709551615 (unlimited)
+    /// - env: `OHKAMI_MAX_CONNECTIONS`
+    #[cfg(feature = "__rt_native__")]
+    pub max_connections: usize,
+
+    /// whether to set `TCP_NODELAY` on accepted connections, sending
+    /// small writes immediately without Nagle's algorithm.
+    ///
+    /// - default: false
+    /// - env: `OHKAMI_TCP_NODELAY`
+    #[cfg(feature = "__rt_native__")]
+    pub tcp_nodelay: bool,
+
+    /// [secs] idle time before TCP keepalive probes are sent on accepted
+    /// connections. `0` disables TCP keepalive.
+    ///
+    /// - default: 0 (disabled)
+    /// - env: `OHKAMI_TCP_KEEPALIVE`
+    #[cfg(feature = "__rt_native__")]
+    pub tcp_keepalive: u64,
+
+    /// [secs] interval between TCP keepalive probes when `tcp_keepalive` is
+    /// enabled. `0` leaves it to the OS default.
+    ///
+    /// - default: 0 (OS default)
+    /// - env: `OHKAMI_TCP_KEEPALIVE_INTERVAL`
+    #[cfg(feature = "__rt_native__")]
+    pub tcp_keepalive_interval: u64,
+
+    /// whether to set `SO_REUSEADDR` on a listener bound from an address,
+    /// to rebind the address soon after restart. Ignored on Windows, where
+    /// this allows other sockets to steal the address.
+    ///
+    /// - default: true
+    /// - env: `OHKAMI_REUSE_ADDRESS`
+    #[cfg(feature = "__rt_native__")]
+    pub reuse_address: bool,
+
+    /// whether to set `SO_REUSEPORT` on a listener bound from an address,
+    /// to let multiple processes or threads listen on the same port. Unix only,
+    /// and the connections are balanced among them only on Linux.
+    ///
+    /// - default: false
+    /// - env: `OHKAMI_REUSE_PORT`
+    #[cfg(feature = "__rt_native__")]
+    pub reuse_port: bool,
+
+    /// maximum length of the queue of pending connections on a listener
+    /// bound from an address.
+    ///
+    /// - default: 1024
+    /// - env: `OHKAMI_LISTEN_BACKLOG`
+    #[cfg(feature = "__rt_native__")]
+    pub listen_backlog: u32,
+
+    /// whether connections start with a PROXY protocol ( v1 or v2 ) header
+    /// from a proxy like HAProxy or AWS NLB. When enabled, `Request::ip` is
+    /// the original client's address from the header, and connections
+    /// without a valid header are rejected.
+    ///
+    /// - default: false
+    /// - env: `OHKAMI_PROXY_PROTOCOL`
+    #[cfg(feature = "__rt_native__")]
+    pub proxy_protocol: bool,
+
+    /// [secs] deadline of graceful shutdown. Connections still open after this
+    /// since the shutdown started are force-closed.
+    ///
+    /// - default: 20 (20 seconds)
+    /// - env: `OHKAMI_SHUTDOWN_TIMEOUT`
+    #[cfg(feature = "__rt_native__")]
+    pub shutdown_timeout: u64,
+
+    /// [secs] duration of the WebSocket session timeout, a hard cap on
+    /// the whole session. `0` for no timeout, leaving dead connections to
+    /// the heartbeat settings of `ws::Config`.
     ///
     /// - default: 3600 (1 hour)
     /// - env: `OHKAMI_WEBSOCKET_TIMEOUT`
@@ -82,12 +200,51 @@ impl Default for Config {
             #[cfg(feature = "__rt_native__")]
             request_bufsize: 1 << 11, // 2 KiB
 
+            #[cfg(feature = "__rt_native__")]
+            max_request_head_size: 1 << 16, // 64 KiB
+
+            #[cfg(feature = "__rt_native__")]
+            max_request_headers: 100,
+
             #[cfg(feature = "__rt_native__")]
             request_payload_limit: 1 << 32, // 4 GiB
 
             #[cfg(feature = "__rt_native__")]
             keepalive_timeout: 30, // 30 seconds
 
+            #[cfg(feature = "__rt_native__")]
+            request_head_timeout: 10, // 10 seconds
+
+            #[cfg(feature = "__rt_native__")]
+            request_body_timeout: 0, // no timeout
+
+            #[cfg(feature = "__rt_native__")]
+            max_connections: usize::MAX, // unlimited
+
+            #[cfg(feature = "__rt_native__")]
+            tcp_nodelay: false,
+
+            #[cfg(feature = "__rt_native__")]
+            tcp_keepalive: 0, // disabled
+
+            #[cfg(feature = "__rt_native__")]
+            tcp_keepalive_interval: 0, // OS default
+
+            #[cfg(feature = "__rt_native__")]
+            reuse_address: true,
+
+            #[cfg(feature = "__rt_native__")]
+            reuse_port: false,
+
+            #[cfg(feature = "__rt_native__")]
+            listen_backlog: 1024,
+
+            #[cfg(feature = "__rt_native__")]
+            proxy_protocol: false,
+
+            #[cfg(feature = "__rt_native__")]
+            shutdown_timeout: 20, // 20 seconds
+
             #[cfg(feature = "__rt_native__")]
             #[cfg(feature = "ws")]
             websocket_timeout: 60 * 60, // 1 hour
@@ -117,6 +274,14 @@ impl Config {
             request_bufsize: parse_env("OHKAMI_REQUEST_BUFSIZE")
                 .unwrap_or(Self::default().request_bufsize),
 
+            #[cfg(feature = "__rt_native__")]
+            max_request_head_size: parse_env("OHKAMI_MAX_REQUEST_HEAD_SIZE")
+                .unwrap_or(Self::default().max_request_head_size),
+
+            #[cfg(feature = "__rt_native__")]
+            max_request_headers: parse_env("OHKAMI_MAX_REQUEST_HEADERS")
+                .unwrap_or(Self::default().max_request_headers),
+
             #[cfg(feature = "__rt_native__")]
             request_payload_limit: parse_env("OHKAMI_REQUEST_PAYLOAD_LIMIT")
                 .unwrap_or(Self::default().request_payload_limit),
@@ -125,6 +290,48 @@ impl Config {
             keepalive_timeout: parse_env("OHKAMI_KEEPALIVE_TIMEOUT")
                 .unwrap_or(Self::default().keepalive_timeout),
 
+            #[cfg(feature = "__rt_native__")]
+            request_head_timeout: parse_env("OHKAMI_REQUEST_HEAD_TIMEOUT")
+                .unwrap_or(Self::default().request_head_timeout),
+
+            #[cfg(feature = "__rt_native__")]
+            request_body_timeout: parse_env("OHKAMI_REQUEST_BODY_TIMEOUT")
+                .unwrap_or(Self::default().request_body_timeout),
+
+            #[cfg(feature = "__rt_native__")]
+            max_connections: parse_env("OHKAMI_MAX_CONNECTIONS")
+                .unwrap_or(Self::default().max_connections),
+
+            #[cfg(feature = "__rt_native__")]
+            tcp_nodelay: parse_env("OHKAMI_TCP_NODELAY").unwrap_or(Self::default().tcp_nodelay),
+
+            #[cfg(feature = "__rt_native__")]
+            tcp_keepalive: parse_env("OHKAMI_TCP_KEEPALIVE")
+                .unwrap_or(Self::default().tcp_keepalive),
+
+            #[cfg(feature = "__rt_native__")]
+            tcp_keepalive_interval: parse_env("OHKAMI_TCP_KEEPALIVE_INTERVAL")
+                .unwrap_or(Self::default().tcp_keepalive_interval),
+
+            #[cfg(feature = "__rt_native__")]
+            reuse_address: parse_env("OHKAMI_REUSE_ADDRESS")
+                .unwrap_or(Self::default().reuse_address),
+
+            #[cfg(feature = "__rt_native__")]
+            reuse_port: parse_env("OHKAMI_REUSE_PORT").unwrap_or(Self::default().reuse_port),
+
+            #[cfg(feature = "__rt_native__")]
+            listen_backlog: parse_env("OHKAMI_LISTEN_BACKLOG")
+                .unwrap_or(Self::default().listen_backlog),
+
+            #[cfg(feature = "__rt_native__")]
+            proxy_protocol: parse_env("OHKAMI_PROXY_PROTOCOL")
+                .unwrap_or(Self::default().proxy_protocol),
+
+            #[cfg(feature = "__rt_native__")]
+            shutdown_timeout: parse_env("OHKAMI_SHUTDOWN_TIMEOUT")
+                .unwrap_or(Self::default().shutdown_timeout),
+
             #[cfg(feature = "__rt_native__")]
             #[cfg(feature = "ws")]
             websocket_timeout: parse_env("OHKAMI_WEBSOCKET_TIMEOUT")
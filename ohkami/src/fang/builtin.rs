@@ -7,6 +7,9 @@ pub use cors::Cors;
 mod csrf;
 pub use csrf::Csrf;
 
+mod trusted_proxy;
+pub use trusted_proxy::TrustedProxy;
+
 mod jwt;
 pub use jwt::{Jwt, JwtToken};
 
@@ -28,6 +28,9 @@ use std::sync::Arc;
 /// }
 /// ```
 ///
+/// Behind reverse proxies, put [`TrustedProxy`](crate::fang::TrustedProxy) before
+/// `Csrf` so that `Origin` is compared with the original host, not the proxied one.
+///
 /// ### Multi Server Service
 ///
 /// If you have multiple servers, you can use `Csrf::with_trusted_origins`
@@ -121,7 +124,8 @@ impl Csrf {
                     .ok_or(CsrfError::InvalidSecFetchSite),
             }
         } else {
-            match (req.headers.origin(), req.headers.host()) {
+            // effective host resolved by `TrustedProxy` if any
+            match (req.headers.origin(), req.host()) {
                 (None, _) => Ok(()), // No Origin header, so we assume it's same-origin or not a browser request.
                 (_, None) => Err(CsrfError::NoHostHeader),
                 (Some(origin), Some(host))
@@ -266,6 +270,38 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_behind_trusted_proxy() {
+        let t = Ohkami::new((
+            crate::fang::TrustedProxy::new(["0.0.0.0"]),
+            Csrf::new(),
+            "/".POST(async || ()),
+        ))
+        .test();
+
+        crate::__rt__::testing::block_on(async {
+            for (req, expected) in [
+                (
+                    TestRequest::POST("/")
+                        .header("host", "internal:8080")
+                        .header("origin", "https://example.com"),
+                    Status::Forbidden,
+                ),
+                (
+                    TestRequest::POST("/")
+                        .header("host", "internal:8080")
+                        .header("origin", "https://example.com")
+                        .header("x-forwarded-for", "203.0.113.1")
+                        .header("x-forwarded-host", "example.com"),
+                    Status::OK,
+                ),
+            ] {
+                let res = t.oneshot(req).await;
+                assert_eq!(res.status(), expected);
+            }
+        });
+    }
+
     #[test]
     fn test_invalid_trusted_origins() {
         for (trusted_origin, should_judged_as_invalid) in [
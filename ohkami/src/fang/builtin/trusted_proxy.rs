@@ -0,0 +1,503 @@
+use crate::request::Forwarded;
+use crate::{Fang, FangProc, Request, Response};
+use std::net::IpAddr;
+use std::sync::Arc;
+
+/// # Built-in fang for running behind reverse proxies
+///
+/// When the directly connected peer is one of the trusted proxies, this
+/// resolves the original request's
+///
+/// - client IP address into `Request::ip`
+/// - scheme into [`Request::scheme`]
+/// - host into [`Request::host`]
+///
+/// from `X-Forwarded-For`, `X-Forwarded-Proto` and `X-Forwarded-Host` headers
+/// ( or RFC 7239 `Forwarded` header with [`use_forwarded`](TrustedProxy::use_forwarded) ).
+///
+/// The client is the rightmost address in the headers that is not a trusted
+/// proxy, because the ones left of it may be forged by the client.
+///
+/// Trusted proxies are given as CIDRs ( like `10.0.0.0/8` or `2400:cb00::/32` )
+/// or single IP addresses. Invalid ones cause a panic.
+///
+/// **NOTE** : Only the header of the selected kind is trusted. Make sure that your
+/// proxies overwrite or append to it, since a client can send it on its own.
+///
+/// ## Example
+///
+/// ```no_run
+/// use ohkami::{Ohkami, Route, Request, fang::TrustedProxy};
+///
+/// async fn whoami(req: &Request) -> String {
+///     format!("{} on {}://{}", req.ip, req.scheme(), req.host().unwrap_or_default())
+/// }
+///
+/// #[tokio::main]
+/// async fn main() {
+///     Ohkami::new((
+///         TrustedProxy::new(["10.0.0.0/8", "127.0.0.1"]),
+///         "/whoami".GET(whoami),
+///     )).howl("0.0.0.0:3000").await
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct TrustedProxy {
+    cidrs: Arc<Vec<Cidr>>,
+    use_forwarded: bool,
+}
+
+impl TrustedProxy {
+    pub fn new(cidrs: impl IntoIterator<Item = impl AsRef<str>>) -> Self {
+        let cidrs = cidrs
+            .into_iter()
+            .map(|cidr| {
+                Cidr::parse(cidr.as_ref())
+                    .unwrap_or_else(|err| panic!("[TrustedProxy::new] {err}: `{}`", cidr.as_ref()))
+            })
+            .collect();
+
+        Self {
+            cidrs: Arc::new(cidrs),
+            use_forwarded: false,
+        }
+    }
+
+    /// Read RFC 7239 `Forwarded` header instead of `X-Forwarded-*` headers.
+    pub fn use_forwarded(mut self, yes: bool) -> Self {
+        self.use_forwarded = yes;
+        self
+    }
+
+    fn trusts(&self, ip: IpAddr) -> bool {
+        self.cidrs.iter().any(|cidr| cidr.contains(ip))
+    }
+
+    /// Resolve `req.ip`, `req.scheme()` and `req.host()` of the original request
+    pub fn resolve(&self, req: &mut Request) {
+        if !self.trusts(req.ip) {
+            return;
+        }
+
+        let hops: Option<Vec<Hop>> = if self.use_forwarded {
+            req.headers
+                .forwarded()
+                .map(|value| split_list(value).map(parse_forwarded_element).collect())
+        } else {
+            x_forwarded(req, "x-forwarded-for").map(|value| {
+                let protos = x_forwarded(req, "x-forwarded-proto");
+                let hosts = x_forwarded(req, "x-forwarded-host");
+                let addrs = split_list(value).collect::<Vec<_>>();
+                let n = addrs.len();
+                addrs
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, addr)| Hop {
+                        r#for: parse_node(addr),
+                        // proxies appending to `X-Forwarded-For` are expected to append to
+                        // these too, so they're aligned with `X-Forwarded-For` from the right
+                        proto: from_right(protos, n - 1 - i),
+                        host: from_right(hosts, n - 1 - i),
+                    })
+                    .collect()
+            })
+        };
+        let Some(hops) = hops else { return };
+
+        let mut client = None;
+        for hop in hops.into_iter().rev() {
+            let Some(ip) = hop.r#for else {
+                // `unknown`, obfuscated or invalid node : can't see beyond it
+                break;
+            };
+            client = Some(hop);
+            if !self.trusts(ip) {
+                break;
+            }
+        }
+        let Some(Hop {
+            r#for: Some(ip),
+            proto,
+            host,
+        }) = client
+        else {
+            return;
+        };
+
+        let proto = proto
+            .filter(|p| p.eq_ignore_ascii_case("http") || p.eq_ignore_ascii_case("https"))
+            .map(|p| p.to_ascii_lowercase().into_boxed_str());
+        let host = host
+            .filter(|h| !h.is_empty() && !h.contains(['/', '?', '#', '@', ' ']))
+            .map(Box::from);
+        req.forwarded =
+            (proto.is_some() || host.is_some()).then(|| Box::new(Forwarded { proto, host }));
+        req.ip = ip;
+    }
+}
+
+const _: () = {
+    pub struct TrustedProxyProc<I: FangProc> {
+        trusted_proxy: TrustedProxy,
+        inner: I,
+    }
+
+    impl<I: FangProc> Fang<I> for TrustedProxy {
+        type Proc = TrustedProxyProc<I>;
+
+        fn chain(&self, inner: I) -> Self::Proc {
+            TrustedProxyProc {
+                trusted_proxy: self.clone(),
+                inner,
+            }
+        }
+    }
+
+    impl<I: FangProc> FangProc for TrustedProxyProc<I> {
+        async fn bite<'b>(&'b self, req: &'b mut Request) -> Response {
+            self.trusted_proxy.resolve(req);
+            self.inner.bite(req).await
+        }
+    }
+};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn parse(s: &str) -> Result<Self, &'static str> {
+        let (addr, prefix) = s.split_once('/').map_or((s, None), |(a, p)| (a, Some(p)));
+        let addr = addr
+            .parse::<IpAddr>()
+            .map_err(|_| "invalid IP address of CIDR")?
+            .to_canonical();
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix {
+            None => max,
+            Some(p) => p
+                .parse::<u8>()
+                .ok()
+                .filter(|p| *p <= max)
+                .ok_or("invalid prefix length of CIDR")?,
+        };
+        Ok(Self { addr, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        let mask = |bits: u32| match self.prefix {
+            0 => 0,
+            p => u128::MAX << (bits - p as u32),
+        };
+        match (self.addr, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = mask(32) as u32;
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = mask(128);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A proxy hop reported in forwarding headers
+struct Hop<'h> {
+    r#for: Option<IpAddr>,
+    proto: Option<&'h str>,
+    host: Option<&'h str>,
+}
+
+/// custom header, looked up case-insensitively
+fn x_forwarded<'r>(req: &'r Request, name: &str) -> Option<&'r str> {
+    req.headers
+        .iter()
+        .find_map(|(key, value)| key.eq_ignore_ascii_case(name).then_some(value))
+}
+
+/// `n`-th element from the right of a comma-separated list,
+/// `None` if the list is shorter ( not to take another hop's value )
+fn from_right(list: Option<&str>, n: usize) -> Option<&str> {
+    let elements = split_list(list?).collect::<Vec<_>>();
+    elements
+        .len()
+        .checked_sub(n + 1)
+        .and_then(|i| elements.get(i))
+        .copied()
+}
+
+/// Split a comma-separated list, respecting quoted-strings
+fn split_list(list: &str) -> impl Iterator<Item = &str> {
+    let mut rest = Some(list);
+    std::iter::from_fn(move || {
+        let list = rest?;
+        let mut in_quotes = false;
+        let mut escaped = false;
+        let end = list.char_indices().find_map(|(i, c)| {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' if in_quotes => escaped = true,
+                '"' => in_quotes = !in_quotes,
+                ',' if !in_quotes => return Some(i),
+                _ => (),
+            }
+            None
+        });
+        let (element, next) = match end {
+            Some(i) => (&list[..i], Some(&list[i + 1..])),
+            None => (list, None),
+        };
+        rest = next;
+        Some(element.trim_matches([' ', '\t']))
+    })
+    .filter(|element| !element.is_empty())
+}
+
+/// `for=192.0.2.60;proto=http;by=203.0.113.43` ( RFC 7239, Section 4 )
+fn parse_forwarded_element(element: &str) -> Hop<'_> {
+    let mut hop = Hop {
+        r#for: None,
+        proto: None,
+        host: None,
+    };
+    for pair in element.split(';') {
+        let Some((key, value)) = pair.trim_matches([' ', '\t']).split_once('=') else {
+            continue;
+        };
+        let value = value
+            .strip_prefix('"')
+            .and_then(|v| v.strip_suffix('"'))
+            .unwrap_or(value);
+        if key.eq_ignore_ascii_case("for") {
+            hop.r#for = parse_node(value);
+        } else if key.eq_ignore_ascii_case("proto") {
+            hop.proto = Some(value);
+        } else if key.eq_ignore_ascii_case("host") {
+            hop.host = Some(value);
+        }
+    }
+    hop
+}
+
+/// `192.0.2.43`, `192.0.2.43:47011`, `2001:db8:cafe::17`, `[2001:db8:cafe::17]:4711`
+fn parse_node(node: &str) -> Option<IpAddr> {
+    let ip = match node.strip_prefix('[') {
+        Some(v6) => v6.split_once(']')?.0,
+        None if node.matches(':').count() == 1 => node.split_once(':')?.0,
+        None => node,
+    };
+    ip.parse::<IpAddr>().ok().map(|ip| ip.to_canonical())
+}
+
+#[cfg(test)]
+#[cfg(feature = "__rt_native__")]
+mod tests {
+    use super::*;
+    use crate::testing::*;
+    use crate::{Ohkami, Route};
+
+    #[test]
+    fn test_cidr() {
+        for (cidr, ip, expected) in [
+            ("10.0.0.0/8", "10.1.2.3", true),
+            ("10.0.0.0/8", "11.1.2.3", false),
+            ("10.0.0.0/8", "::ffff:10.1.2.3", true),
+            ("127.0.0.1", "127.0.0.1", true),
+            ("127.0.0.1", "127.0.0.2", false),
+            ("0.0.0.0/0", "203.0.113.1", true),
+            ("0.0.0.0/0", "2001:db8::1", false),
+            ("2400:cb00::/32", "2400:cb00:1::1", true),
+            ("2400:cb00::/32", "2400:cb01::1", false),
+            ("::/0", "2001:db8::1", true),
+            ("::1", "::1", true),
+        ] {
+            assert_eq!(
+                Cidr::parse(cidr).unwrap().contains(ip.parse().unwrap()),
+                expected,
+                "{cidr} contains {ip}"
+            );
+        }
+
+        for invalid in [
+            "",
+            "10.0.0.0/",
+            "10.0.0.0/33",
+            "::/129",
+            "10.0.0/8",
+            "localhost",
+        ] {
+            assert!(Cidr::parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_x_forwarded() {
+        // `TestRequest`s come from `0.0.0.0`
+        let t = Ohkami::new((
+            TrustedProxy::new(["0.0.0.0", "10.0.0.0/8"]),
+            "/".GET(async |req: &Request| {
+                format!(
+                    "{} {}://{}",
+                    req.ip,
+                    req.scheme(),
+                    req.host().unwrap_or_default()
+                )
+            }),
+        ))
+        .test();
+
+        crate::__rt__::testing::block_on(async {
+            for (req, expected) in [
+                (TestRequest::GET("/"), "0.0.0.0 http://ohkami.test"),
+                (
+                    TestRequest::GET("/").header("host", "internal:8080"),
+                    "0.0.0.0 http://internal:8080",
+                ),
+                (
+                    TestRequest::GET("/")
+                        .header("host", "internal:8080")
+                        .header("x-forwarded-for", "203.0.113.1")
+                        .header("x-forwarded-proto", "https")
+                        .header("x-forwarded-host", "example.com"),
+                    "203.0.113.1 https://example.com",
+                ),
+                (
+                    // forged by the client, followed by trusted hops
+                    TestRequest::GET("/")
+                        .header("X-Forwarded-For", "198.51.100.1, 203.0.113.1, 10.0.0.2")
+                        .header("X-Forwarded-Proto", "http, https, http")
+                        .header("X-Forwarded-Host", "forged.example, example.com, internal"),
+                    "203.0.113.1 https://example.com",
+                ),
+                (
+                    // shorter than `X-Forwarded-For` : not for the client's hop
+                    TestRequest::GET("/")
+                        .header("x-forwarded-for", "203.0.113.1, 10.0.0.2")
+                        .header("x-forwarded-proto", "https"),
+                    "203.0.113.1 http://ohkami.test",
+                ),
+                (
+                    // all hops are trusted
+                    TestRequest::GET("/").header("x-forwarded-for", "10.0.0.3, 10.0.0.2"),
+                    "10.0.0.3 http://ohkami.test",
+                ),
+                (
+                    TestRequest::GET("/").header("x-forwarded-for", "[2001:db8::1]:4711"),
+                    "2001:db8::1 http://ohkami.test",
+                ),
+                (
+                    // invalid node
+                    TestRequest::GET("/")
+                        .header("x-forwarded-for", "203.0.113.1, garbage")
+                        .header("x-forwarded-proto", "https"),
+                    "0.0.0.0 http://ohkami.test",
+                ),
+                (
+                    TestRequest::GET("/")
+                        .header("x-forwarded-for", "203.0.113.1")
+                        .header("x-forwarded-proto", "javascript")
+                        .header("x-forwarded-host", "evil.example/path"),
+                    "203.0.113.1 http://ohkami.test",
+                ),
+                (
+                    // `Forwarded` is ignored by default
+                    TestRequest::GET("/").header("forwarded", "for=203.0.113.1;proto=https"),
+                    "0.0.0.0 http://ohkami.test",
+                ),
+            ] {
+                let res = t.oneshot(req).await;
+                assert_eq!(res.text(), Some(expected));
+            }
+        });
+    }
+
+    #[test]
+    fn test_repeated_x_forwarded_for() {
+        let config = crate::Config::default();
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        let mut req = std::pin::Pin::new(&mut req);
+
+        // appended by another proxy as a new field line
+        let mut raw: &[u8] = b"GET / HTTP/1.1\r\n\
+            X-Forwarded-For: 203.0.113.1\r\n\
+            X-Forwarded-For: 10.0.0.2\r\n\
+            \r\n";
+        crate::__rt__::testing::block_on(req.as_mut().read_head(&mut raw, &config)).unwrap();
+
+        TrustedProxy::new(["0.0.0.0", "10.0.0.0/8"]).resolve(&mut req);
+        assert_eq!(req.ip.to_string(), "203.0.113.1");
+    }
+
+    #[test]
+    fn test_forwarded() {
+        let t = Ohkami::new((
+            TrustedProxy::new(["0.0.0.0", "10.0.0.0/8"]).use_forwarded(true),
+            "/".GET(async |req: &Request| {
+                format!(
+                    "{} {}://{}",
+                    req.ip,
+                    req.scheme(),
+                    req.host().unwrap_or_default()
+                )
+            }),
+        ))
+        .test();
+
+        crate::__rt__::testing::block_on(async {
+            for (req, expected) in [
+                (
+                    TestRequest::GET("/")
+                        .header("forwarded", "for=203.0.113.1;proto=https;host=example.com"),
+                    "203.0.113.1 https://example.com",
+                ),
+                (
+                    TestRequest::GET("/").header(
+                        "forwarded",
+                        r#"for=198.51.100.1;host=forged.example, For="[2001:db8::1]:4711";Proto=https;Host="example.com:8443", for=10.0.0.2;proto=http"#,
+                    ),
+                    "2001:db8::1 https://example.com:8443",
+                ),
+                (
+                    TestRequest::GET("/").header("forwarded", "for=unknown, for=10.0.0.2"),
+                    "10.0.0.2 http://ohkami.test",
+                ),
+                (
+                    TestRequest::GET("/").header("forwarded", "for=_hidden;proto=https"),
+                    "0.0.0.0 http://ohkami.test",
+                ),
+                (
+                    // `X-Forwarded-*` is ignored
+                    TestRequest::GET("/").header("x-forwarded-for", "203.0.113.1"),
+                    "0.0.0.0 http://ohkami.test",
+                ),
+            ] {
+                let res = t.oneshot(req).await;
+                assert_eq!(res.text(), Some(expected));
+            }
+        });
+    }
+
+    #[test]
+    fn test_untrusted_peer() {
+        let t = Ohkami::new((
+            TrustedProxy::new(["10.0.0.0/8"]),
+            "/".GET(async |req: &Request| format!("{} {}", req.ip, req.scheme())),
+        ))
+        .test();
+
+        crate::__rt__::testing::block_on(async {
+            let res = t
+                .oneshot(
+                    TestRequest::GET("/")
+                        .header("x-forwarded-for", "203.0.113.1")
+                        .header("x-forwarded-proto", "https"),
+                )
+                .await;
+            assert_eq!(res.text(), Some("0.0.0.0 http"));
+        });
+    }
+}
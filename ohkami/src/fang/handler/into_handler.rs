@@ -112,6 +112,7 @@ where
                     .inbound(Req1::openapi_inbound())
             },
         )
+        .streams_payload(Req1::streams_payload())
     }
 }
 
@@ -143,6 +144,7 @@ where
                     .inbound(Req2::openapi_inbound())
             },
         )
+        .streams_payload(Req1::streams_payload() || Req2::streams_payload())
     }
 }
 
@@ -182,6 +184,9 @@ where
                     .inbound(Req3::openapi_inbound())
             },
         )
+        .streams_payload(
+            Req1::streams_payload() || Req2::streams_payload() || Req3::streams_payload(),
+        )
     }
 }
 
@@ -227,6 +232,12 @@ where
                     .inbound(Req4::openapi_inbound())
             },
         )
+        .streams_payload(
+            Req1::streams_payload()
+                || Req2::streams_payload()
+                || Req3::streams_payload()
+                || Req4::streams_payload(),
+        )
     }
 }
 
@@ -279,6 +290,13 @@ where
                     .inbound(Req5::openapi_inbound())
             },
         )
+        .streams_payload(
+            Req1::streams_payload()
+                || Req2::streams_payload()
+                || Req3::streams_payload()
+                || Req4::streams_payload()
+                || Req5::streams_payload(),
+        )
     }
 }
 
@@ -336,6 +354,14 @@ where
                     .inbound(Req6::openapi_inbound())
             },
         )
+        .streams_payload(
+            Req1::streams_payload()
+                || Req2::streams_payload()
+                || Req3::streams_payload()
+                || Req4::streams_payload()
+                || Req5::streams_payload()
+                || Req6::streams_payload(),
+        )
     }
 }
 
@@ -357,6 +383,7 @@ where
         let h = h.into_handler();
         Handler {
             proc: Fangs::build(&f, h.proc),
+            streams_payload: h.streams_payload,
             #[cfg(feature = "openapi")]
             openapi_operation: Fangs::openapi_map_operation(&f, h.openapi_operation),
         }
@@ -379,6 +406,7 @@ where
         let f = (f1, f2);
         Handler {
             proc: Fangs::build(&f, h.proc),
+            streams_payload: h.streams_payload,
             #[cfg(feature = "openapi")]
             openapi_operation: Fangs::openapi_map_operation(&f, h.openapi_operation),
         }
@@ -402,6 +430,7 @@ where
         let f = (f1, f2, f3);
         Handler {
             proc: Fangs::build(&f, h.proc),
+            streams_payload: h.streams_payload,
             #[cfg(feature = "openapi")]
             openapi_operation: Fangs::openapi_map_operation(&f, h.openapi_operation),
         }
@@ -427,6 +456,7 @@ where
         let f = (f1, f2, f3, f4);
         Handler {
             proc: Fangs::build(&f, h.proc),
+            streams_payload: h.streams_payload,
             #[cfg(feature = "openapi")]
             openapi_operation: Fangs::openapi_map_operation(&f, h.openapi_operation),
         }
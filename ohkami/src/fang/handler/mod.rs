@@ -13,6 +13,11 @@ pub struct Handler {
     #[allow(dead_code/* read only in router */)]
     pub(crate) proc: BoxedFPC,
 
+    /// whether the handler reads the request payload by itself via
+    /// `claw::content::BodyStream`, so the router must not load it in advance
+    #[allow(dead_code/* read only in native router */)]
+    pub(crate) streams_payload: bool,
+
     #[cfg(feature = "openapi")]
     pub(crate) openapi_operation: openapi::Operation,
 }
@@ -54,11 +59,18 @@ impl Handler {
         Self {
             proc: BoxedFPC::from_proc(HandlerProc(proc)),
 
+            streams_payload: false,
+
             #[cfg(feature = "openapi")]
             openapi_operation,
         }
     }
 
+    pub(crate) fn streams_payload(mut self, streams_payload: bool) -> Self {
+        self.streams_payload = streams_payload;
+        self
+    }
+
     /// A utility to create an owned `Handler` instance,
     /// with `openapi_operation` cloned and `proc` replaced to a dummy (meaningless thing),
     /// from a `&Handler`.
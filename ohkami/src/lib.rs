@@ -142,44 +142,276 @@ mod __rt__ {
     #[cfg(feature = "rt_compio")]
     pub(crate) type TcpStream = compio::io::compat::AsyncStream<compio::net::TcpStream>;
 
+    #[cfg(all(unix, feature = "rt_compio"))]
+    pub(crate) use compio::net::UnixListener;
+    #[cfg(all(unix, feature = "rt_glommio"))]
+    pub(crate) use glommio::net::{UnixListener, UnixStream};
+    #[cfg(all(unix, feature = "rt_smol"))]
+    pub(crate) use smol::net::unix::{UnixListener, UnixStream};
+    #[cfg(all(unix, feature = "rt_tokio"))]
+    pub(crate) use tokio::net::{UnixListener, UnixStream};
+    #[cfg(all(unix, feature = "rt_monoio"))]
+    pub(crate) use {monoio::net::UnixListener, monoio_compat::UnixStreamCompat as UnixStream};
+
+    #[cfg(all(unix, feature = "rt_compio"))]
+    pub(crate) type UnixStream = compio::io::compat::AsyncStream<compio::net::UnixStream>;
+
+    pub enum Listener {
+        Tcp(TcpListener),
+        #[cfg(all(unix, not(feature = "rt_nio")))]
+        Unix(UnixListener),
+    }
+
     #[cfg(not(feature = "rt_nio"))]
-    #[inline(always)]
-    pub(crate) async fn accept(
-        listener: &TcpListener,
-    ) -> std::io::Result<(TcpStream, std::net::SocketAddr)> {
-        #[cfg(any(feature = "rt_tokio", feature = "rt_smol"))]
-        {
-            listener.accept().await
+    impl Listener {
+        /// accept a connection with the IP address of the peer and
+        /// the local address of the connection, applying the socket
+        /// options of `config` to it
+        pub(crate) async fn accept(
+            &self,
+            config: &crate::Config,
+        ) -> std::io::Result<(
+            crate::session::Connection,
+            std::net::IpAddr,
+            Option<std::net::SocketAddr>,
+        )> {
+            match self {
+                Self::Tcp(listener) => {
+                    #[cfg(any(feature = "rt_tokio", feature = "rt_smol"))]
+                    let (connection, addr) = listener.accept().await?;
+                    #[cfg(any(feature = "rt_tokio", feature = "rt_smol"))]
+                    let local_addr = {
+                        tune(&connection, config);
+                        connection.local_addr().ok()
+                    };
+                    #[cfg(any(feature = "rt_glommio"))]
+                    let (connection, addr, local_addr) = {
+                        let connection = listener.accept().await?;
+                        tune(&connection, config);
+                        let addr = connection.peer_addr()?;
+                        let local_addr = connection.local_addr().ok();
+                        (connection, addr, local_addr)
+                    };
+                    #[cfg(any(feature = "rt_monoio", feature = "rt_compio"))]
+                    let (connection, addr, local_addr) = {
+                        let (conn, addr) = listener.accept().await?;
+                        tune(&conn, config);
+                        let local_addr = conn.local_addr().ok();
+                        (TcpStream::new(conn), addr, local_addr)
+                    };
+                    Ok((connection.into(), addr.ip(), local_addr))
+                }
+                #[cfg(unix)]
+                Self::Unix(listener) => {
+                    #[cfg(any(feature = "rt_tokio", feature = "rt_smol"))]
+                    let (connection, _) = listener.accept().await?;
+                    #[cfg(any(feature = "rt_glommio"))]
+                    let connection = listener.accept().await?;
+                    #[cfg(any(feature = "rt_monoio", feature = "rt_compio"))]
+                    let connection = {
+                        let (conn, _) = listener.accept().await?;
+                        UnixStream::new(conn)
+                    };
+                    // the peer is on the same host, without an IP address
+                    Ok((
+                        connection.into(),
+                        std::net::Ipv4Addr::LOCALHOST.into(),
+                        None,
+                    ))
+                }
+            }
         }
-        #[cfg(any(feature = "rt_glommio"))]
-        {
-            let connection = listener.accept().await?;
-            let addr = connection.peer_addr()?;
-            Ok((connection, addr))
+    }
+
+    impl Listener {
+        /// a listener of the runtime from the one bound by `bind_tcp`,
+        /// called in the runtime's context
+        pub(crate) fn from_std(listener: std::net::TcpListener) -> std::io::Result<Self> {
+            #[cfg(any(feature = "rt_tokio", feature = "rt_monoio", feature = "rt_compio"))]
+            let listener = TcpListener::from_std(listener)?;
+            #[cfg(feature = "rt_smol")]
+            let listener = TcpListener::try_from(listener)?;
+            #[cfg(feature = "rt_nio")]
+            let listener = TcpListener::new(mio::net::TcpListener::from_std(listener))?;
+            #[cfg(feature = "rt_glommio")]
+            let listener = {
+                use std::os::fd::{FromRawFd, IntoRawFd};
+                // SAFETY: the fd is of a listening TCP socket, owned by this listener
+                unsafe { TcpListener::from_raw_fd(listener.into_raw_fd()) }
+            };
+            Ok(Self::Tcp(listener))
+        }
+
+        pub(crate) fn local_addr(&self) -> std::io::Result<crate::ListenAddr> {
+            match self {
+                Self::Tcp(listener) => Ok(crate::ListenAddr::Tcp(listener.local_addr()?)),
+                #[cfg(all(unix, not(feature = "rt_nio")))]
+                Self::Unix(listener) => {
+                    // `monoio::net::UnixListener` doesn't provide its address
+                    #[cfg(feature = "rt_monoio")]
+                    let path = {
+                        let _ = listener;
+                        None
+                    };
+                    #[cfg(not(feature = "rt_monoio"))]
+                    let path = listener
+                        .local_addr()?
+                        .as_pathname()
+                        .map(std::path::Path::to_owned);
+
+                    Ok(crate::ListenAddr::Unix(path))
+                }
+            }
         }
-        #[cfg(any(feature = "rt_monoio", feature = "rt_compio"))]
-        {
-            let (conn, addr) = listener.accept().await?;
-            Ok((TcpStream::new(conn), addr))
+    }
+
+    impl std::fmt::Display for Listener {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self.local_addr() {
+                Ok(addr) => addr.fmt(f),
+                Err(_) => f.write_str("listener"),
+            }
         }
     }
 
-    pub trait IntoTcpListener<T> {
-        fn into_tcp_listener(self) -> impl Future<Output = TcpListener>;
+    /// apply the socket options of `config` to an accepted TCP connection
+    #[cfg(not(feature = "rt_nio"))]
+    fn tune(
+        #[cfg(unix)] connection: &impl std::os::fd::AsRawFd,
+        #[cfg(windows)] connection: &impl std::os::windows::io::AsRawSocket,
+        config: &crate::Config,
+    ) {
+        // SAFETY: `connection` is alive while the borrow is used
+        #[cfg(unix)]
+        let socket = unsafe { std::os::fd::BorrowedFd::borrow_raw(connection.as_raw_fd()) };
+        #[cfg(windows)]
+        let socket =
+            unsafe { std::os::windows::io::BorrowedSocket::borrow_raw(connection.as_raw_socket()) };
+        let socket = socket2::SockRef::from(&socket);
+
+        if let Err(e) = (|| {
+            if config.tcp_nodelay {
+                socket.set_tcp_nodelay(true)?;
+            }
+            if let Some(keepalive) = tcp_keepalive(config) {
+                socket.set_tcp_keepalive(&keepalive)?;
+            }
+            std::io::Result::Ok(())
+        })() {
+            crate::WARNING!("Failed to set socket options on accepted connection: {e}");
+        }
     }
-    impl IntoTcpListener<()> for TcpListener {
-        async fn into_tcp_listener(self) -> TcpListener {
-            self
+
+    fn tcp_keepalive(config: &crate::Config) -> Option<socket2::TcpKeepalive> {
+        use std::time::Duration;
+
+        if config.tcp_keepalive == 0 {
+            return None;
         }
+        let keepalive =
+            socket2::TcpKeepalive::new().with_time(Duration::from_secs(config.tcp_keepalive));
+        #[cfg(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos",
+            target_os = "ios",
+            target_os = "freebsd",
+            target_os = "netbsd",
+            target_os = "windows",
+        ))]
+        let keepalive = match config.tcp_keepalive_interval {
+            0 => keepalive,
+            secs => keepalive.with_interval(Duration::from_secs(secs)),
+        };
+        Some(keepalive)
     }
-    impl<A: ToSocketAddrs> IntoTcpListener<A> for A {
-        async fn into_tcp_listener(self) -> TcpListener {
-            let binded = TcpListener::bind(self);
 
-            #[cfg(not(any(feature = "rt_glommio", feature = "rt_monoio")))]
-            let binded = binded.await;
+    /// bind a TCP listener to the first of `addrs` available, with the
+    /// socket options of `config`
+    pub(crate) fn bind_tcp(
+        addrs: impl IntoIterator<Item = std::net::SocketAddr>,
+        config: &crate::Config,
+    ) -> std::io::Result<std::net::TcpListener> {
+        use socket2::{Domain, Protocol, Socket, Type};
+
+        let bind = |addr: std::net::SocketAddr| -> std::io::Result<std::net::TcpListener> {
+            let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+            #[cfg(not(windows))]
+            socket.set_reuse_address(config.reuse_address)?;
+            #[cfg(all(
+                unix,
+                not(any(target_os = "solaris", target_os = "illumos", target_os = "cygwin"))
+            ))]
+            if config.reuse_port {
+                socket.set_reuse_port(true)?;
+            }
+            // `nio` doesn't expose accepted sockets, so set the options to be
+            // inherited by them ( on Linux, BSDs and Windows )
+            #[cfg(feature = "rt_nio")]
+            if let Some(keepalive) = tcp_keepalive(config) {
+                socket.set_tcp_keepalive(&keepalive)?;
+            }
+            socket.bind(&addr.into())?;
+            socket.listen(config.listen_backlog.min(i32::MAX as u32) as i32)?;
+            // io_uring-based `glommio` and `compio` take blocking sockets
+            #[cfg(not(any(feature = "rt_glommio", feature = "rt_compio")))]
+            socket.set_nonblocking(true)?;
+            Ok(socket.into())
+        };
+
+        let mut last_error = None;
+        for addr in addrs {
+            match bind(addr) {
+                Ok(listener) => return Ok(listener),
+                Err(e) => last_error = Some(e),
+            }
+        }
+        Err(last_error.unwrap_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "could not resolve to any address",
+            )
+        }))
+    }
 
-            binded.expect("Failed to bind TCP listener")
+    /// `config` is applied to a listener bound from an address. An already
+    /// bound listener is used as it is, with `tcp_nodelay` and `tcp_keepalive`
+    /// still applied to its connections.
+    pub trait IntoListener<T> {
+        fn into_listener(self, config: &crate::Config) -> impl Future<Output = Listener>;
+    }
+    impl IntoListener<()> for TcpListener {
+        async fn into_listener(self, _: &crate::Config) -> Listener {
+            Listener::Tcp(self)
+        }
+    }
+    impl<A: ToSocketAddrs> IntoListener<A> for A {
+        async fn into_listener(self, config: &crate::Config) -> Listener {
+            #[cfg(feature = "rt_tokio")]
+            let addrs = tokio::net::lookup_host(self).await;
+            #[cfg(feature = "rt_smol")]
+            let addrs = smol::net::resolve(self).await;
+            #[cfg(feature = "rt_compio")]
+            let addrs = self.to_socket_addrs_async().await;
+            #[cfg(any(feature = "rt_glommio", feature = "rt_monoio", feature = "rt_nio"))]
+            let addrs = self.to_socket_addrs();
+
+            addrs
+                .and_then(|addrs| bind_tcp(addrs, config))
+                .and_then(Listener::from_std)
+                .expect("Failed to bind TCP listener")
+        }
+    }
+    #[cfg(all(unix, not(feature = "rt_nio")))]
+    impl IntoListener<()> for UnixListener {
+        async fn into_listener(self, _: &crate::Config) -> Listener {
+            Listener::Unix(self)
+        }
+    }
+    #[cfg(all(unix, not(feature = "rt_nio")))]
+    impl IntoListener<()> for crate::UnixSocket {
+        async fn into_listener(self, config: &crate::Config) -> Listener {
+            Listener::Unix(self.bind(config).await)
         }
     }
 
@@ -256,6 +488,74 @@ mod __rt__ {
             return compio::runtime::Runtime::new().unwrap().block_on(future);
         }
 
+        /// Serve `ohkami` on a TCP port of localhost with `config`, running `client`
+        /// with the address on another thread. The server shuts down when `client`
+        /// returns, or earlier by the `ShutdownGuard`.
+        pub(crate) fn serve<T: Send + 'static>(
+            ohkami: crate::Ohkami,
+            config: crate::Config,
+            client: impl FnOnce(std::net::SocketAddr, ShutdownGuard) -> T + Send + 'static,
+        ) -> T {
+            serve_multi(
+                ohkami,
+                [crate::Listen::new(("localhost", 0)).config(config)],
+                |addrs, shutdown| {
+                    let [crate::ListenAddr::Tcp(addr)] = addrs[..] else {
+                        panic!("expected a TCP address: {addrs:?}")
+                    };
+                    client(addr, shutdown)
+                },
+            )
+        }
+
+        /// Same as `serve`, but on the `listens`
+        pub(crate) fn serve_multi<T: Send + 'static>(
+            ohkami: crate::Ohkami,
+            listens: impl IntoIterator<Item = crate::Listen>,
+            client: impl FnOnce(Vec<crate::ListenAddr>, ShutdownGuard) -> T + Send + 'static,
+        ) -> T {
+            use std::sync::atomic::{AtomicBool, Ordering};
+
+            let shutdown = std::sync::Arc::new(AtomicBool::new(false));
+            let (addrs_tx, addrs_rx) = std::sync::mpsc::channel();
+
+            let client = std::thread::spawn({
+                let shutdown = ShutdownGuard(shutdown.clone());
+                move || client(addrs_rx.recv().unwrap(), shutdown)
+            });
+
+            block_on(
+                ohkami
+                    .on_start(move |addrs| async move {
+                        addrs_tx.send(addrs).unwrap();
+                    })
+                    .howl_multi_until(listens, async {
+                        while !shutdown.load(Ordering::SeqCst) {
+                            super::sleep(std::time::Duration::from_millis(10)).await;
+                        }
+                    }),
+            );
+
+            client
+                .join()
+                .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+        }
+
+        /// Shuts down the server of `serve` when dropped, even by a panic in the client
+        pub(crate) struct ShutdownGuard(std::sync::Arc<std::sync::atomic::AtomicBool>);
+
+        impl ShutdownGuard {
+            pub(crate) fn shutdown(&self) {
+                self.0.store(true, std::sync::atomic::Ordering::SeqCst);
+            }
+        }
+
+        impl Drop for ShutdownGuard {
+            fn drop(&mut self) {
+                self.shutdown();
+            }
+        }
+
         pub(crate) const PORT: u16 = {
             #[cfg(feature = "rt_tokio")]
             {
@@ -303,12 +603,24 @@ pub use response::{IntoResponse, Response, Status};
 
 #[cfg(feature = "__rt_native__")]
 mod session;
+#[cfg(all(feature = "__rt_native__", feature = "tls"))]
+pub use session::TlsInfo;
+#[cfg(feature = "__rt_native__")]
+pub use session::{ConnectionInfo, ProxyHeader};
 
 #[cfg(feature = "__rt__")]
 mod router;
 
 #[cfg(feature = "__rt__")]
 mod ohkami;
+#[cfg(all(feature = "__rt_native__", not(feature = "__rt_threaded__")))]
+pub use ohkami::PerCore;
+#[cfg(all(feature = "__rt_native__", feature = "tls"))]
+pub use ohkami::TlsCerts;
+#[cfg(all(unix, feature = "__rt_native__", not(feature = "rt_nio")))]
+pub use ohkami::UnixSocket;
+#[cfg(feature = "__rt_native__")]
+pub use ohkami::{Health, Listen, ListenAddr};
 #[cfg(feature = "__rt__")]
 pub use ohkami::{Ohkami, Route};
 
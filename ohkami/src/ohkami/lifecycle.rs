@@ -0,0 +1,163 @@
+#![cfg(feature = "__rt_native__")]
+
+use super::sync::Shutdown;
+use crate::fang::bound::{SendOnThreaded, SendSyncOnThreaded};
+use crate::{FromRequest, Request};
+use std::{future::Future, pin::Pin};
+
+/// Local address of a listener an `Ohkami` is serving on, passed to the
+/// [`on_start`](crate::Ohkami::on_start) hooks.
+///
+/// Handlers can see which listener a request came in on as `&ListenAddr`
+/// ( or by `req.context.listen_addr()` in fangs ), which is not available
+/// for a `Request` not served by `howl` ( like in `ohkami::testing` ).
+#[derive(Clone, Debug, PartialEq)]
+pub enum ListenAddr {
+    Tcp(std::net::SocketAddr),
+    /// path of the socket file ( `None` when the async runtime doesn't provide it )
+    #[cfg(unix)]
+    Unix(Option<std::path::PathBuf>),
+}
+
+impl std::fmt::Display for ListenAddr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Tcp(addr) => addr.fmt(f),
+            #[cfg(unix)]
+            Self::Unix(Some(path)) => write!(f, "unix:{}", path.display()),
+            #[cfg(unix)]
+            Self::Unix(None) => f.write_str("Unix domain socket"),
+        }
+    }
+}
+
+/// Readiness and liveness of the server, available in handlers
+/// as `&Health` ( or by `req.context.health()` in fangs ).
+///
+/// - `is_ready` : `false` once the server has started shutting down
+/// - `is_alive` : `false` once the connections are being force-closed
+///   after `shutdown_timeout` of `Config`
+///
+/// For a `Request` not served by `howl` ( like in `ohkami::testing` ),
+/// both are always `true`.
+///
+/// ## Example
+///
+/// ```
+/// use ohkami::{Ohkami, Route, Health, Status};
+///
+/// async fn healthz(health: &Health) -> Status {
+///     if health.is_ready() {
+///         Status::OK
+///     } else {
+///         Status::ServiceUnavailable
+///     }
+/// }
+///
+/// # fn __() -> Ohkami {
+/// Ohkami::new((
+///     "/healthz".GET(healthz),
+/// ))
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct Health(Option<Shutdown>);
+
+impl Health {
+    pub(crate) const fn always() -> Self {
+        Self(None)
+    }
+
+    pub(crate) fn of(shutdown: &Shutdown) -> Self {
+        Self(Some(shutdown.clone()))
+    }
+
+    /// `true` while the server accepts new requests
+    pub fn is_ready(&self) -> bool {
+        self.0.as_ref().is_none_or(|s| !s.is_draining())
+    }
+
+    /// `true` until the server force-closes the connections
+    pub fn is_alive(&self) -> bool {
+        self.0.as_ref().is_none_or(|s| !s.is_closing())
+    }
+}
+
+impl<'req> FromRequest<'req> for &'req ListenAddr {
+    type Error = std::convert::Infallible;
+
+    #[inline(always)]
+    fn from_request(req: &'req Request) -> Option<Result<Self, Self::Error>> {
+        req.context.listen_addr().map(Ok)
+    }
+}
+
+impl<'req> FromRequest<'req> for &'req Health {
+    type Error = std::convert::Infallible;
+
+    #[inline(always)]
+    fn from_request(req: &'req Request) -> Option<Result<Self, Self::Error>> {
+        Some(Ok(req.context.health()))
+    }
+}
+
+#[cfg(feature = "__rt_threaded__")]
+type HookFuture = Pin<Box<dyn Future<Output = ()> + Send>>;
+#[cfg(not(feature = "__rt_threaded__"))]
+type HookFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+trait Hook<Arg>: SendSyncOnThreaded {
+    fn call(self: Box<Self>, arg: Arg) -> HookFuture;
+}
+impl<Arg, F, Fut> Hook<Arg> for F
+where
+    F: FnOnce(Arg) -> Fut + SendSyncOnThreaded,
+    Fut: Future<Output = ()> + SendOnThreaded + 'static,
+{
+    fn call(self: Box<Self>, arg: Arg) -> HookFuture {
+        Box::pin(self(arg))
+    }
+}
+
+/// async hooks run around serving by `howl`
+#[derive(Default)]
+pub(crate) struct Hooks {
+    on_start: Vec<Box<dyn Hook<Vec<ListenAddr>>>>,
+    on_shutdown: Vec<Box<dyn Hook<()>>>,
+}
+
+impl Hooks {
+    pub(crate) fn is_empty(&self) -> bool {
+        self.on_start.is_empty() && self.on_shutdown.is_empty()
+    }
+
+    pub(crate) fn on_start<Fut>(
+        &mut self,
+        hook: impl FnOnce(Vec<ListenAddr>) -> Fut + SendSyncOnThreaded + 'static,
+    ) where
+        Fut: Future<Output = ()> + SendOnThreaded + 'static,
+    {
+        self.on_start.push(Box::new(hook));
+    }
+
+    pub(crate) fn on_shutdown<Fut>(
+        &mut self,
+        hook: impl FnOnce() -> Fut + SendSyncOnThreaded + 'static,
+    ) where
+        Fut: Future<Output = ()> + SendOnThreaded + 'static,
+    {
+        self.on_shutdown.push(Box::new(move |()| hook()));
+    }
+
+    pub(crate) async fn start(&mut self, addrs: Vec<ListenAddr>) {
+        for hook in self.on_start.drain(..) {
+            hook.call(addrs.clone()).await;
+        }
+    }
+
+    pub(crate) async fn shutdown(&mut self) {
+        for hook in self.on_shutdown.drain(..) {
+            hook.call(()).await;
+        }
+    }
+}
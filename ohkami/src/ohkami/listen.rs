@@ -0,0 +1,107 @@
+#![cfg(feature = "__rt_native__")]
+
+use super::ListenAddr;
+use crate::__rt__;
+use std::{future::Future, pin::Pin, sync::Arc};
+
+/// A listener with its own `Config` ( and TLS config ) to serve an `Ohkami`
+/// on with [`howl_multi`](crate::Ohkami::howl_multi).
+///
+/// ## Example
+///
+/// ```no_run
+/// use ohkami::{Ohkami, Route, Listen, Config};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     Ohkami::new((
+///         "/".GET(async || "Hello, ohkami!"),
+///     )).howl_multi([
+///         Listen::new("0.0.0.0:8080"),
+///         Listen::new("127.0.0.1:9090").config(Config {
+///             keepalive_timeout: 5,
+///             ..Config::new()
+///         }),
+///     ]).await
+/// }
+/// ```
+pub struct Listen {
+    listener: Bind,
+    config: crate::Config,
+    #[cfg(feature = "tls")]
+    tls_config: Option<rustls::ServerConfig>,
+}
+
+impl Listen {
+    /// `bind` is the same as the one of [`howl`](crate::Ohkami::howl), and
+    /// it's bound when `howl_multi` starts.
+    pub fn new<T: 'static>(bind: impl __rt__::IntoListener<T> + 'static) -> Self {
+        Self {
+            listener: Box::new(|config| Box::pin(async move { bind.into_listener(&config).await })),
+            config: crate::Config::new(),
+            #[cfg(feature = "tls")]
+            tls_config: None,
+        }
+    }
+
+    /// Use the given `Config` instead of the [default one](crate::Config::new)
+    /// for this listener and the connections on it.
+    pub fn config(mut self, config: crate::Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Serve with TLS on this listener ( see [`howls`](crate::Ohkami::howls) ).
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    pub fn tls(mut self, tls_config: rustls::ServerConfig) -> Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    pub(crate) async fn bind(self) -> Bound {
+        Bound::new(
+            (self.listener)(self.config).await,
+            self.config,
+            #[cfg(feature = "tls")]
+            self.tls_config,
+        )
+    }
+}
+
+/// binding a listener with the `Config` given later
+type Bind = Box<dyn FnOnce(crate::Config) -> Pin<Box<dyn Future<Output = __rt__::Listener>>>>;
+
+/// A bound listener to accept connections on
+pub(crate) struct Bound {
+    pub(crate) listener: __rt__::Listener,
+    pub(crate) addr: Arc<ListenAddr>,
+    pub(crate) config: crate::Config,
+    #[cfg(feature = "tls")]
+    pub(crate) tls_acceptor: Option<anysc_rustls::TlsAcceptor>,
+}
+
+impl Bound {
+    pub(crate) fn new(
+        listener: __rt__::Listener,
+        config: crate::Config,
+        #[cfg(feature = "tls")] tls_config: Option<rustls::ServerConfig>,
+    ) -> Self {
+        #[cfg(all(feature = "tls", unix, not(feature = "rt_nio")))]
+        if tls_config.is_some() && matches!(listener, __rt__::Listener::Unix(_)) {
+            panic!("TLS over Unix domain socket is not supported")
+        }
+
+        let addr = listener
+            .local_addr()
+            .expect("Failed to get local address of listener");
+
+        Self {
+            listener,
+            addr: Arc::new(addr),
+            config,
+            #[cfg(feature = "tls")]
+            tls_acceptor: tls_config.map(super::tls_acceptor),
+        }
+    }
+}
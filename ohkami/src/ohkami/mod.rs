@@ -7,6 +7,26 @@ mod _test;
 #[cfg(feature = "__rt_native__")]
 mod dir;
 
+mod unix;
+#[cfg(all(unix, feature = "__rt_native__", not(feature = "rt_nio")))]
+pub use unix::UnixSocket;
+
+mod lifecycle;
+#[cfg(feature = "__rt_native__")]
+pub use lifecycle::{Health, ListenAddr};
+
+mod listen;
+#[cfg(feature = "__rt_native__")]
+pub use listen::Listen;
+
+mod tls;
+#[cfg(all(feature = "__rt_native__", feature = "tls"))]
+pub use tls::TlsCerts;
+
+mod per_core;
+#[cfg(all(feature = "__rt_native__", not(feature = "__rt_threaded__")))]
+pub use per_core::PerCore;
+
 pub(crate) mod routing;
 pub use routing::{Route, Routing};
 
@@ -17,6 +37,17 @@ use std::sync::Arc;
 #[cfg(feature = "__rt_native__")]
 use crate::{__rt__, session};
 
+#[cfg(feature = "__rt_native__")]
+#[cfg(feature = "tls")]
+#[cfg_attr(not(feature = "http2"), allow(unused_mut))]
+fn tls_acceptor(mut tls_config: rustls::ServerConfig) -> anysc_rustls::TlsAcceptor {
+    #[cfg(feature = "http2")]
+    if tls_config.alpn_protocols.is_empty() {
+        tls_config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    }
+    anysc_rustls::TlsAcceptor::from(Arc::new(tls_config))
+}
+
 /// # Ohkami - a smart wolf who serves your web app
 ///
 /// ## Definition
@@ -353,6 +384,8 @@ pub struct Ohkami {
     router: Router,
     /// apply just before merged to another, or just before `howl`ing
     fangs: Option<Arc<dyn Fangs>>,
+    #[cfg(feature = "__rt_native__")]
+    hooks: lifecycle::Hooks,
 }
 
 impl Ohkami {
@@ -468,6 +501,8 @@ impl Ohkami {
         let mut this = Self {
             router: Router::new(),
             fangs: None,
+            #[cfg(feature = "__rt_native__")]
+            hooks: lifecycle::Hooks::default(),
         };
         crate::DEBUG!(
             "[Ohkami::new] Fangs for Router({:?}) is `{}`",
@@ -485,13 +520,79 @@ impl Ohkami {
         let mut this = Self {
             router: Router::new(),
             fangs: Some(Arc::new(fangs)),
+            #[cfg(feature = "__rt_native__")]
+            hooks: lifecycle::Hooks::default(),
         };
         routes.apply(&mut this);
         this
     }
 
+    /// Add an async hook run once the listener(s) are bound, just before
+    /// accepting connections, with the addresses of them.
+    /// Multiple hooks are run in the added order.
+    ///
+    /// This is useful to log the actual address when binding port `0`,
+    /// or to warm up caches before serving.
+    ///
+    /// *note* : Only the hooks of the `Ohkami` to `howl` are run, not ones of
+    /// the `Ohkami`s nested by `By`.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use ohkami::{Ohkami, Route, ListenAddr};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     Ohkami::new((
+    ///         "/".GET(async || "Hello, ohkami!"),
+    ///     ))
+    ///     .on_start(|addrs: Vec<ListenAddr>| async move {
+    ///         println!("listening on {}", addrs[0]);
+    ///     })
+    ///     .howl("localhost:0").await
+    /// }
+    /// ```
+    #[cfg(feature = "__rt_native__")]
+    pub fn on_start<Fut>(
+        mut self,
+        hook: impl FnOnce(Vec<ListenAddr>) -> Fut + crate::fang::bound::SendSyncOnThreaded + 'static,
+    ) -> Self
+    where
+        Fut: Future<Output = ()> + crate::fang::bound::SendOnThreaded + 'static,
+    {
+        self.hooks.on_start(hook);
+        self
+    }
+
+    /// Add an async hook run after all connections are closed in the shutdown,
+    /// just before `howl` returns. Multiple hooks are run in the added order.
+    ///
+    /// *note* : Only the hooks of the `Ohkami` to `howl` are run, not ones of
+    /// the `Ohkami`s nested by `By`.
+    #[cfg(feature = "__rt_native__")]
+    pub fn on_shutdown<Fut>(
+        mut self,
+        hook: impl FnOnce() -> Fut + crate::fang::bound::SendSyncOnThreaded + 'static,
+    ) -> Self
+    where
+        Fut: Future<Output = ()> + crate::fang::bound::SendOnThreaded + 'static,
+    {
+        self.hooks.on_shutdown(hook);
+        self
+    }
+
     pub(crate) fn into_router(self) -> Router {
-        let Self { fangs, mut router } = self;
+        #[cfg(feature = "__rt_native__")]
+        if !self.hooks.is_empty() {
+            crate::WARNING!(
+                "`on_start` / `on_shutdown` hooks of an `Ohkami` nested by `By` are not run"
+            );
+        }
+
+        let Self {
+            fangs, mut router, ..
+        } = self;
 
         if let Some(fangs) = fangs {
             router.apply_fangs(router.id(), fangs);
@@ -505,14 +606,32 @@ impl Ohkami {
         router
     }
 
-    #[cfg(not(feature = "rt_nio"))]
     #[cfg(feature = "__rt_native__")]
     async fn howl_core<T>(
         self,
-        bind: impl __rt__::IntoTcpListener<T>,
+        bind: impl __rt__::IntoListener<T>,
         config: crate::Config,
         #[cfg(feature = "tls")] tls_config: Option<rustls::ServerConfig>,
+        shutdown_signal: impl Future<Output = ()>,
     ) {
+        let listener = listen::Bound::new(
+            bind.into_listener(&config).await,
+            config,
+            #[cfg(feature = "tls")]
+            tls_config,
+        );
+        self.howl_listeners(vec![listener], shutdown_signal).await
+    }
+
+    #[cfg(not(feature = "rt_nio"))]
+    #[cfg(feature = "__rt_native__")]
+    async fn howl_listeners(
+        mut self,
+        listeners: Vec<listen::Bound>,
+        shutdown_signal: impl Future<Output = ()>,
+    ) {
+        let mut hooks = std::mem::take(&mut self.hooks);
+
         let (router, _) = self.into_router().finalize();
         #[cfg_attr(
             not(feature = "__rt_threaded__"),
@@ -520,48 +639,73 @@ impl Ohkami {
         )]
         let router = Arc::new(router);
 
-        let listener = bind.into_tcp_listener().await;
         let (wg, ctrl_c) = (sync::WaitGroup::new(), sync::CtrlC::new());
+        let shutdown = sync::Shutdown::new();
 
-        #[cfg(feature = "tls")]
-        let tls_acceptor = tls_config.map(|it| anysc_rustls::TlsAcceptor::from(Arc::new(it)));
+        let shutdown_timeout = listeners
+            .iter()
+            .map(|l| l.config.shutdown_timeout)
+            .max()
+            .expect("No listener to serve on");
 
-        crate::INFO!("start serving on {}", listener.local_addr().unwrap());
-        while let Some(accept) = ctrl_c.until_interrupt(__rt__::accept(&listener)).await {
-            let Ok((connection, address)) = accept else {
-                continue;
-            };
+        hooks
+            .start(listeners.iter().map(|l| (*l.addr).clone()).collect())
+            .await;
 
-            #[cfg(feature = "tls")]
-            let connection: session::Connection = match &tls_acceptor {
-                None => connection.into(),
-                Some(tls_acceptor) => match ctrl_c
-                    .until_interrupt(tls_acceptor.accept(connection))
-                    .await
-                {
-                    None => break,
-                    Some(Ok(tls_stream)) => tls_stream.into(),
-                    Some(Err(e)) => {
-                        crate::ERROR!("TLS accept error: {e}");
-                        continue;
-                    }
-                },
+        let serving = listeners.into_iter().map(|listener| {
+            crate::INFO!("start serving on {}", listener.addr);
+            Self::accept_on(listener, &router, &wg, &shutdown)
+        });
+
+        // Ctrl-C, SIGTERM or `shutdown_signal`
+        let stop = ctrl_c.until_interrupt(shutdown_signal);
+        sync::until(stop, sync::join_all(serving)).await;
+
+        sync::drain(&shutdown, wg, shutdown_timeout).await;
+
+        hooks.shutdown().await;
+    }
+
+    /// Accept connections on `listener` and spawn a session for each of them,
+    /// until this future is dropped ( with the listener ) for shutdown
+    #[cfg(not(feature = "rt_nio"))]
+    #[cfg(feature = "__rt_native__")]
+    async fn accept_on(
+        listener: listen::Bound,
+        router: &Arc<crate::router::r#final::Router>,
+        wg: &sync::WaitGroup,
+        shutdown: &sync::Shutdown,
+    ) {
+        let limit = sync::ConnectionLimit::new(listener.config.max_connections);
+        loop {
+            let permit = limit.acquire(&listener.addr).await;
+            let Ok((connection, ip, local_addr)) = listener.listener.accept(&listener.config).await
+            else {
+                continue;
             };
 
-            let session = session::Session::new(config, connection, address.ip(), router.clone());
+            // PROXY protocol header and TLS handshake are read in the
+            // spawned task not to block accepting other connections
+            let accept = session::Session::accept(
+                listener.config,
+                connection,
+                ip,
+                session::ConnectionInfo::new(listener.addr.clone(), local_addr),
+                #[cfg(feature = "tls")]
+                listener.tls_acceptor.clone(),
+                router.clone(),
+                shutdown.clone(),
+            );
 
             let wg = wg.add();
             __rt__::spawn(async move {
-                session.manage().await;
+                if let Some(session) = accept.await {
+                    session.manage().await;
+                }
                 wg.done();
+                drop(permit);
             });
         }
-
-        crate::INFO!("interrupted, trying graceful shutdown...");
-        drop(listener);
-
-        crate::INFO!("waiting {} session(s) to finish...", wg.count());
-        wg.await;
     }
 
     /// Bind this `Ohkami` to an address and start serving !
@@ -575,10 +719,24 @@ impl Ohkami {
     ///
     /// depending on the async runtime.
     ///
+    /// On Unix, `bind` can also be a [`UnixSocket`](crate::UnixSocket) or
+    /// `UnixListener` of the async runtime ( except for `nio` ) to serve over
+    /// Unix domain socket.
+    ///
+    /// To serve on multiple listeners at once, see [`howl_multi`](crate::Ohkami::howl_multi).
+    ///
     /// *note* : Keep-Alive timeout is 39 seconds by default.
     /// This can be configured by `OHKAMI_KEEPALIVE_TIMEOUT`
     /// environment variable.
     ///
+    /// *note* : With `http2` feature, HTTP/2 over cleartext TCP (h2c) is also
+    /// served for clients starting with the connection preface
+    /// ( "prior knowledge" ).
+    ///
+    /// *note* : Socket options like `SO_REUSEPORT` or listen backlog for a
+    /// listener bound from an address, and `TCP_NODELAY` or TCP keepalive for
+    /// the connections, can be set by `Config` ( see [`howl_with`](crate::Ohkami::howl_with) ).
+    ///
     /// ## Examples
     ///
     /// ---
@@ -628,12 +786,13 @@ impl Ohkami {
     /// }
     /// ```
     #[cfg(feature = "__rt_native__")]
-    pub async fn howl<T>(self, bind: impl __rt__::IntoTcpListener<T>) {
+    pub async fn howl<T>(self, bind: impl __rt__::IntoListener<T>) {
         self.howl_core(
             bind,
             crate::Config::new(),
             #[cfg(feature = "tls")]
             None,
+            std::future::pending(),
         )
         .await
     }
@@ -641,12 +800,75 @@ impl Ohkami {
     /// Same as [`howl`](crate::Ohkami::howl) but uses the given `Config`
     /// instead of the [default one](crate::Config::new).
     #[cfg(feature = "__rt_native__")]
-    pub async fn howl_with<T>(self, config: crate::Config, bind: impl __rt__::IntoTcpListener<T>) {
+    pub async fn howl_with<T>(self, config: crate::Config, bind: impl __rt__::IntoListener<T>) {
+        self.howl_core(
+            bind,
+            config,
+            #[cfg(feature = "tls")]
+            None,
+            std::future::pending(),
+        )
+        .await
+    }
+
+    /// Same as [`howl`](crate::Ohkami::howl), but also shuts down gracefully
+    /// when `shutdown` completes.
+    ///
+    /// Ohkami always shuts down gracefully on Ctrl-C ( SIGINT ) or SIGTERM :
+    ///
+    /// 1. stops accepting new connections
+    /// 2. closes idle keep-alive connections right away
+    /// 3. responds to in-flight requests with `Connection: close`
+    /// 4. force-closes the connections still open after `shutdown_timeout` of `Config`
+    ///
+    /// Other signals like SIGHUP don't, unless `shutdown` waits for them.
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use ohkami::{Ohkami, Route};
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     // serve for an hour
+    ///     let shutdown = tokio::time::sleep(std::time::Duration::from_secs(60 * 60));
+    ///
+    ///     Ohkami::new((
+    ///         "/".GET(async || "Hello, ohkami!"),
+    ///     )).howl_until("localhost:5000", shutdown).await
+    /// }
+    /// ```
+    #[cfg(feature = "__rt_native__")]
+    pub async fn howl_until<T>(
+        self,
+        bind: impl __rt__::IntoListener<T>,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        self.howl_core(
+            bind,
+            crate::Config::new(),
+            #[cfg(feature = "tls")]
+            None,
+            shutdown,
+        )
+        .await
+    }
+
+    /// Same as [`howl_until`](crate::Ohkami::howl_until) but uses the given `Config`
+    /// instead of the [default one](crate::Config::new).
+    #[cfg(feature = "__rt_native__")]
+    pub async fn howl_with_until<T>(
+        self,
+        config: crate::Config,
+        bind: impl __rt__::IntoListener<T>,
+        shutdown: impl Future<Output = ()>,
+    ) {
         self.howl_core(
             bind,
             config,
             #[cfg(feature = "tls")]
             None,
+            shutdown,
         )
         .await
     }
@@ -731,16 +953,28 @@ impl Ohkami {
     ///
     /// For localhost-testing with browser (or `curl` without `--insecure`),
     /// [`mkcert`](https://github.com/FiloSottile/mkcert) is highly recommended.
+    ///
+    /// To renew certificates without restarting, or to select one by SNI hostname,
+    /// build `tls_config` with [`TlsCerts`](crate::TlsCerts) as the certificate resolver.
+    ///
+    /// With `http2` feature, HTTP/2 is also served when negotiated by ALPN.
+    /// If `alpn_protocols` of `tls_config` is empty, `h2` and `http/1.1` are
+    /// offered in this order.
     #[cfg(feature = "__rt_native__")]
     #[cfg(feature = "tls")]
     #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
     pub async fn howls<T>(
         self,
-        bind: impl __rt__::IntoTcpListener<T>,
+        bind: impl __rt__::IntoListener<T>,
         tls_config: rustls::ServerConfig,
     ) {
-        self.howl_core(bind, crate::Config::new(), Some(tls_config))
-            .await
+        self.howl_core(
+            bind,
+            crate::Config::new(),
+            Some(tls_config),
+            std::future::pending(),
+        )
+        .await
     }
 
     /// Same as [`howls`](crate::Ohkami::howls) but uses the given `Config`
@@ -751,10 +985,95 @@ impl Ohkami {
     pub async fn howls_with<T>(
         self,
         config: crate::Config,
-        bind: impl __rt__::IntoTcpListener<T>,
+        bind: impl __rt__::IntoListener<T>,
+        tls_config: rustls::ServerConfig,
+    ) {
+        self.howl_core(bind, config, Some(tls_config), std::future::pending())
+            .await
+    }
+
+    /// Same as [`howls`](crate::Ohkami::howls), but also shuts down gracefully
+    /// when `shutdown` completes ( see [`howl_until`](crate::Ohkami::howl_until) ).
+    #[cfg(feature = "__rt_native__")]
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    pub async fn howls_until<T>(
+        self,
+        bind: impl __rt__::IntoListener<T>,
         tls_config: rustls::ServerConfig,
+        shutdown: impl Future<Output = ()>,
     ) {
-        self.howl_core(bind, config, Some(tls_config)).await
+        self.howl_core(bind, crate::Config::new(), Some(tls_config), shutdown)
+            .await
+    }
+
+    /// Same as [`howls_until`](crate::Ohkami::howls_until) but uses the given `Config`
+    /// instead the [default one](crate::Config::new).
+    #[cfg(feature = "__rt_native__")]
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    pub async fn howls_with_until<T>(
+        self,
+        config: crate::Config,
+        bind: impl __rt__::IntoListener<T>,
+        tls_config: rustls::ServerConfig,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        self.howl_core(bind, config, Some(tls_config), shutdown)
+            .await
+    }
+
+    /// Serve this `Ohkami` on all the listeners at once, sharing one router
+    /// and one graceful shutdown among them.
+    ///
+    /// Each [`Listen`](crate::Listen) has its own `Config` and optional TLS
+    /// config. The graceful shutdown waits for the longest `shutdown_timeout`
+    /// of them.
+    ///
+    /// Handlers can see which listener a request came in on by
+    /// [`ListenAddr`](crate::ListenAddr).
+    ///
+    /// ## Example
+    ///
+    /// ```no_run
+    /// use ohkami::{Ohkami, Route, Listen, ListenAddr};
+    ///
+    /// async fn hello(addr: &ListenAddr) -> String {
+    ///     format!("Hello from {addr}!")
+    /// }
+    ///
+    /// #[tokio::main]
+    /// async fn main() {
+    ///     Ohkami::new((
+    ///         "/".GET(hello),
+    ///     )).howl_multi([
+    ///         Listen::new("0.0.0.0:8080"),
+    ///         Listen::new("127.0.0.1:9090"),
+    ///     ]).await
+    /// }
+    /// ```
+    ///
+    /// ## Panics
+    ///
+    /// When `listens` is empty.
+    #[cfg(feature = "__rt_native__")]
+    pub async fn howl_multi(self, listens: impl IntoIterator<Item = Listen>) {
+        self.howl_multi_until(listens, std::future::pending()).await
+    }
+
+    /// Same as [`howl_multi`](crate::Ohkami::howl_multi), but also shuts down
+    /// gracefully when `shutdown` completes ( see [`howl_until`](crate::Ohkami::howl_until) ).
+    #[cfg(feature = "__rt_native__")]
+    pub async fn howl_multi_until(
+        self,
+        listens: impl IntoIterator<Item = Listen>,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        let mut listeners = Vec::new();
+        for listen in listens {
+            listeners.push(listen.bind().await);
+        }
+        self.howl_listeners(listeners, shutdown).await
     }
 
     #[cfg(feature = "rt_worker")]
@@ -869,6 +1188,8 @@ impl Ohkami {
         let (router, routes) = (Self {
             router: self.router.to_dummy_owned_for_openapi(),
             fangs: self.fangs.clone(),
+            #[cfg(feature = "__rt_native__")]
+            hooks: lifecycle::Hooks::default(),
         })
         .into_router()
         .finalize();
@@ -899,7 +1220,7 @@ const _: () = {
         type Response = lambda_runtime::FunctionResponse<
             crate::x_lambda::LambdaResponse,
             std::pin::Pin<
-                Box<dyn ohkami_lib::Stream<Item = Result<String, std::convert::Infallible>> + Send>,
+                Box<dyn ohkami_lib::Stream<Item = Result<Vec<u8>, std::io::Error>> + Send>,
             >,
         >;
         type Error = lambda_runtime::Error;
@@ -957,64 +1278,317 @@ const _: () = {
 };
 
 #[cfg(feature = "__rt_native__")]
-mod sync {
-    pub struct WaitGroup(std::ptr::NonNull<std::sync::atomic::AtomicUsize>);
+pub(crate) mod sync {
+    use std::future::Future;
+
+    /// Run `task` until `stop` completes, then `None`
+    pub async fn until<T>(stop: impl Future, task: impl Future<Output = T>) -> Option<T> {
+        use std::pin::pin;
+        use std::task::Poll;
+
+        let (mut stop, mut task) = (pin!(stop), pin!(task));
+        std::future::poll_fn(|cx| match task.as_mut().poll(cx) {
+            Poll::Ready(t) => Poll::Ready(Some(t)),
+            Poll::Pending => stop.as_mut().poll(cx).map(|_| None),
+        })
+        .await
+    }
+
+    /// Run all `tasks` concurrently until all of them complete
+    pub async fn join_all<F: Future<Output = ()>>(tasks: impl IntoIterator<Item = F>) {
+        use std::task::Poll;
+
+        let mut tasks = tasks
+            .into_iter()
+            .map(|task| Some(Box::pin(task)))
+            .collect::<Vec<_>>();
+        std::future::poll_fn(|cx| {
+            for slot in &mut tasks {
+                if let Some(task) = slot
+                    && task.as_mut().poll(cx).is_ready()
+                {
+                    *slot = None;
+                }
+            }
+            match tasks.iter().all(Option::is_none) {
+                true => Poll::Ready(()),
+                false => Poll::Pending,
+            }
+        })
+        .await
+    }
+
+    /// Shutdown phase of a server, shared with its sessions
+    #[derive(Clone)]
+    pub struct Shutdown(std::sync::Arc<ShutdownState>);
+
+    pub struct ShutdownState {
+        phase: std::sync::atomic::AtomicU8,
+        /// wakers of the tasks waiting for a phase, keyed by `Phase::key`
+        wakers: std::sync::Mutex<(usize, std::collections::HashMap<usize, std::task::Waker>)>,
+    }
+
+    /// Future resolving when the server has reached the phase
+    pub struct Phase {
+        state: std::sync::Arc<ShutdownState>,
+        phase: u8,
+        key: Option<usize>,
+    }
+
     const _: () = {
-        use std::future::Future;
         use std::pin::Pin;
-        use std::ptr::NonNull;
-        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::atomic::Ordering;
         use std::task::{Context, Poll};
 
-        unsafe impl Send for WaitGroup {}
-        unsafe impl Sync for WaitGroup {}
+        const RUNNING: u8 = 0;
+        /// no more connections are accepted, and idle connections are closed
+        const DRAINING: u8 = 1;
+        /// the drain deadline has passed, and all connections are closed
+        const CLOSING: u8 = 2;
+
+        impl Shutdown {
+            pub fn new() -> Self {
+                Self(std::sync::Arc::new(ShutdownState {
+                    phase: RUNNING.into(),
+                    wakers: Default::default(),
+                }))
+            }
+
+            pub fn drain(&self) {
+                self.advance(DRAINING);
+            }
+            pub fn close(&self) {
+                self.advance(CLOSING);
+            }
+
+            pub fn is_draining(&self) -> bool {
+                self.0.phase.load(Ordering::Acquire) >= DRAINING
+            }
+            pub fn is_closing(&self) -> bool {
+                self.0.phase.load(Ordering::Acquire) >= CLOSING
+            }
+
+            pub fn draining(&self) -> Phase {
+                Phase {
+                    state: self.0.clone(),
+                    phase: DRAINING,
+                    key: None,
+                }
+            }
+            pub fn closing(&self) -> Phase {
+                Phase {
+                    state: self.0.clone(),
+                    phase: CLOSING,
+                    key: None,
+                }
+            }
+
+            fn advance(&self, phase: u8) {
+                self.0.phase.fetch_max(phase, Ordering::AcqRel);
+                let (_, wakers) = &mut *self.0.wakers.lock().unwrap();
+                for (_, waker) in wakers.drain() {
+                    waker.wake();
+                }
+            }
+        }
+
+        impl Future for Phase {
+            type Output = ();
+
+            fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+                if self.state.phase.load(Ordering::Acquire) >= self.phase {
+                    return Poll::Ready(());
+                }
+
+                {
+                    let Phase { state, key, .. } = &mut *self;
+                    let (next_key, wakers) = &mut *state.wakers.lock().unwrap();
+                    let key = *key.get_or_insert_with(|| {
+                        *next_key += 1;
+                        *next_key
+                    });
+                    wakers.insert(key, cx.waker().clone());
+                }
+
+                // the phase may have advanced before the waker is registered
+                if self.state.phase.load(Ordering::Acquire) >= self.phase {
+                    Poll::Ready(())
+                } else {
+                    Poll::Pending
+                }
+            }
+        }
+
+        impl Drop for Phase {
+            fn drop(&mut self) {
+                if let Some(key) = self.key {
+                    self.state.wakers.lock().unwrap().1.remove(&key);
+                }
+            }
+        }
+    };
+
+    /// Stop accepting and wait for the sessions to finish, closing idle ones
+    /// right away and force-closing the rest after `shutdown_timeout`
+    pub async fn drain(shutdown: &Shutdown, wg: WaitGroup, shutdown_timeout: u64) {
+        crate::INFO!("shutting down, trying graceful shutdown...");
+        shutdown.drain();
+
+        crate::INFO!("waiting {} session(s) to finish...", wg.count());
+        let timeout = std::time::Duration::from_secs(shutdown_timeout);
+        if crate::util::with_timeout(timeout, wg.wait())
+            .await
+            .is_none()
+        {
+            crate::WARNING!(
+                "\
+                {} session(s) didn't finish within shutdown timeout ({} secs), force-closing. \
+                The timeout can be configured via `shutdown_timeout` of `Config`, \
+                or `OHKAMI_SHUTDOWN_TIMEOUT` environment variable \
+                (default: {}).\
+            ",
+                wg.count(),
+                shutdown_timeout,
+                crate::Config::default().shutdown_timeout
+            );
+            shutdown.close();
+            wg.wait().await;
+        }
+    }
+
+    pub struct WaitGroup(std::sync::Arc<WaitState>);
+
+    pub struct WaitState {
+        count: std::sync::atomic::AtomicUsize,
+        /// waker of the task waiting for all to be done
+        waker: std::sync::Mutex<Option<std::task::Waker>>,
+    }
+
+    const _: () = {
+        use std::sync::atomic::Ordering;
+        use std::task::Poll;
 
         impl WaitGroup {
             pub fn new() -> Self {
-                let n = AtomicUsize::new(0);
-                let n = Box::leak(Box::new(n));
-                Self(NonNull::new(n).unwrap())
+                Self(std::sync::Arc::new(WaitState {
+                    count: 0.into(),
+                    waker: Default::default(),
+                }))
             }
 
             pub fn count(&self) -> usize {
-                unsafe { self.0.as_ref() }.load(Ordering::Relaxed)
+                self.0.count.load(Ordering::Relaxed)
             }
 
             #[inline]
             pub fn add(&self) -> Self {
-                let ptr = self.0;
-                unsafe { ptr.as_ref() }.fetch_add(1, Ordering::Relaxed);
-                Self(ptr)
+                self.0.count.fetch_add(1, Ordering::Relaxed);
+                Self(self.0.clone())
             }
 
             pub fn done(self) {
                 /* just drop */
             }
+
+            /// Wait until all the added ones are done, woken by the last one
+            pub async fn wait(&self) {
+                std::future::poll_fn(|cx| {
+                    if self.0.count.load(Ordering::Acquire) == 0 {
+                        return Poll::Ready(());
+                    }
+                    *self.0.waker.lock().unwrap() = Some(cx.waker().clone());
+                    // the last one may have been done before the waker is registered
+                    if self.0.count.load(Ordering::Acquire) == 0 {
+                        cx.waker().wake_by_ref();
+                    }
+                    Poll::Pending
+                })
+                .await;
+                crate::DEBUG!("[WaitGroup::wait] Ready");
+            }
         }
 
         impl Drop for WaitGroup {
             #[inline]
             fn drop(&mut self) {
-                unsafe { self.0.as_ref() }.fetch_sub(1, Ordering::Release);
+                if self.0.count.fetch_sub(1, Ordering::AcqRel) == 1
+                    && let Some(waker) = self.0.waker.lock().unwrap().take()
+                {
+                    waker.wake();
+                }
             }
         }
+    };
 
-        impl Future for WaitGroup {
-            type Output = ();
+    /// Limit of concurrent connections on a listener by `max_connections`
+    /// of `Config`, awaited by its accept loop ( the only one acquiring )
+    pub struct ConnectionLimit(std::sync::Arc<LimitState>);
 
-            #[inline(always)]
-            fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-                if unsafe { self.0.as_ref() }.load(Ordering::Acquire) == 0 {
-                    crate::DEBUG!("[WaitGroup::poll] Ready");
-                    Poll::Ready(())
-                } else {
-                    cx.waker().wake_by_ref();
+    pub struct LimitState {
+        max: usize,
+        count: std::sync::atomic::AtomicUsize,
+        /// waker of the accept loop waiting for a connection to close
+        waker: std::sync::Mutex<Option<std::task::Waker>>,
+    }
+
+    /// A slot of `ConnectionLimit`, released on drop
+    pub struct ConnectionPermit(std::sync::Arc<LimitState>);
+
+    const _: () = {
+        use std::sync::atomic::Ordering;
+        use std::task::Poll;
+
+        impl ConnectionLimit {
+            pub fn new(max: usize) -> Self {
+                Self(std::sync::Arc::new(LimitState {
+                    max,
+                    count: 0.into(),
+                    waker: Default::default(),
+                }))
+            }
+
+            /// Wait for a slot, stopping accepting ( and letting new connections
+            /// queue in the listen backlog ) while the limit is reached
+            pub async fn acquire(&self, listen_addr: &super::ListenAddr) -> ConnectionPermit {
+                if self.0.count.load(Ordering::Acquire) >= self.0.max {
+                    crate::WARNING!(
+                        "\
+                        [max_connections] Reached the limit of concurrent connections ({}) on {}, \
+                        new connections wait in the listen backlog. \
+                        The limit can be configured via `max_connections` of `Config`, \
+                        or `OHKAMI_MAX_CONNECTIONS` environment variable.\
+                    ",
+                        self.0.max,
+                        listen_addr
+                    );
+                }
+                std::future::poll_fn(|cx| {
+                    if self.0.count.load(Ordering::Acquire) < self.0.max {
+                        self.0.count.fetch_add(1, Ordering::AcqRel);
+                        return Poll::Ready(ConnectionPermit(self.0.clone()));
+                    }
+                    *self.0.waker.lock().unwrap() = Some(cx.waker().clone());
+                    // a permit may have been released before the waker is registered
+                    if self.0.count.load(Ordering::Acquire) < self.0.max {
+                        cx.waker().wake_by_ref();
+                    }
                     Poll::Pending
+                })
+                .await
+            }
+        }
+
+        impl Drop for ConnectionPermit {
+            fn drop(&mut self) {
+                self.0.count.fetch_sub(1, Ordering::AcqRel);
+                if let Some(waker) = self.0.waker.lock().unwrap().take() {
+                    waker.wake();
                 }
             }
         }
     };
 
+    /// Ctrl-C ( SIGINT ), and also SIGTERM on Unix
     pub struct CtrlC {
         index: usize,
     }
@@ -1030,6 +1604,19 @@ mod sync {
         static WAKERS: std::sync::RwLock<Vec<AtomicPtr<Waker>>> =
             std::sync::RwLock::new(Vec::new());
 
+        fn interrupt() {
+            INTERRUPTED.store(true, Ordering::SeqCst);
+
+            let wakers = WAKERS.read().unwrap();
+            crate::DEBUG!("CtrlC handler: Waiting for {} Ohkami(s)", wakers.len());
+            for w in &*wakers {
+                let w = w.swap(null_mut(), Ordering::SeqCst);
+                if !w.is_null() {
+                    (unsafe { Box::from_raw(w) }).wake();
+                }
+            }
+        }
+
         impl CtrlC {
             pub fn new() -> Self {
                 /*
@@ -1064,19 +1651,19 @@ mod sync {
                     together with the main thread in this handler. So we have to hold
                     all `Waker`s in `WAKERS` and wake each them.
                 */
-                ::ctrlc::set_handler(|| {
-                    INTERRUPTED.store(true, Ordering::SeqCst);
-
-                    let wakers = WAKERS.read().unwrap();
-                    crate::DEBUG!("CtrlC handler: Waiting for {} Ohkami(s)", wakers.len());
-                    for w in &*wakers {
-                        let w = w.swap(null_mut(), Ordering::SeqCst);
-                        if !w.is_null() {
-                            (unsafe { Box::from_raw(w) }).wake();
+                ::ctrlc::set_handler(interrupt).ok();
+
+                #[cfg(unix)]
+                {
+                    use signal_hook::{consts::SIGTERM, iterator::Signals};
+
+                    static SIGTERM_HANDLER: std::sync::Once = std::sync::Once::new();
+                    SIGTERM_HANDLER.call_once(|| {
+                        if let Ok(mut signals) = Signals::new([SIGTERM]) {
+                            std::thread::spawn(move || signals.forever().for_each(|_| interrupt()));
                         }
-                    }
-                })
-                .ok();
+                    });
+                }
 
                 let index = {
                     static WAKER_INDEX: AtomicUsize = AtomicUsize::new(0);
@@ -1153,6 +1740,415 @@ mod test {
         });
     }
 
+    #[test]
+    fn can_run_lifecycle_hooks() {
+        use crate::{Health, Status};
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+        use std::sync::atomic::{AtomicBool, Ordering};
+        use std::time::Duration;
+
+        static SHUTDOWN_HOOKED: AtomicBool = AtomicBool::new(false);
+
+        async fn healthz(health: &Health) -> Status {
+            if health.is_ready() {
+                Status::OK
+            } else {
+                Status::ServiceUnavailable
+            }
+        }
+
+        let (ready, draining) = __rt__::testing::serve(
+            Ohkami::new((
+                "/healthz".GET(healthz),
+                "/healthz/later".GET(async |health: &Health| {
+                    __rt__::sleep(Duration::from_millis(300)).await;
+                    healthz(health).await
+                }),
+            ))
+            .on_shutdown(|| async {
+                SHUTDOWN_HOOKED.store(true, Ordering::SeqCst);
+            }),
+            crate::Config::default(),
+            |addr, shutdown| {
+                assert_ne!(addr.port(), 0);
+
+                let request = |path: &str| {
+                    let mut stream = TcpStream::connect(addr).unwrap();
+                    stream
+                        .write_all(
+                            format!("GET {path} HTTP/1.1\r\nConnection: close\r\n\r\n").as_bytes(),
+                        )
+                        .unwrap();
+                    stream
+                };
+                let read = |mut stream: TcpStream| {
+                    let mut res = String::new();
+                    stream.read_to_string(&mut res).unwrap();
+                    res
+                };
+
+                let ready = read(request("/healthz"));
+
+                let draining = request("/healthz/later");
+                std::thread::sleep(Duration::from_millis(100));
+                assert!(!SHUTDOWN_HOOKED.load(Ordering::SeqCst));
+                shutdown.shutdown();
+
+                (ready, read(draining))
+            },
+        );
+
+        assert!(ready.starts_with("HTTP/1.1 200 OK\r\n"), "{ready}");
+        assert!(
+            draining.starts_with("HTTP/1.1 503 Service Unavailable\r\n"),
+            "{draining}"
+        );
+        assert!(SHUTDOWN_HOOKED.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn can_howl_multi() {
+        use crate::{Listen, ListenAddr};
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        let (res_a, res_b, a, b) = __rt__::testing::serve_multi(
+            Ohkami::new(("/addr".GET(async |addr: &ListenAddr| addr.to_string()),)),
+            [
+                Listen::new(("localhost", 0)),
+                Listen::new(("localhost", 0)).config(crate::Config {
+                    shutdown_timeout: 1,
+                    ..crate::Config::default()
+                }),
+            ],
+            |addrs, _| {
+                let [ListenAddr::Tcp(a), ListenAddr::Tcp(b)] = addrs[..] else {
+                    panic!("expected 2 TCP addresses: {addrs:?}")
+                };
+                assert_ne!(a, b);
+
+                let get = |addr| {
+                    let mut stream = TcpStream::connect(addr).unwrap();
+                    stream
+                        .write_all(b"GET /addr HTTP/1.1\r\nConnection: close\r\n\r\n")
+                        .unwrap();
+                    let mut res = String::new();
+                    stream.read_to_string(&mut res).unwrap();
+                    res
+                };
+                (get(a), get(b), a, b)
+            },
+        );
+
+        assert!(res_a.ends_with(&format!("\r\n\r\n{a}")), "{res_a}");
+        assert!(res_b.ends_with(&format!("\r\n\r\n{b}")), "{res_b}");
+    }
+
+    #[test]
+    fn can_limit_connections() {
+        use std::io::{ErrorKind, Read, Write};
+        use std::net::TcpStream;
+        use std::time::Duration;
+
+        let (waited, res) = __rt__::testing::serve(
+            Ohkami::new(("/".GET(async || "ok"),)),
+            crate::Config {
+                max_connections: 1,
+                shutdown_timeout: 1,
+                ..crate::Config::default()
+            },
+            |addr, _| {
+                let mut first = TcpStream::connect(addr).unwrap();
+                first.write_all(b"GET / HTTP/1.1\r\n\r\n").unwrap();
+                let mut buf = [0; 1024];
+                assert!(first.read(&mut buf).unwrap() > 0);
+
+                // waiting in the backlog while `first` is kept alive
+                let mut second = TcpStream::connect(addr).unwrap();
+                second
+                    .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+                second
+                    .set_read_timeout(Some(Duration::from_millis(300)))
+                    .unwrap();
+                let waited = second.read(&mut buf).map_err(|e| e.kind());
+
+                drop(first);
+                second.set_read_timeout(None).unwrap();
+                let mut res = String::new();
+                second.read_to_string(&mut res).unwrap();
+
+                (waited, res)
+            },
+        );
+
+        assert!(
+            matches!(waited, Err(ErrorKind::WouldBlock | ErrorKind::TimedOut)),
+            "{waited:?}"
+        );
+        assert!(res.ends_with("\r\n\r\nok"), "{res}");
+    }
+
+    #[test]
+    fn can_read_proxy_protocol_header() {
+        use crate::{ProxyHeader, Request};
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        async fn peer(req: &Request, proxy: &ProxyHeader) -> String {
+            format!("{} via {}", req.ip, proxy.destination().unwrap())
+        }
+
+        let (with_header, without_header) = __rt__::testing::serve(
+            Ohkami::new(("/".GET(peer),)),
+            crate::Config {
+                proxy_protocol: true,
+                shutdown_timeout: 1,
+                ..crate::Config::default()
+            },
+            |addr, _| {
+                let request = |bytes: &[u8]| {
+                    let mut stream = TcpStream::connect(addr).unwrap();
+                    stream.write_all(bytes).unwrap();
+                    let mut res = String::new();
+                    let _ = stream.read_to_string(&mut res);
+                    res
+                };
+
+                let with_header = request(
+                    b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n\
+                    GET / HTTP/1.1\r\nConnection: close\r\n\r\n",
+                );
+                let without_header = request(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n");
+                (with_header, without_header)
+            },
+        );
+
+        assert!(
+            with_header.ends_with("\r\n\r\n192.0.2.1 via 198.51.100.1:443"),
+            "{with_header}"
+        );
+        assert_eq!(without_header, "");
+    }
+
+    #[test]
+    fn can_see_connection_info() {
+        use crate::ConnectionInfo;
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        async fn connection(conn: &ConnectionInfo) -> String {
+            format!(
+                "{} on {} ( TLS: {} )",
+                conn.local_addr().unwrap(),
+                conn.listen_addr(),
+                conn.is_tls()
+            )
+        }
+
+        let (addr, res) = __rt__::testing::serve(
+            Ohkami::new(("/".GET(connection),)),
+            crate::Config {
+                shutdown_timeout: 1,
+                ..crate::Config::default()
+            },
+            |addr, _| {
+                let mut stream = TcpStream::connect(addr).unwrap();
+                stream
+                    .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+                let mut res = String::new();
+                stream.read_to_string(&mut res).unwrap();
+                (addr, res)
+            },
+        );
+
+        assert!(
+            res.ends_with(&format!("\r\n\r\n{addr} on {addr} ( TLS: false )")),
+            "{res}"
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn can_bind_with_socket_options() {
+        use std::io::{Read, Write};
+        use std::net::TcpStream;
+
+        let (shared, res) = __rt__::testing::serve(
+            Ohkami::new(("/".GET(async || "ok"),)),
+            crate::Config {
+                reuse_port: true,
+                listen_backlog: 16,
+                tcp_nodelay: true,
+                tcp_keepalive: 60,
+                tcp_keepalive_interval: 10,
+                shutdown_timeout: 1,
+                ..crate::Config::default()
+            },
+            |addr, _| {
+                // another listener can share the port by `SO_REUSEPORT`
+                let shared = {
+                    let socket = socket2::Socket::new(
+                        socket2::Domain::for_address(addr),
+                        socket2::Type::STREAM,
+                        None,
+                    )
+                    .unwrap();
+                    socket.set_reuse_port(true).unwrap();
+                    socket.bind(&addr.into())
+                };
+
+                let mut stream = TcpStream::connect(addr).unwrap();
+                stream
+                    .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+                let mut res = String::new();
+                stream.read_to_string(&mut res).unwrap();
+                (shared, res)
+            },
+        );
+
+        assert!(shared.is_ok(), "{shared:?}");
+        assert!(res.ends_with("\r\n\r\nok"), "{res}");
+    }
+
+    #[cfg(all(unix, not(feature = "rt_nio")))]
+    #[test]
+    fn can_howl_on_unix_socket() {
+        use std::io::{Read, Write};
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = std::env::temp_dir().join(format!("ohkami-test-{}.sock", std::process::id()));
+
+        // a stale socket file left by a dead server
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+
+        let client = std::thread::spawn({
+            let path = path.clone();
+            move || {
+                let mut stream = loop {
+                    match std::os::unix::net::UnixStream::connect(&path) {
+                        Ok(stream) => break stream,
+                        Err(_) => std::thread::sleep(std::time::Duration::from_millis(10)),
+                    }
+                };
+                stream
+                    .write_all(b"GET /ip HTTP/1.1\r\nConnection: close\r\n\r\n")
+                    .unwrap();
+                let mut res = String::new();
+                stream.read_to_string(&mut res).unwrap();
+
+                let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+                (mode, res)
+            }
+        });
+
+        __rt__::testing::block_on(async {
+            crate::util::with_timeout(
+                std::time::Duration::from_secs(3),
+                Ohkami::new(("/ip".GET(async |req: &crate::Request| req.ip.to_string()),))
+                    .howl(UnixSocket::new(&path).mode(0o600).remove_stale(true)),
+            )
+            .await
+        });
+
+        let (mode, res) = client.join().unwrap();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(res.starts_with("HTTP/1.1 200 OK\r\n"), "{res}");
+        assert!(res.ends_with("\r\n\r\n127.0.0.1"), "{res}");
+
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[cfg(all(unix, not(feature = "rt_nio")))]
+    #[test]
+    fn can_shutdown_gracefully() {
+        use crate::Listen;
+        use std::io::{Read, Write};
+        use std::os::unix::net::UnixStream;
+        use std::time::{Duration, Instant};
+
+        let path =
+            std::env::temp_dir().join(format!("ohkami-test-shutdown-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        fn connect(path: &std::path::Path, request: &str) -> UnixStream {
+            let mut stream = UnixStream::connect(path).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            stream
+        }
+        fn read_until_closed(mut stream: UnixStream) -> String {
+            let mut res = String::new();
+            stream.read_to_string(&mut res).unwrap();
+            res
+        }
+
+        let (idle, idle_closed, in_flight, stuck, all_closed) = __rt__::testing::serve_multi(
+            Ohkami::new((
+                "/hello".GET(async || "Hello!"),
+                "/slow".GET(async || {
+                    __rt__::sleep(Duration::from_millis(300)).await;
+                    "slow"
+                }),
+                "/stuck".GET(async || {
+                    __rt__::sleep(Duration::from_secs(60)).await;
+                    "stuck"
+                }),
+            )),
+            [Listen::new(UnixSocket::new(&path)).config(crate::Config {
+                shutdown_timeout: 1,
+                ..crate::Config::default()
+            })],
+            {
+                let path = path.clone();
+                move |_, shutdown| {
+                    // keep-alive connection left idle after a response
+                    let mut idle = connect(&path, "GET /hello HTTP/1.1\r\n\r\n");
+                    let mut buf = [0; 1024];
+                    let n = idle.read(&mut buf).unwrap();
+                    assert!(buf[..n].ends_with(b"Hello!"));
+
+                    let in_flight = connect(&path, "GET /slow HTTP/1.1\r\n\r\n");
+                    let stuck = connect(&path, "GET /stuck HTTP/1.1\r\n\r\n");
+                    std::thread::sleep(Duration::from_millis(100));
+
+                    let started = Instant::now();
+                    shutdown.shutdown();
+                    (
+                        read_until_closed(idle),
+                        started.elapsed(),
+                        read_until_closed(in_flight),
+                        read_until_closed(stuck),
+                        started.elapsed(),
+                    )
+                }
+            },
+        );
+
+        // the idle connection is closed right away
+        assert_eq!(idle, "");
+        assert!(idle_closed < Duration::from_millis(200), "{idle_closed:?}");
+
+        // the in-flight request is responded with `Connection: close`
+        assert!(in_flight.starts_with("HTTP/1.1 200 OK\r\n"), "{in_flight}");
+        assert!(
+            in_flight.contains("\r\nConnection: close\r\n"),
+            "{in_flight}"
+        );
+        assert!(in_flight.ends_with("\r\n\r\nslow"), "{in_flight}");
+
+        // the stuck one is force-closed after `shutdown_timeout`
+        assert_eq!(stuck, "");
+        assert!(
+            Duration::from_secs(1) <= all_closed && all_closed < Duration::from_secs(2),
+            "{all_closed:?}"
+        );
+
+        std::fs::remove_file(&path).ok();
+    }
+
     #[cfg(feature = "tls")]
     #[test]
     fn can_howl_with_tls_on_any_native_async_runtime() {
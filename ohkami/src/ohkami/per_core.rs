@@ -0,0 +1,341 @@
+#![cfg(all(feature = "__rt_native__", not(feature = "__rt_threaded__")))]
+
+use super::{Ohkami, listen, sync};
+use crate::__rt__;
+use std::{future::Future, sync::Arc};
+
+/// Serve an `Ohkami` on multiple cores with a thread-per-core runtime
+/// ( glommio, monoio, compio or nio ).
+///
+/// A single-threaded executor is started on each core, with its own listener
+/// sharing the port by `SO_REUSEPORT` and its own router built from the `Ohkami`
+/// given by the factory. Executors are pinned to the cores on glommio, monoio
+/// and compio. On nio, they're the workers of one runtime, which also distributes
+/// connections among them.
+///
+/// *note* : The kernel balances the connections among the listeners sharing
+/// the port only on Linux. On macOS and BSDs, `SO_REUSEPORT` doesn't distribute
+/// them, and most of the connections go to a single core.
+///
+/// Ctrl-C ( or SIGTERM ), or `shutdown` of [`howl_until`](PerCore::howl_until),
+/// shuts down all of them gracefully together.
+///
+/// ## Example
+///
+/// ```no_run
+/// use ohkami::{Ohkami, Route, PerCore};
+///
+/// fn ohkami() -> Ohkami {
+///     Ohkami::new((
+///         "/".GET(async || "Hello, world!"),
+///     ))
+/// }
+///
+/// fn main() {
+///     PerCore::new(ohkami).howl("0.0.0.0:8000")
+/// }
+/// ```
+pub struct PerCore<F> {
+    factory: F,
+    cores: usize,
+    config: crate::Config,
+    #[cfg(feature = "tls")]
+    tls_config: Option<rustls::ServerConfig>,
+}
+
+impl<F: Fn() -> Ohkami + Send + Sync + 'static> PerCore<F> {
+    /// `factory` is called on each core to build the `Ohkami` served there.
+    /// By default, all the cores available are used.
+    pub fn new(factory: F) -> Self {
+        Self {
+            factory,
+            cores: std::thread::available_parallelism().map_or(1, |n| n.get()),
+            config: crate::Config::new(),
+            #[cfg(feature = "tls")]
+            tls_config: None,
+        }
+    }
+
+    /// Use the first `cores` cores. Panics if `cores` is 0.
+    pub fn cores(mut self, cores: usize) -> Self {
+        assert!(cores > 0, "`PerCore` requires at least one core");
+        self.cores = cores;
+        self
+    }
+
+    /// Use the given `Config` instead of the [default one](crate::Config::new)
+    /// for the listeners and the connections on them. `reuse_port` is always
+    /// enabled to share the port ( see the note on [`PerCore`] about platforms ).
+    pub fn config(mut self, config: crate::Config) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Serve with TLS ( see [`howls`](crate::Ohkami::howls) ).
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    pub fn tls(mut self, tls_config: rustls::ServerConfig) -> Self {
+        self.tls_config = Some(tls_config);
+        self
+    }
+
+    /// Bind to `bind` on all the cores and start serving, blocking the current
+    /// thread ( used as the first core ) until shutdown.
+    pub fn howl(self, bind: impl std::net::ToSocketAddrs) {
+        self.howl_until(bind, std::future::pending())
+    }
+
+    /// Same as [`howl`](PerCore::howl), but also shuts down all the cores
+    /// gracefully when `shutdown` completes.
+    pub fn howl_until(
+        self,
+        bind: impl std::net::ToSocketAddrs,
+        shutdown: impl Future<Output = ()>,
+    ) {
+        let Self {
+            factory,
+            cores,
+            config,
+            #[cfg(feature = "tls")]
+            tls_config,
+        } = self;
+
+        let config = crate::Config {
+            reuse_port: true,
+            ..config
+        };
+
+        // `nio` has at most `u8::MAX` workers
+        #[cfg(feature = "rt_nio")]
+        let cores = if cores > u8::MAX as usize {
+            crate::WARNING!(
+                "[PerCore] nio runs at most {} workers, so using {} of {cores} cores",
+                u8::MAX,
+                u8::MAX
+            );
+            u8::MAX as usize
+        } else {
+            cores
+        };
+
+        // bound here at once so that the cores share the same port
+        // even when the port is 0
+        let mut listeners = Vec::with_capacity(cores);
+        let first = bind
+            .to_socket_addrs()
+            .and_then(|addrs| __rt__::bind_tcp(addrs, &config))
+            .expect("Failed to bind TCP listener");
+        let addr = first.local_addr().expect("Failed to get local address");
+        listeners.push(first);
+        for _ in 1..cores {
+            listeners.push(__rt__::bind_tcp([addr], &config).expect("Failed to bind TCP listener"));
+        }
+
+        let core = Core {
+            factory: Arc::new(factory),
+            stop: sync::Shutdown::new(),
+            config,
+            #[cfg(feature = "tls")]
+            tls_config,
+        };
+
+        let mut listeners = listeners.into_iter().enumerate();
+        let (_, first) = listeners.next().unwrap();
+
+        #[cfg(not(feature = "rt_nio"))]
+        {
+            let threads = listeners
+                .map(|(id, listener)| {
+                    let core = core.clone();
+                    std::thread::Builder::new()
+                        .name(format!("ohkami-core-{id}"))
+                        .spawn(move || {
+                            run_on_core(id, core.serve(listener, std::future::pending()))
+                        })
+                        .expect("Failed to spawn thread")
+                })
+                .collect::<Vec<_>>();
+
+            // `shutdown` may not be `Send`, so driven on this thread
+            run_on_core(0, core.serve(first, shutdown));
+
+            for thread in threads {
+                if let Err(panic) = thread.join() {
+                    std::panic::resume_unwind(panic)
+                }
+            }
+        }
+
+        // `nio` wakes a local task correctly only within its own runtime,
+        // so the cores are served on the workers of one runtime
+        #[cfg(feature = "rt_nio")]
+        {
+            let mut runtime = nio::RuntimeBuilder::new()
+                .worker_threads(cores as u8)
+                .build()
+                .expect("Failed to build runtime");
+
+            let others = listeners
+                .map(|(id, listener)| {
+                    let core = core.clone();
+                    runtime
+                        .runtime_context()
+                        .spawn_pinned_at(id as u8, move || {
+                            core.serve(listener, std::future::pending())
+                        })
+                })
+                .collect::<Vec<_>>();
+
+            // `shutdown` may not be `Send`, so driven on this thread
+            runtime.block_on(async {
+                core.serve(first, shutdown).await;
+                for other in others {
+                    if let Err(e) = other.await
+                        && e.is_panic()
+                    {
+                        std::panic::resume_unwind(e.into_panic())
+                    }
+                }
+            });
+        }
+    }
+}
+
+/// What is shared among the cores
+struct Core<F> {
+    factory: Arc<F>,
+    /// shutdown of all the cores, started by any of them
+    stop: sync::Shutdown,
+    config: crate::Config,
+    #[cfg(feature = "tls")]
+    tls_config: Option<rustls::ServerConfig>,
+}
+
+impl<F> Clone for Core<F> {
+    fn clone(&self) -> Self {
+        Self {
+            factory: self.factory.clone(),
+            stop: self.stop.clone(),
+            config: self.config,
+            #[cfg(feature = "tls")]
+            tls_config: self.tls_config.clone(),
+        }
+    }
+}
+
+impl<F: Fn() -> Ohkami> Core<F> {
+    async fn serve(self, listener: std::net::TcpListener, shutdown: impl Future<Output = ()>) {
+        let listener = listen::Bound::new(
+            __rt__::Listener::from_std(listener).expect("Failed to register listener"),
+            self.config,
+            #[cfg(feature = "tls")]
+            self.tls_config,
+        );
+        let shutdown = async {
+            sync::until(self.stop.draining(), shutdown).await;
+            // stop the other cores too when this core starts shutting down,
+            // not after its own sessions are drained
+            self.stop.drain();
+        };
+        (self.factory)()
+            .howl_listeners(vec![listener], shutdown)
+            .await;
+    }
+}
+
+/// Run `future` on a new single-threaded executor pinned to the core if possible
+#[cfg(not(feature = "rt_nio"))]
+fn run_on_core<T>(core: usize, future: impl Future<Output = T>) -> T {
+    #[cfg(feature = "rt_glommio")]
+    return glommio::LocalExecutorBuilder::new(glommio::Placement::Fixed(core))
+        .make()
+        .or_else(|e| {
+            crate::WARNING!("Failed to pin executor to core #{core}, running unpinned: {e}");
+            glommio::LocalExecutorBuilder::new(glommio::Placement::Unbound).make()
+        })
+        .expect("Failed to build executor")
+        .run(future);
+
+    #[cfg(feature = "rt_compio")]
+    return compio::runtime::RuntimeBuilder::new()
+        .thread_affinity([core].into())
+        .build()
+        .expect("Failed to build executor")
+        .block_on(future);
+
+    #[cfg(feature = "rt_monoio")]
+    {
+        if monoio::utils::bind_to_cpu_set([core]).is_err() {
+            crate::WARNING!("Failed to pin executor to core #{core}, running unpinned");
+        }
+        monoio::RuntimeBuilder::<monoio::FusionDriver>::new()
+            .enable_all()
+            .build()
+            .expect("Failed to build executor")
+            .block_on(future)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{ListenAddr, Route};
+    use std::io::{Read, Write};
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::time::Duration;
+
+    #[test]
+    fn can_howl_per_core() {
+        static SHUTDOWN: AtomicBool = AtomicBool::new(false);
+
+        let (addrs_tx, addrs_rx) = std::sync::mpsc::channel();
+
+        let client = std::thread::spawn(move || {
+            let addrs = [addrs_rx.recv().unwrap(), addrs_rx.recv().unwrap()];
+            let [[ListenAddr::Tcp(addr)], [ListenAddr::Tcp(addr2)]] = addrs
+                .clone()
+                .map(|a: Vec<_>| <[ListenAddr; 1]>::try_from(a).unwrap())
+            else {
+                panic!("expected TCP addresses: {addrs:?}")
+            };
+
+            let responses = (0..4)
+                .map(|_| {
+                    let mut stream = std::net::TcpStream::connect(addr).unwrap();
+                    stream
+                        .write_all(b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n")
+                        .unwrap();
+                    let mut res = String::new();
+                    stream.read_to_string(&mut res).unwrap();
+                    res
+                })
+                .collect::<Vec<_>>();
+
+            SHUTDOWN.store(true, Ordering::SeqCst);
+            (addr, addr2, responses)
+        });
+
+        PerCore::new(move || {
+            let addrs_tx = addrs_tx.clone();
+            Ohkami::new(("/".GET(async || "ok"),)).on_start(move |addrs| async move {
+                addrs_tx.send(addrs).unwrap();
+            })
+        })
+        .cores(2)
+        .config(crate::Config {
+            shutdown_timeout: 1,
+            ..crate::Config::default()
+        })
+        .howl_until(("127.0.0.1", 0), async {
+            while !SHUTDOWN.load(Ordering::SeqCst) {
+                __rt__::sleep(Duration::from_millis(10)).await;
+            }
+        });
+
+        let (addr, addr2, responses) = client.join().unwrap();
+        assert_eq!(addr, addr2);
+        for res in responses {
+            assert!(res.ends_with("\r\n\r\nok"), "{res}");
+        }
+    }
+}
@@ -2,12 +2,13 @@ use super::*;
 
 impl Ohkami {
     #[cfg(feature = "__rt_native__")]
-    pub(crate) async fn howl_core<T>(
-        self,
-        bind: impl __rt__::IntoTcpListener<T>,
-        config: crate::Config,
-        #[cfg(feature = "tls")] tls_config: Option<rustls::ServerConfig>,
+    pub(crate) async fn howl_listeners(
+        mut self,
+        listeners: Vec<listen::Bound>,
+        shutdown_signal: impl Future<Output = ()>,
     ) {
+        let mut hooks = std::mem::take(&mut self.hooks);
+
         let (router, _) = self.into_router().finalize();
         #[cfg_attr(
             not(feature = "__rt_threaded__"),
@@ -15,17 +16,58 @@ impl Ohkami {
         )]
         let router = Arc::new(router);
 
-        let mut listener = bind.into_tcp_listener().await;
         let (wg, ctrl_c) = (sync::WaitGroup::new(), sync::CtrlC::new());
+        let shutdown = sync::Shutdown::new();
+
+        let shutdown_timeout = listeners
+            .iter()
+            .map(|l| l.config.shutdown_timeout)
+            .max()
+            .expect("No listener to serve on");
+
+        hooks
+            .start(listeners.iter().map(|l| (*l.addr).clone()).collect())
+            .await;
+
+        let serving = listeners.into_iter().map(|listener| {
+            crate::INFO!("start serving on {}", listener.addr);
+            Self::accept_on(listener, &router, &wg, &shutdown)
+        });
+
+        // Ctrl-C, SIGTERM or `shutdown_signal`
+        let stop = ctrl_c.until_interrupt(shutdown_signal);
+        sync::until(stop, sync::join_all(serving)).await;
+
+        sync::drain(&shutdown, wg, shutdown_timeout).await;
+
+        hooks.shutdown().await;
+    }
 
-        #[cfg(feature = "tls")]
-        let tls_acceptor = tls_config.map(|it| anysc_rustls::TlsAcceptor::from(Arc::new(it)));
+    /// Accept connections on `listener` and spawn a session for each of them,
+    /// until this future is dropped ( with the listener ) for shutdown
+    async fn accept_on(
+        listener: listen::Bound,
+        router: &Arc<crate::router::r#final::Router>,
+        wg: &sync::WaitGroup,
+        shutdown: &sync::Shutdown,
+    ) {
+        let listen::Bound {
+            listener: __rt__::Listener::Tcp(mut listener),
+            addr: listen_addr,
+            config,
+            #[cfg(feature = "tls")]
+            tls_acceptor,
+        } = listener;
 
-        crate::INFO!("start serving on {}", listener.local_addr().unwrap());
-        while let Some(accept) = ctrl_c.until_interrupt(listener.accept()).await {
-            let Ok(conn) = accept else {
+        let limit = sync::ConnectionLimit::new(config.max_connections);
+        loop {
+            let permit = limit.acquire(&listen_addr).await;
+            let Ok(conn) = listener.accept().await else {
                 continue;
             };
+            if config.tcp_nodelay {
+                conn.set_nodelay(true).ok();
+            }
             // Manually impl Send + Sync, bcs `__rt_threaded__` is disabled
             #[allow(warnings)]
             {
@@ -35,37 +77,35 @@ impl Ohkami {
             #[cfg(feature = "tls")]
             let tls_acceptor = tls_acceptor.clone();
             let router = router.clone();
+            let shutdown = shutdown.clone();
+            let listen_addr = listen_addr.clone();
 
             let wg = wg.add();
             let accept = || async move {
                 let address = conn.peer_addr()?;
                 let stream = conn.connect().await?;
+                let info = session::ConnectionInfo::new(listen_addr, stream.local_addr().ok());
 
-                #[cfg(feature = "tls")]
-                let stream: session::Connection = match tls_acceptor {
-                    None => stream.into(),
-                    Some(tls_acceptor) => match tls_acceptor.accept(stream).await {
-                        Ok(tls_stream) => tls_stream.into(),
-                        Err(e) => {
-                            crate::ERROR!("TLS accept error: {e}");
-                            return Ok(());
-                        }
-                    },
-                };
-
-                let session = session::Session::new(config, stream, address.ip(), router);
-                session.manage().await;
+                if let Some(session) = session::Session::accept(
+                    config,
+                    stream,
+                    address.ip(),
+                    info,
+                    #[cfg(feature = "tls")]
+                    tls_acceptor,
+                    router,
+                    shutdown,
+                )
+                .await
+                {
+                    session.manage().await;
+                }
                 wg.done();
+                drop(permit);
 
                 std::io::Result::Ok(())
             };
             nio::spawn_pinned(accept);
         }
-
-        crate::INFO!("interrupted, trying graceful shutdown...");
-        drop(listener);
-
-        crate::INFO!("waiting {} session(s) to finish...", wg.count());
-        wg.await;
     }
 }
@@ -0,0 +1,333 @@
+#![cfg(all(feature = "__rt_native__", feature = "tls"))]
+
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::{ClientHello, ResolvesServerCert};
+use rustls::sign::CertifiedKey;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+/// Reloadable certificates for `howls`, read from PEM files and selected
+/// by SNI hostname.
+///
+/// `TlsCerts` is a certificate resolver of rustls : set it to a `ServerConfig`
+/// by `with_cert_resolver`. Certificates are re-read from the files by
+/// [`reload`](TlsCerts::reload) or [`watch`](TlsCerts::watch), and then new
+/// handshakes use the fresh ones while established sessions continue.
+/// Clones share the same certificates.
+///
+/// ## Example
+///
+/// ```no_run
+/// use ohkami::{Ohkami, Route, TlsCerts};
+/// use std::{sync::Arc, time::Duration};
+///
+/// #[tokio::main]
+/// async fn main() -> std::io::Result<()> {
+///     rustls::crypto::ring::default_provider().install_default()
+///         .expect("Failed to install rustls crypto provider");
+///
+///     let certs = TlsCerts::new()
+///         .cert("/etc/tls/default.crt", "/etc/tls/default.key")?
+///         .sni("api.example.com", "/etc/tls/api.crt", "/etc/tls/api.key")?
+///         .sni("*.example.com", "/etc/tls/wildcard.crt", "/etc/tls/wildcard.key")?;
+///
+///     // re-read the files when they're renewed
+///     tokio::spawn(certs.clone().watch(Duration::from_secs(60)));
+///
+///     let tls_config = rustls::ServerConfig::builder()
+///         .with_no_client_auth()
+///         .with_cert_resolver(Arc::new(certs));
+///
+///     Ohkami::new((
+///         "/".GET(async || "Hello, secure ohkami!"),
+///     )).howls("0.0.0.0:8443", tls_config).await;
+///
+///     Ok(())
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct TlsCerts {
+    default: Option<Arc<Source>>,
+    by_name: Vec<(Box<str>, Arc<Source>)>,
+}
+
+#[derive(Debug)]
+struct Source {
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    loaded: RwLock<Loaded>,
+}
+
+#[derive(Debug)]
+struct Loaded {
+    key: Arc<CertifiedKey>,
+    modified: Option<SystemTime>,
+}
+
+impl TlsCerts {
+    /// Empty certificates, to be added by [`cert`](TlsCerts::cert) or [`sni`](TlsCerts::sni)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load the default certificate chain and private key, used when no certificate
+    /// for the client's SNI hostname is added ( or the client doesn't send SNI ).
+    ///
+    /// This requires a process-level `rustls::crypto::CryptoProvider` to be installed.
+    pub fn cert(
+        mut self,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> std::io::Result<Self> {
+        self.default = Some(Arc::new(Source::load(cert_path, key_path)?));
+        Ok(self)
+    }
+
+    /// Load a certificate chain and private key for the SNI hostname `name`.
+    /// `name` can be a wildcard like `*.example.com` to match one label
+    /// at the head.
+    ///
+    /// This requires a process-level `rustls::crypto::CryptoProvider` to be installed.
+    pub fn sni(
+        mut self,
+        name: &str,
+        cert_path: impl AsRef<Path>,
+        key_path: impl AsRef<Path>,
+    ) -> std::io::Result<Self> {
+        let source = Arc::new(Source::load(cert_path, key_path)?);
+        self.by_name
+            .push((name.to_ascii_lowercase().into(), source));
+        Ok(self)
+    }
+
+    /// Re-read all the certificates from their files.
+    ///
+    /// If any of them fails to load, none is replaced and the error is returned.
+    pub fn reload(&self) -> std::io::Result<()> {
+        let reloaded = self
+            .sources()
+            .map(|source| Ok((source, source.read()?)))
+            .collect::<std::io::Result<Vec<_>>>()?;
+        for (source, loaded) in reloaded {
+            *source.loaded.write().unwrap() = loaded;
+        }
+        Ok(())
+    }
+
+    /// Check the files every `interval` and re-read the certificates modified.
+    ///
+    /// This never completes, so should be spawned on the async runtime. Failure of
+    /// reading is logged and the previous certificate is kept to be used.
+    pub async fn watch(self, interval: Duration) {
+        loop {
+            crate::__rt__::sleep(interval).await;
+            self.refresh();
+        }
+    }
+
+    fn refresh(&self) {
+        for source in self.sources() {
+            if source.modified() == source.loaded.read().unwrap().modified {
+                continue;
+            }
+            match source.read() {
+                Ok(loaded) => {
+                    crate::INFO!("[TlsCerts] reloaded {}", source.cert_path.display());
+                    *source.loaded.write().unwrap() = loaded;
+                }
+                Err(e) => crate::WARNING!(
+                    "[TlsCerts] Failed to reload {}, keeping the previous one: {e}",
+                    source.cert_path.display()
+                ),
+            }
+        }
+    }
+
+    fn sources(&self) -> impl Iterator<Item = &Source> {
+        (self.default.iter())
+            .chain(self.by_name.iter().map(|(_, source)| source))
+            .map(|source| &**source)
+    }
+
+    fn resolve_name(&self, server_name: Option<&str>) -> Option<Arc<CertifiedKey>> {
+        let matches = |pattern: &str, name: &str| match pattern.strip_prefix("*.") {
+            Some(parent) => name
+                .split_once('.')
+                .is_some_and(|(_, name_parent)| name_parent.eq_ignore_ascii_case(parent)),
+            None => name.eq_ignore_ascii_case(pattern),
+        };
+
+        let source = server_name
+            .and_then(|name| {
+                // exact names take priority over wildcards
+                (self.by_name.iter())
+                    .find(|(pattern, _)| !pattern.starts_with("*.") && matches(pattern, name))
+                    .or_else(|| {
+                        (self.by_name.iter()).find(|(pattern, _)| {
+                            pattern.starts_with("*.") && matches(pattern, name)
+                        })
+                    })
+                    .map(|(_, source)| source)
+            })
+            .or(self.default.as_ref())?;
+
+        Some(source.loaded.read().unwrap().key.clone())
+    }
+}
+
+impl ResolvesServerCert for TlsCerts {
+    fn resolve(&self, client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.resolve_name(client_hello.server_name())
+    }
+}
+
+impl Source {
+    fn load(cert_path: impl AsRef<Path>, key_path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let (cert_path, key_path) = (cert_path.as_ref(), key_path.as_ref());
+        Ok(Self {
+            loaded: RwLock::new(read(cert_path, key_path)?),
+            cert_path: cert_path.to_owned(),
+            key_path: key_path.to_owned(),
+        })
+    }
+
+    fn read(&self) -> std::io::Result<Loaded> {
+        read(&self.cert_path, &self.key_path)
+    }
+
+    fn modified(&self) -> Option<SystemTime> {
+        modified(&self.cert_path, &self.key_path)
+    }
+}
+
+/// last modified time of the files, changing when either is replaced
+fn modified(cert_path: &Path, key_path: &Path) -> Option<SystemTime> {
+    let modified = |path: &Path| path.metadata().and_then(|m| m.modified()).ok();
+    Option::max(modified(cert_path), modified(key_path))
+}
+
+fn read(cert_path: &Path, key_path: &Path) -> std::io::Result<Loaded> {
+    fn invalid(path: &Path, e: impl std::fmt::Display) -> std::io::Error {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{}: {e}", path.display()),
+        )
+    }
+
+    let modified = modified(cert_path, key_path);
+
+    let cert_chain = CertificateDer::pem_file_iter(cert_path)
+        .and_then(Iterator::collect::<Result<Vec<_>, _>>)
+        .map_err(|e| invalid(cert_path, e))?;
+    if cert_chain.is_empty() {
+        return Err(invalid(cert_path, "no certificate found"));
+    }
+
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| invalid(key_path, e))?;
+
+    let provider = rustls::crypto::CryptoProvider::get_default().ok_or_else(|| {
+        std::io::Error::other("No process-level `rustls::crypto::CryptoProvider` is installed")
+    })?;
+    let key =
+        CertifiedKey::from_der(cert_chain, key, provider).map_err(|e| invalid(key_path, e))?;
+
+    Ok(Loaded {
+        key: Arc::new(key),
+        modified,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn openssl_x509_newkey(dir: &Path, name: &str) -> (PathBuf, PathBuf) {
+        let (cert_path, key_path) = (
+            dir.join(format!("{name}.crt")),
+            dir.join(format!("{name}.key")),
+        );
+        let status = std::process::Command::new("openssl")
+            .args(["req", "-x509", "-newkey", "ec", "-pkeyopt"])
+            .args(["ec_paramgen_curve:prime256v1", "-nodes", "-days", "1"])
+            .arg("-subj")
+            .arg(format!("/CN={name}"))
+            .arg("-out")
+            .arg(&cert_path)
+            .arg("-keyout")
+            .arg(&key_path)
+            .stderr(std::process::Stdio::null())
+            .status()
+            .expect("`openssl` failed");
+        assert!(status.success());
+        (cert_path, key_path)
+    }
+
+    fn end_entity(key: Option<Arc<CertifiedKey>>) -> Vec<u8> {
+        key.unwrap().cert[0].to_vec()
+    }
+
+    #[test]
+    fn test_tls_certs() {
+        rustls::crypto::ring::default_provider()
+            .install_default()
+            .ok();
+
+        let dir = std::env::temp_dir().join(format!("ohkami-tls-certs-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (default_cert, default_key) = openssl_x509_newkey(&dir, "default.test");
+        let (api_cert, api_key) = openssl_x509_newkey(&dir, "api.example.com");
+        let (wildcard_cert, wildcard_key) = openssl_x509_newkey(&dir, "wildcard.example.com");
+
+        let certs = TlsCerts::new()
+            .sni("*.example.com", &wildcard_cert, &wildcard_key)
+            .unwrap()
+            .sni("API.example.com", &api_cert, &api_key)
+            .unwrap();
+        assert!(certs.resolve_name(Some("other.test")).is_none());
+        assert!(certs.resolve_name(None).is_none());
+
+        let certs = certs.cert(&default_cert, &default_key).unwrap();
+
+        let [default, api, wildcard] = [&default_cert, &api_cert, &wildcard_cert]
+            .map(|path| CertificateDer::from_pem_file(path).unwrap().to_vec());
+        assert_eq!(end_entity(certs.resolve_name(None)), default);
+        assert_eq!(end_entity(certs.resolve_name(Some("other.test"))), default);
+        assert_eq!(end_entity(certs.resolve_name(Some("api.example.com"))), api);
+        assert_eq!(
+            end_entity(certs.resolve_name(Some("www.example.com"))),
+            wildcard
+        );
+        assert_eq!(end_entity(certs.resolve_name(Some("example.com"))), default);
+        assert_eq!(
+            end_entity(certs.resolve_name(Some("a.b.example.com"))),
+            default
+        );
+
+        /* renewed */
+        let clone = certs.clone();
+        openssl_x509_newkey(&dir, "api.example.com");
+        let renewed = CertificateDer::from_pem_file(&api_cert).unwrap().to_vec();
+        assert_ne!(renewed, api);
+        assert_eq!(end_entity(clone.resolve_name(Some("api.example.com"))), api);
+        certs.reload().unwrap();
+        assert_eq!(
+            end_entity(clone.resolve_name(Some("api.example.com"))),
+            renewed
+        );
+
+        /* broken, keeping the previous ones */
+        std::fs::write(&default_key, "").unwrap();
+        assert!(certs.reload().is_err());
+        certs.refresh();
+        assert_eq!(end_entity(certs.resolve_name(None)), default);
+        assert_eq!(
+            end_entity(certs.resolve_name(Some("api.example.com"))),
+            renewed
+        );
+
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
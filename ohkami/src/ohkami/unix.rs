@@ -0,0 +1,135 @@
+#![cfg(all(unix, feature = "__rt_native__", not(feature = "rt_nio")))]
+
+use crate::__rt__;
+use std::path::{Path, PathBuf};
+
+/// Unix domain socket to bind for [`Ohkami::howl`](crate::Ohkami::howl),
+/// with the options for the socket file.
+///
+/// `howl` also accepts a `UnixListener` of the async runtime as it is.
+///
+/// *note* : `Request::ip` is `127.0.0.1` for the requests over Unix domain socket,
+/// because the peer doesn't have an IP address.
+///
+/// ## Example
+///
+/// ```no_run
+/// use ohkami::{Ohkami, Route, UnixSocket};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     Ohkami::new((
+///         "/".GET(async || "Hello, Unix domain socket!"),
+///     )).howl(
+///         UnixSocket::new("/run/app/ohkami.sock")
+///             .mode(0o660)
+///             .remove_stale(true)
+///     ).await
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct UnixSocket {
+    path: PathBuf,
+    mode: Option<u32>,
+    remove_stale: bool,
+}
+
+impl UnixSocket {
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            mode: None,
+            remove_stale: false,
+        }
+    }
+
+    /// Set the permissions of the socket file ( like `0o660` ) after binding.
+    ///
+    /// They're set before the socket starts listening, so no one can connect
+    /// with the default permissions in between. Only on `rt_glommio`, that binds
+    /// and listens at once, the socket is connectable with the default ones
+    /// until they're set just after binding.
+    ///
+    /// default: not set ( following the umask of the process )
+    pub fn mode(mut self, mode: u32) -> Self {
+        self.mode = Some(mode);
+        self
+    }
+
+    /// Remove the socket file left by a previous process before binding.
+    ///
+    /// The file is removed only when it's a socket and no one is listening on it,
+    /// so a running server is never replaced.
+    ///
+    /// default: `false` ( binding fails if the file exists )
+    pub fn remove_stale(mut self, yes: bool) -> Self {
+        self.remove_stale = yes;
+        self
+    }
+
+    pub(crate) async fn bind(self, config: &crate::Config) -> __rt__::UnixListener {
+        if self.remove_stale && self.is_stale() {
+            crate::DEBUG!("removing stale socket file `{}`", self.path.display());
+            std::fs::remove_file(&self.path).expect("Failed to remove stale socket file");
+        }
+
+        #[cfg(not(feature = "rt_glommio"))]
+        let listener = {
+            let listener = self.listen(config).expect("Failed to bind Unix listener");
+            #[cfg(any(feature = "rt_tokio", feature = "rt_monoio", feature = "rt_compio"))]
+            let listener = __rt__::UnixListener::from_std(listener);
+            #[cfg(feature = "rt_smol")]
+            let listener = __rt__::UnixListener::try_from(listener);
+            listener.expect("Failed to register Unix listener")
+        };
+
+        #[cfg(feature = "rt_glommio")]
+        let listener = {
+            let _ = config;
+            let listener =
+                __rt__::UnixListener::bind(&self.path).expect("Failed to bind Unix listener");
+            self.set_mode()
+                .expect("Failed to set permissions of socket file");
+            listener
+        };
+
+        listener
+    }
+
+    /// Bind the socket and set `mode` to the file before listening,
+    /// as connecting to a socket not listening yet is refused
+    #[cfg(not(feature = "rt_glommio"))]
+    fn listen(&self, config: &crate::Config) -> std::io::Result<std::os::unix::net::UnixListener> {
+        use socket2::{Domain, SockAddr, Socket, Type};
+
+        let socket = Socket::new(Domain::UNIX, Type::STREAM, None)?;
+        socket.bind(&SockAddr::unix(&self.path)?)?;
+        self.set_mode()?;
+        socket.listen(config.listen_backlog.min(i32::MAX as u32) as i32)?;
+        // io_uring-based `compio` takes blocking sockets
+        #[cfg(not(feature = "rt_compio"))]
+        socket.set_nonblocking(true)?;
+        Ok(socket.into())
+    }
+
+    fn set_mode(&self) -> std::io::Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        match self.mode {
+            Some(mode) => {
+                std::fs::set_permissions(&self.path, std::fs::Permissions::from_mode(mode))
+            }
+            None => Ok(()),
+        }
+    }
+
+    fn is_stale(&self) -> bool {
+        use std::os::unix::fs::FileTypeExt;
+
+        let is_socket = std::fs::symlink_metadata(&self.path)
+            .is_ok_and(|metadata| metadata.file_type().is_socket());
+        is_socket
+            && std::os::unix::net::UnixStream::connect(&self.path)
+                .is_err_and(|e| e.kind() == std::io::ErrorKind::ConnectionRefused)
+    }
+}
@@ -91,6 +91,32 @@ fn extract_optional_payload() {
     });
 }
 
+#[test]
+fn extract_body_stream() {
+    use crate::claw::content::BodyStream;
+
+    async fn count_bytes(mut body: BodyStream<'_>) -> String {
+        let mut buf = Vec::new();
+        let size = body.read_to_end(&mut buf).await.unwrap();
+        format!("{size}: {}", String::from_utf8_lossy(&buf))
+    }
+
+    let t = Ohkami::new(("/".POST(count_bytes),)).test();
+
+    crate::__rt__::testing::block_on(async {
+        {
+            let req = TestRequest::POST("/").content("application/octet-stream", b"Hello, stream!");
+            let res = t.oneshot(req).await;
+            assert_eq!(res.text(), Some("14: Hello, stream!"));
+        }
+        {
+            let req = TestRequest::POST("/");
+            let res = t.oneshot(req).await;
+            assert_eq!(res.text(), Some("0: "));
+        }
+    });
+}
+
 #[test]
 fn extract_query() {
     async fn hello(Query(HelloQuery { name, repeat }): Query<HelloQuery<'_>>) -> String {
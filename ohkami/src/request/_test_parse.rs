@@ -6,6 +6,83 @@ use super::{Context, Method, Path, QueryParams, Request};
 use super::{RequestHeader, RequestHeaders};
 use ohkami_lib::{CowSlice, Slice};
 use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+
+/// connection delivering the bytes by separate reads of `chunks`
+struct Split<'c> {
+    chunks: std::collections::VecDeque<&'c [u8]>,
+    /// bytes written back to the client, like `100 Continue`
+    written: Vec<u8>,
+    /// keep the connection open without sending anything after `chunks`
+    stall: bool,
+}
+
+impl<'c> Split<'c> {
+    fn new(chunks: impl IntoIterator<Item = &'c str>) -> Self {
+        Self {
+            chunks: chunks.into_iter().map(str::as_bytes).collect(),
+            written: Vec::new(),
+            stall: false,
+        }
+    }
+
+    fn stalling(self) -> Self {
+        Self {
+            stall: true,
+            ..self
+        }
+    }
+
+    fn read_chunk(&mut self, buf: &mut [u8]) -> Poll<usize> {
+        let Some(chunk) = self.chunks.pop_front() else {
+            return if self.stall {
+                Poll::Pending
+            } else {
+                Poll::Ready(0)
+            };
+        };
+        let n = chunk.len().min(buf.len());
+        buf[..n].copy_from_slice(&chunk[..n]);
+        if n < chunk.len() {
+            self.chunks.push_front(&chunk[n..]);
+        }
+        Poll::Ready(n)
+    }
+}
+
+#[cfg(feature = "__io_tokio__")]
+impl tokio::io::AsyncRead for Split<'_> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _: &mut TaskContext<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let n = std::task::ready!(self.get_mut().read_chunk(buf.initialize_unfilled()));
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "__io_futures__")]
+impl futures_util::AsyncRead for Split<'_> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        _: &mut TaskContext<'_>,
+        buf: &mut [u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.get_mut().read_chunk(buf).map(Ok)
+    }
+}
+
+impl super::payload::ReadStream for Split<'_> {
+    fn poll_write_continue(
+        self: Pin<&mut Self>,
+        cx: &mut TaskContext<'_>,
+        written: &mut usize,
+    ) -> Poll<std::io::Result<()>> {
+        super::payload::poll_write_continue(Pin::new(&mut self.get_mut().written), cx, written)
+    }
+}
 
 #[test]
 fn parse_path() {
@@ -80,6 +157,8 @@ fn test_parse_request() {
         CASE_1,
         Request {
             __buf__: metadataize(CASE_1),
+            __unread__: Default::default(),
+            version: super::Version::Http11,
             method: Method::GET,
             path: Path::from_literal("/hello.html"),
             query: QueryParams::new(b""),
@@ -95,7 +174,8 @@ fn test_parse_request() {
             ),
             payload: None,
             context: Context::init(),
-            ip: crate::util::IP_0000
+            ip: crate::util::IP_0000,
+            forwarded: None,
         }
     );
 
@@ -114,6 +194,8 @@ fn test_parse_request() {
         CASE_2,
         Request {
             __buf__: metadataize(CASE_2),
+            __unread__: Default::default(),
+            version: super::Version::Http11,
             method: Method::POST,
             path: Path::from_literal("/signup"),
             query: QueryParams::new(b""),
@@ -131,7 +213,8 @@ fn test_parse_request() {
                 br#"{"name":"kanarus","age":20}"#
             ))),
             context: Context::init(),
-            ip: crate::util::IP_0000
+            ip: crate::util::IP_0000,
+            forwarded: None,
         }
     );
 
@@ -156,6 +239,8 @@ fn test_parse_request() {
             CASE_3,
             Request {
                 __buf__: metadataize(CASE_3),
+                __unread__: Default::default(),
+                version: super::Version::Http11,
                 method: Method::POST,
                 path: Path::from_literal("/foo.php"),
                 query: QueryParams::from([("query", "1"), ("q2", "xxx"),]),
@@ -186,8 +271,825 @@ fn test_parse_request() {
                     Vec::from("first_name=John&last_name=Doe&action=Submit").into()
                 )),
                 context: Context::init(),
-                ip: crate::util::IP_0000
+                ip: crate::util::IP_0000,
+                forwarded: None,
+            }
+        );
+    }
+}
+
+#[test]
+fn test_parse_header_whitespace() {
+    const CASE: &str = "\
+        GET /hello HTTP/1.1\r\n\
+        Host:example.com\r\n\
+        User-Agent: \t device/1.0 \t\r\n\
+        Accept:\r\n\
+        X-Request-Id:   42  \r\n\
+        \r\n\
+    ";
+    assert_parse!(
+        CASE,
+        Request {
+            __buf__: metadataize(CASE),
+            __unread__: Default::default(),
+            version: super::Version::Http11,
+            method: Method::GET,
+            path: Path::from_literal("/hello"),
+            query: QueryParams::new(b""),
+            headers: RequestHeaders::from_iters(
+                [
+                    (RequestHeader::Host, "example.com"),
+                    (RequestHeader::UserAgent, "device/1.0"),
+                    (RequestHeader::Accept, ""),
+                ],
+                [("X-Request-Id", "42")]
+            ),
+            payload: None,
+            context: Context::init(),
+            ip: crate::util::IP_0000,
+            forwarded: None,
+        }
+    );
+}
+
+#[test]
+fn test_reject_malformed_headers() {
+    use crate::Status;
+
+    fn read_status(case: &str, config: crate::Config) -> Status {
+        let mut case = case.as_bytes();
+
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        let mut req = Pin::new(&mut req);
+        match crate::__rt__::testing::block_on(req.as_mut().read(&mut case, &config)) {
+            Ok(_) => Status::OK,
+            Err(res) => res.status,
+        }
+    }
+
+    for case in [
+        // whitespace between the name and colon
+        "GET / HTTP/1.1\r\nHost : example.com\r\n\r\n",
+        // obs-fold
+        "GET / HTTP/1.1\r\nX-Long: a\r\n  b\r\n\r\n",
+        "GET / HTTP/1.1\r\nX-Long: a\r\n\tb\r\n\r\n",
+        // whitespace before the first field line
+        "GET / HTTP/1.1\r\n Host: example.com\r\n\r\n",
+        // no colon
+        "GET / HTTP/1.1\r\nHost example.com\r\n\r\n",
+        // empty or invalid name
+        "GET / HTTP/1.1\r\n: example.com\r\n\r\n",
+        "GET / HTTP/1.1\r\nX(Custom): value\r\n\r\n",
+        // bare CR or LF, NUL in value
+        "GET / HTTP/1.1\r\nHost: example.com\rX: y\r\n\r\n",
+        "GET / HTTP/1.1\r\nHost: example.com\nX: y\r\n\r\n",
+        "GET / HTTP/1.1\r\nHost: example\0.com\r\n\r\n",
+        // incomplete head not filling the buffer
+        "GET / HTTP/1.1\r\nHost: exam",
+        "GET / HTTP/1.1\r\nHost: example.com\r",
+    ] {
+        assert_eq!(
+            read_status(case, crate::Config::new()),
+            Status::BadRequest,
+            "{}",
+            case.escape_debug()
+        );
+    }
+}
+
+#[test]
+fn test_read_head_across_reads() {
+    use crate::Status;
+
+    fn read(chunks: &[&str], config: crate::Config) -> Result<(Request, usize), Status> {
+        let mut stream = Split::new(chunks.iter().copied());
+
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        let result =
+            crate::__rt__::testing::block_on(Pin::new(&mut req).read(&mut stream, &config));
+        match result {
+            Ok(_) => {
+                let buf_len = req.__buf__.len();
+                Ok((req, buf_len))
+            }
+            Err(res) => Err(res.status),
+        }
+    }
+
+    let small = crate::Config {
+        request_bufsize: 16,
+        max_request_head_size: 512,
+        ..crate::Config::new()
+    };
+
+    // head split at any point, even in the middle of the final CRLF CRLF
+    for chunks in [
+        &["GET /hello", " HTTP/1.1\r\nHost: loc", "alhost\r\n\r\n"][..],
+        &["GET /hello HTTP/1.1\r\nHost: localhost\r", "\n\r", "\n"],
+        &["GET /hello HTTP/1.1\r\nHost: localhost\r\n\r\n"],
+    ] {
+        let (req, buf_len) = read(chunks, small).unwrap();
+        assert_eq!(req.path.str(), "/hello");
+        assert_eq!(req.headers.host(), Some("localhost"));
+        assert!(buf_len > 16 && buf_len <= 512);
+    }
+
+    // large header growing the buffer, with payload following the head
+    let cookie = format!("session={}", "x".repeat(200));
+    let head = format!("POST /login HTTP/1.1\r\nCookie: {cookie}\r\nContent-Length: 5\r\n\r\n");
+    let (req, _) = read(&[&head[..30], &head[30..], "hello"], small).unwrap();
+    assert_eq!(req.headers.cookie(), Some(&*cookie));
+    assert_eq!(req.payload.as_deref(), Some(&b"hello"[..]));
+
+    // exceeding `max_request_head_size`
+    assert_eq!(
+        read(
+            &[&head],
+            crate::Config {
+                max_request_head_size: 128,
+                ..small
             }
+        )
+        .unwrap_err(),
+        Status::RequestHeaderFieldsTooLarge
+    );
+
+    // connection closed in the middle of the head
+    assert_eq!(
+        read(&["GET /hello HTTP/1.1\r\nHost: localhost\r\n"], small).unwrap_err(),
+        Status::BadRequest
+    );
+
+    // the buffer shrinks back for the next request
+    let config = small;
+    let mut req = Request::uninit(crate::util::IP_0000, &config);
+    let mut req = Pin::new(&mut req);
+    crate::__rt__::testing::block_on(async {
+        let mut stream = Split::new([&*head, "hello"]);
+        assert!(req.as_mut().read(&mut stream, &config).await.is_ok());
+        assert_eq!(req.__buf__.len(), 512);
+
+        req.clear();
+        let mut stream = Split::new(["GET / HTTP/1.1\r\n\r\n"]);
+        assert!(req.as_mut().read(&mut stream, &config).await.is_ok());
+        assert_eq!(
+            req.__buf__.len(),
+            32 /* 16 -> 32 for the 18-byte head */
+        );
+    });
+}
+
+#[test]
+fn test_read_limits() {
+    use crate::Status;
+
+    fn read(stream: Split, config: crate::Config) -> Result<Option<()>, Status> {
+        let mut stream = stream;
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        crate::__rt__::testing::block_on(Pin::new(&mut req).read(&mut stream, &config))
+            .map_err(|res| res.status)
+    }
+
+    let config = crate::Config {
+        max_request_headers: 3,
+        keepalive_timeout: 1,
+        request_head_timeout: 1,
+        request_body_timeout: 1,
+        ..crate::Config::new()
+    };
+
+    // `max_request_headers`
+    assert_eq!(
+        read(
+            Split::new(["GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\n\r\n"]),
+            config
+        ),
+        Ok(Some(()))
+    );
+    assert_eq!(
+        read(
+            Split::new(["GET / HTTP/1.1\r\nA: 1\r\nB: 2\r\nC: 3\r\nD: 4\r\n\r\n"]),
+            config
+        ),
+        Err(Status::RequestHeaderFieldsTooLarge)
+    );
+
+    // idle until `keepalive_timeout` : just closing the connection
+    assert_eq!(read(Split::new([]).stalling(), config), Ok(None));
+
+    // `request_head_timeout` after the first byte of the head
+    assert_eq!(
+        read(Split::new(["GET / HTTP/1.1\r\nHo"]).stalling(), config),
+        Err(Status::RequestTimeout)
+    );
+
+    // `request_body_timeout`
+    assert_eq!(
+        read(
+            Split::new(["POST / HTTP/1.1\r\nContent-Length: 5\r\n\r\nhel"]).stalling(),
+            config
+        ),
+        Err(Status::RequestTimeout)
+    );
+}
+
+#[test]
+fn test_pipelined_requests() {
+    use crate::FromRequest;
+    use crate::claw::content::BodyStream;
+
+    /// read requests like the keep-alive session loop, optionally
+    /// streaming the payloads instead of loading them
+    fn read_all(chunks: &[&str], streaming: bool) -> Vec<String> {
+        let config = crate::Config {
+            request_bufsize: 32,
+            ..crate::Config::new()
+        };
+        let mut stream = Split::new(chunks.iter().copied());
+
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        let mut req = Pin::new(&mut req);
+        crate::__rt__::testing::block_on(async {
+            let mut handled = Vec::new();
+            loop {
+                req.clear();
+                match req.as_mut().read_head(&mut stream, &config).await {
+                    Ok(super::Head::Request) => (),
+                    Ok(_) => break handled,
+                    Err(res) => panic!("{res:?}"),
+                }
+
+                let mut payload = Vec::new();
+                if streaming && let Some(Ok(mut body)) = BodyStream::from_request(&req) {
+                    body.read_to_end(&mut payload).await.unwrap();
+                } else {
+                    req.as_mut().load_payload().await.unwrap();
+                    payload = req.payload().map(<[u8]>::to_vec).unwrap_or_default();
+                }
+                assert!(req.__unread__.is_done());
+
+                handled.push(format!(
+                    "{} {} {}",
+                    req.method,
+                    req.path.str(),
+                    String::from_utf8(payload).unwrap()
+                ));
+            }
+        })
+    }
+
+    // coalesced into one read
+    for streaming in [false, true] {
+        assert_eq!(
+            read_all(
+                &["\
+                    GET /a HTTP/1.1\r\n\r\n\
+                    POST /b HTTP/1.1\r\nContent-Length: 5\r\n\r\nhello\
+                    PUT /c HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n\
+                    \r\n\
+                    GET /d HTTP/1.1\r\n\r\n\
+                "],
+                streaming
+            ),
+            ["GET /a ", "POST /b hello", "PUT /c abc", "GET /d "]
+        );
+    }
+
+    // split at arbitrary points, across the boundaries of the requests
+    for streaming in [false, true] {
+        assert_eq!(
+            read_all(
+                &[
+                    "GET /a HTTP/1.1\r\n\r\nPOST /b HT",
+                    "TP/1.1\r\nContent-Length: 5\r\n\r\nhel",
+                    "loPUT /c HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r",
+                    "\nabc\r\n0\r\n\r\nGET /d HTTP/1.1\r\n",
+                    "\r\n",
+                ],
+                streaming
+            ),
+            ["GET /a ", "POST /b hello", "PUT /c abc", "GET /d "]
+        );
+    }
+
+    // a payload larger than the buffer, followed by the next request
+    let large = "x".repeat(100);
+    assert_eq!(
+        read_all(
+            &[
+                "POST /large HTTP/1.1\r\nContent-Length: 100\r\n\r\n",
+                &format!("{large}GET /next HTTP/1.1\r\n\r\n"),
+            ],
+            false
+        ),
+        [format!("POST /large {large}"), "GET /next ".into()]
+    );
+}
+
+#[test]
+fn test_expect_continue() {
+    use crate::claw::content::{BodyStream, Text};
+    use crate::{FangAction, Ohkami, Response, Route, Status};
+
+    const CONTINUE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+
+    /// `(written, payload or status)` of reading the request
+    fn read(chunks: &[&str]) -> (Vec<u8>, Result<String, Status>) {
+        let config = crate::Config {
+            request_payload_limit: 16,
+            ..crate::Config::new()
+        };
+        let mut stream = Split::new(chunks.iter().copied());
+
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        let result =
+            crate::__rt__::testing::block_on(Pin::new(&mut req).read(&mut stream, &config));
+        let result = result
+            .map(|_| String::from_utf8(req.payload().unwrap_or_default().to_vec()).unwrap())
+            .map_err(|res| res.status);
+        (stream.written, result)
+    }
+
+    // waiting for `100 Continue` before sending the payload
+    assert_eq!(
+        read(&[
+            "POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n",
+            "hello"
+        ]),
+        (CONTINUE.to_vec(), Ok("hello".into()))
+    );
+    assert_eq!(
+        read(&[
+            "POST / HTTP/1.1\r\nExpect: 100-Continue\r\nTransfer-Encoding: chunked\r\n\r\n",
+            "5\r\nhello\r\n0\r\n\r\n"
+        ]),
+        (CONTINUE.to_vec(), Ok("hello".into()))
+    );
+
+    // the payload already arriving, or HTTP/1.0 client
+    assert_eq!(
+        read(&["POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\nhello"]),
+        (Vec::new(), Ok("hello".into()))
+    );
+    assert_eq!(
+        read(&[
+            "POST / HTTP/1.0\r\nExpect: 100-continue\r\nContent-Length: 5\r\n\r\n",
+            "hello"
+        ]),
+        (Vec::new(), Ok("hello".into()))
+    );
+
+    // rejected by `request_payload_limit` without `100 Continue`
+    assert_eq!(
+        read(&["POST / HTTP/1.1\r\nExpect: 100-continue\r\nContent-Length: 100\r\n\r\n"]),
+        (Vec::new(), Err(Status::PayloadTooLarge))
+    );
+
+    #[derive(Clone)]
+    struct Auth;
+    impl FangAction for Auth {
+        async fn fore<'a>(&'a self, req: &'a mut Request) -> Result<(), Response> {
+            match req.headers.authorization() {
+                Some(_) => Ok(()),
+                None => Err(Response::Unauthorized()),
+            }
+        }
+    }
+
+    async fn stream(mut body: BodyStream<'_>) -> String {
+        let mut payload = Vec::new();
+        body.read_to_end(&mut payload).await.unwrap();
+        String::from_utf8(payload).unwrap()
+    }
+
+    #[derive(Clone)]
+    struct Digest;
+    impl FangAction for Digest {
+        async fn fore<'a>(&'a self, req: &'a mut Request) -> Result<(), Response> {
+            match req.payload() {
+                Some(b"hello") => Ok(()),
+                _ => Err(Response::BadRequest()),
+            }
+        }
+    }
+
+    /// `(written, status, payload is done)` of handling the request by the router
+    fn handle(ohkami: Ohkami, chunks: &[&str]) -> (Vec<u8>, Status, bool) {
+        let (router, _) = ohkami.into_router().finalize();
+
+        let config = crate::Config::new();
+        let mut stream = Split::new(chunks.iter().copied());
+
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        let mut req = Pin::new(&mut req);
+        let (status, done) = crate::__rt__::testing::block_on(async {
+            req.as_mut().read_head(&mut stream, &config).await.unwrap();
+            let res = router.handle(&mut req).await;
+            (res.status, req.__unread__.is_done())
+        });
+        (stream.written, status, done)
+    }
+
+    let ohkami = || {
+        Ohkami::new((
+            Auth,
+            "/load".POST(async |Text(body): Text<String>| body),
+            "/stream".POST(stream),
+        ))
+    };
+    let head = |path: &str| {
+        format!(
+            "POST {path} HTTP/1.1\r\nContent-Type: text/plain\r\nExpect: 100-continue\r\nContent-Length: 5\r\n"
+        )
+    };
+
+    for path in ["/load", "/stream"] {
+        assert_eq!(
+            handle(
+                ohkami(),
+                &[
+                    &format!("{}Authorization: Bearer x\r\n\r\n", head(path)),
+                    "hello"
+                ]
+            ),
+            (CONTINUE.to_vec(), Status::OK, true)
         );
     }
+
+    // loaded before fangs, so that they see the payload
+    assert_eq!(
+        handle(ohkami(), &[&format!("{}\r\n", head("/load")), "hello"]),
+        (CONTINUE.to_vec(), Status::Unauthorized, true)
+    );
+    assert_eq!(
+        handle(
+            Ohkami::new((Digest, "/load".POST(async |Text(body): Text<String>| body))),
+            &[&format!("{}\r\n", head("/load")), "hello"]
+        ),
+        (CONTINUE.to_vec(), Status::OK, true)
+    );
+
+    // streamed by the handler, so rejected by a fang without receiving the payload
+    assert_eq!(
+        handle(ohkami(), &[&format!("{}\r\n", head("/stream"))]),
+        (Vec::new(), Status::Unauthorized, false)
+    );
+
+    // not found without receiving the payload
+    assert_eq!(
+        handle(
+            ohkami(),
+            &[&format!(
+                "{}Authorization: Bearer x\r\n\r\n",
+                head("/unknown")
+            )]
+        ),
+        (Vec::new(), Status::NotFound, false)
+    );
+}
+
+#[test]
+fn test_parse_chunked_request() {
+    const CASE_1: &str = "\
+        POST /upload HTTP/1.1\r\n\
+        Host: localhost\r\n\
+        Content-Type: text/plain\r\n\
+        Transfer-Encoding: chunked\r\n\
+        \r\n\
+        5\r\n\
+        Hello\r\n\
+        8;ext=value\r\n\
+        , chunke\r\n\
+        1\r\n\
+        d\r\n\
+        0\r\n\
+        \r\n\
+    ";
+    assert_parse!(
+        CASE_1,
+        Request {
+            __buf__: metadataize(CASE_1),
+            __unread__: Default::default(),
+            version: super::Version::Http11,
+            method: Method::POST,
+            path: Path::from_literal("/upload"),
+            query: QueryParams::new(b""),
+            headers: RequestHeaders::from_iters(
+                [
+                    (RequestHeader::Host, "localhost"),
+                    (RequestHeader::ContentType, "text/plain"),
+                    (RequestHeader::TransferEncoding, "chunked"),
+                ],
+                None
+            ),
+            payload: Some(CowSlice::Own(Vec::from("Hello, chunked").into())),
+            context: Context::init(),
+            ip: crate::util::IP_0000,
+            forwarded: None,
+        }
+    );
+
+    const CASE_2: &str = "\
+        PUT /upload HTTP/1.1\r\n\
+        Host: localhost\r\n\
+        Transfer-Encoding: chunked\r\n\
+        Trailer: X-Checksum, Content-Type\r\n\
+        \r\n\
+        3\r\n\
+        abc\r\n\
+        0\r\n\
+        X-Checksum: 900150983cd24fb0\r\n\
+        Content-Type: text/html\r\n\
+        \r\n\
+    ";
+    assert_parse!(
+        CASE_2,
+        Request {
+            __buf__: metadataize(CASE_2),
+            __unread__: Default::default(),
+            version: super::Version::Http11,
+            method: Method::PUT,
+            path: Path::from_literal("/upload"),
+            query: QueryParams::new(b""),
+            headers: RequestHeaders::from_iters(
+                [
+                    (RequestHeader::Host, "localhost"),
+                    (RequestHeader::TransferEncoding, "chunked"),
+                    (RequestHeader::Trailer, "X-Checksum, Content-Type"),
+                ],
+                [("X-Checksum", "900150983cd24fb0")]
+            ),
+            payload: Some(CowSlice::Own(Vec::from("abc").into())),
+            context: Context::init(),
+            ip: crate::util::IP_0000,
+            forwarded: None,
+        }
+    );
+
+    const CASE_3: &str = "\
+        POST /empty HTTP/1.1\r\n\
+        Host: localhost\r\n\
+        Transfer-Encoding: chunked\r\n\
+        \r\n\
+        0\r\n\
+        \r\n\
+    ";
+    assert_parse!(
+        CASE_3,
+        Request {
+            __buf__: metadataize(CASE_3),
+            __unread__: Default::default(),
+            version: super::Version::Http11,
+            method: Method::POST,
+            path: Path::from_literal("/empty"),
+            query: QueryParams::new(b""),
+            headers: RequestHeaders::from_iters(
+                [
+                    (RequestHeader::Host, "localhost"),
+                    (RequestHeader::TransferEncoding, "chunked"),
+                ],
+                None
+            ),
+            payload: None,
+            context: Context::init(),
+            ip: crate::util::IP_0000,
+            forwarded: None,
+        }
+    );
+}
+
+#[test]
+fn test_parse_http10_and_absolute_form() {
+    use super::Version;
+    use crate::Status;
+
+    fn read(case: &str) -> Result<Request, Status> {
+        let config = crate::Config::new();
+        let mut case = case.as_bytes();
+
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        let result = crate::__rt__::testing::block_on(Pin::new(&mut req).read(&mut case, &config));
+        match result {
+            Ok(_) => Ok(req),
+            Err(res) => Err(res.status),
+        }
+    }
+
+    let req = read("GET /health HTTP/1.0\r\n\r\n").unwrap();
+    assert_eq!(req.version, Version::Http10);
+    assert_eq!(req.path.str(), "/health");
+    assert_eq!(req.headers.host(), None);
+
+    let req = read("GET /hello HTTP/1.1\r\nHost: localhost\r\n\r\n").unwrap();
+    assert_eq!(req.version, Version::Http11);
+
+    let req = read(
+        "GET http://example.com:8080/users/42?q=ohkami HTTP/1.1\r\nHost: other.example\r\n\r\n",
+    )
+    .unwrap();
+    assert_eq!(req.path.str(), "/users/42");
+    assert_eq!(req.query, QueryParams::new(b"q=ohkami"));
+    assert_eq!(req.headers.host(), Some("example.com:8080"));
+
+    let req = read("OPTIONS HTTPS://example.com HTTP/1.0\r\n\r\n").unwrap();
+    assert_eq!(req.version, Version::Http10);
+    assert_eq!(req.path.str(), "/");
+    assert_eq!(req.headers.host(), Some("example.com"));
+
+    let req = read("GET http://example.com?q=ohkami HTTP/1.1\r\n\r\n").unwrap();
+    assert_eq!(req.path.str(), "/");
+    assert_eq!(req.query, QueryParams::new(b"q=ohkami"));
+
+    assert_eq!(
+        read("GET http:///path HTTP/1.1\r\n\r\n").unwrap_err(),
+        Status::BadRequest
+    );
+    assert_eq!(
+        read("GET / HTTP/0.9\r\n\r\n").unwrap_err(),
+        Status::HTTPVersionNotSupported
+    );
+    assert_eq!(
+        read("GET / HTTP/2.0\r\n\r\n").unwrap_err(),
+        Status::HTTPVersionNotSupported
+    );
+    // HTTP/1.0 doesn't know chunked transfer coding ( RFC 9112, Section 6.1 )
+    assert_eq!(
+        read("POST / HTTP/1.0\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n").unwrap_err(),
+        Status::BadRequest
+    );
+}
+
+#[test]
+fn test_reject_invalid_chunked_request() {
+    use crate::{Response, Status};
+
+    fn read_status(case: &str, config: crate::Config) -> Status {
+        let mut case = case.as_bytes();
+
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        let mut req = Pin::new(&mut req);
+        let result = crate::__rt__::testing::block_on(req.as_mut().read(&mut case, &config));
+        result
+            .map(|_| Status::OK)
+            .unwrap_or_else(|res: Response| res.status)
+    }
+
+    // both `Transfer-Encoding` and `Content-Length`
+    assert_eq!(
+        read_status(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\nContent-Length: 3\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+            crate::Config::new()
+        ),
+        Status::BadRequest
+    );
+    // `chunked` is not the final coding
+    assert_eq!(
+        read_status(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked, gzip\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+            crate::Config::new()
+        ),
+        Status::BadRequest
+    );
+    // unsupported transfer coding
+    assert_eq!(
+        read_status(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: gzip, chunked\r\n\r\n3\r\nabc\r\n0\r\n\r\n",
+            crate::Config::new()
+        ),
+        Status::NotImplemented
+    );
+    // invalid chunk size
+    assert_eq!(
+        read_status(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\nxyz\r\nabc\r\n0\r\n\r\n",
+            crate::Config::new()
+        ),
+        Status::BadRequest
+    );
+    // chunk data longer than its size
+    assert_eq!(
+        read_status(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nabc\r\n0\r\n\r\n",
+            crate::Config::new()
+        ),
+        Status::BadRequest
+    );
+    // connection closed before the last chunk
+    assert_eq!(
+        read_status(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n",
+            crate::Config::new()
+        ),
+        Status::BadRequest
+    );
+    // exceeding `request_payload_limit`
+    assert_eq!(
+        read_status(
+            "POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n3\r\ndef\r\n0\r\n\r\n",
+            crate::Config {
+                request_payload_limit: 5,
+                ..crate::Config::new()
+            }
+        ),
+        Status::PayloadTooLarge
+    );
+}
+
+#[test]
+fn test_stream_payload() {
+    use crate::FromRequest;
+    use crate::claw::content::BodyStream;
+
+    fn read_streamed(
+        case: &str,
+        config: crate::Config,
+    ) -> (Result<Vec<u8>, std::io::ErrorKind>, bool) {
+        let mut stream = case.as_bytes();
+
+        let mut req = Request::uninit(crate::util::IP_0000, &config);
+        let mut req = Pin::new(&mut req);
+        crate::__rt__::testing::block_on(async {
+            assert_eq!(
+                req.as_mut().read_head(&mut stream, &config).await,
+                Ok(super::Head::Request)
+            );
+            assert!(req.payload.is_none());
+
+            let mut body = BodyStream::from_request(&req).unwrap().unwrap();
+            let mut buf = Vec::new();
+            let result = body
+                .read_to_end(&mut buf)
+                .await
+                .map(|_| buf)
+                .map_err(|e| e.kind());
+            (result, req.__unread__.is_done())
+        })
+    }
+
+    let small_buf = crate::Config {
+        request_bufsize: 64,
+        ..crate::Config::new()
+    };
+
+    // `Content-Length`, partly in the request buffer
+    assert_eq!(
+        read_streamed(
+            "POST /upload HTTP/1.1\r\nContent-Length: 40\r\n\r\n0123456789012345678901234567890123456789",
+            small_buf
+        ),
+        (
+            Ok(Vec::from("0123456789012345678901234567890123456789")),
+            true
+        )
+    );
+
+    // chunked with trailers, partly in the request buffer
+    assert_eq!(
+        read_streamed(
+            "POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n\
+            a\r\n0123456789\r\n10;ext\r\nabcdefghijklmnop\r\n0\r\nX-Checksum: 42\r\n\r\n",
+            small_buf
+        ),
+        (Ok(Vec::from("0123456789abcdefghijklmnop")), true)
+    );
+
+    // connection closed in the middle of the payload
+    assert_eq!(
+        read_streamed(
+            "POST /upload HTTP/1.1\r\nContent-Length: 40\r\n\r\n0123456789",
+            crate::Config::new()
+        ),
+        (Err(std::io::ErrorKind::UnexpectedEof), false)
+    );
+
+    // exceeding `request_payload_limit`
+    assert_eq!(
+        read_streamed(
+            "POST /upload HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n3\r\nabc\r\n3\r\ndef\r\n0\r\n\r\n",
+            crate::Config {
+                request_payload_limit: 5,
+                ..crate::Config::new()
+            }
+        ),
+        (Err(std::io::ErrorKind::InvalidData), false)
+    );
+}
+
+#[cfg(feature = "http2")]
+#[test]
+fn test_h2c_preface() {
+    use super::Head;
+
+    let config = crate::Config::new();
+    let mut req = Request::uninit(crate::util::IP_0000, &config);
+    let mut req = Pin::new(&mut req);
+
+    let mut stream: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n\x00\x00\x00\x04\x00\x00\x00\x00\x00";
+    crate::__rt__::testing::block_on(async {
+        assert_eq!(
+            req.as_mut().read_head(&mut stream, &config).await,
+            Ok(Head::H2c(33))
+        );
+    });
 }
@@ -0,0 +1,198 @@
+#![cfg(feature = "__rt_native__")]
+
+//! Decoder of `Transfer-Encoding: chunked` request body
+//! ( <https://httpwg.org/specs/rfc9112.html#chunked.encoding> )
+
+use crate::__rt__::AsyncRead;
+use crate::Response;
+
+/// max size of a `chunk-size [ chunk-ext ] CRLF` line
+pub(super) const MAX_CHUNK_SIZE_LINE: usize = 1 << 12; // 4 KiB
+
+pub(super) struct Chunked {
+    pub(super) data: Vec<u8>,
+    /// raw bytes of `trailer-section`, excluding the last CRLF
+    pub(super) trailers: Vec<u8>,
+    /// bytes read beyond the body
+    pub(super) leftover: Vec<u8>,
+}
+
+/// Decode a chunked body from `remaining_buf` ( bytes already read
+/// into the request buffer after the head ) followed by `stream`.
+///
+/// `max_trailers_size` is the available space to hold the trailer section.
+pub(super) async fn decode(
+    stream: &mut (impl AsyncRead + Unpin),
+    remaining_buf: &[u8],
+    payload_limit: usize,
+    max_trailers_size: usize,
+) -> Result<Chunked, Response> {
+    let mut source = Source {
+        stream,
+        buf: remaining_buf.to_vec(),
+        pos: 0,
+    };
+
+    let mut data = Vec::new();
+    loop {
+        let size = {
+            let line = source
+                .read_line(MAX_CHUNK_SIZE_LINE, Response::BadRequest)
+                .await?;
+            parse_chunk_size(line).ok_or_else(|| {
+                crate::WARNING!(
+                    "[Request::read] Invalid chunk size: `{}`",
+                    line.escape_ascii()
+                );
+                Response::BadRequest()
+            })?
+        };
+        if size == 0 {
+            break;
+        }
+
+        if data.len().saturating_add(size) > payload_limit {
+            crate::WARNING!(
+                "[Request::read] Chunked payload exceeded `request_payload_limit` ({payload_limit} bytes)"
+            );
+            return Err(Response::PayloadTooLarge());
+        }
+        source.read_exact_into(&mut data, size).await?;
+
+        if !source.read_line(0, Response::BadRequest).await?.is_empty() {
+            crate::WARNING!("[Request::read] Chunk data is not followed by CRLF");
+            return Err(Response::BadRequest());
+        }
+    }
+
+    let mut trailers = Vec::new();
+    loop {
+        let line = source
+            .read_line(
+                max_trailers_size.saturating_sub(trailers.len() + 2),
+                Response::RequestHeaderFieldsTooLarge,
+            )
+            .await?;
+        if line.is_empty() {
+            break;
+        }
+        trailers.extend_from_slice(line);
+        trailers.extend_from_slice(b"\r\n");
+    }
+
+    let leftover = source.buf.split_off(source.pos);
+    Ok(Chunked {
+        data,
+        trailers,
+        leftover,
+    })
+}
+
+/// `chunk-size [ chunk-ext ]`, where `chunk-size = 1*HEXDIG`
+pub(super) fn parse_chunk_size(line: &[u8]) -> Option<usize> {
+    let hex_len = line.iter().take_while(|b| b.is_ascii_hexdigit()).count();
+    if hex_len == 0 {
+        return None;
+    }
+
+    let (hex, ext) = line.split_at(hex_len);
+    if !matches!(ext.trim_ascii_start().first(), None | Some(b';')) {
+        return None;
+    }
+
+    hex.iter().try_fold(0usize, |size, b| {
+        let digit = (*b as char).to_digit(16)? as usize;
+        size.checked_mul(16)?.checked_add(digit)
+    })
+}
+
+struct Source<'s, S> {
+    stream: &'s mut S,
+    buf: Vec<u8>,
+    pos: usize,
+}
+
+impl<S: AsyncRead + Unpin> Source<'_, S> {
+    async fn fill(&mut self) -> Result<(), Response> {
+        if self.pos > 0 {
+            self.buf.drain(..self.pos);
+            self.pos = 0;
+        }
+
+        let mut chunk = [0; 1 << 10];
+        match self.stream.read(&mut chunk).await {
+            Ok(0) => {
+                crate::WARNING!(
+                    "[Request::read] Connection closed in the middle of chunked payload"
+                );
+                Err(Response::BadRequest())
+            }
+            Ok(n) => {
+                self.buf.extend_from_slice(&chunk[..n]);
+                Ok(())
+            }
+            Err(e) => {
+                crate::ERROR!("[Request::read] Failed to read chunked payload from stream: {e}");
+                Err(Response::BadRequest())
+            }
+        }
+    }
+
+    /// read a line terminated by CRLF and return it without the CRLF,
+    /// or `too_long()` if the line is longer than `max_len`
+    async fn read_line(
+        &mut self,
+        max_len: usize,
+        too_long: fn() -> Response,
+    ) -> Result<&[u8], Response> {
+        loop {
+            let unread = &self.buf[self.pos..];
+            let scan = &unread[..unread.len().min(max_len + 2)];
+            if let Some(len) = scan.windows(2).position(|w| w == b"\r\n") {
+                let start = self.pos;
+                self.pos += len + 2;
+                return Ok(&self.buf[start..start + len]);
+            }
+            if scan.len() == max_len + 2 {
+                break;
+            }
+            self.fill().await?;
+        }
+
+        crate::WARNING!("[Request::read] Too long line in chunked payload");
+        Err(too_long())
+    }
+
+    async fn read_exact_into(&mut self, data: &mut Vec<u8>, size: usize) -> Result<(), Response> {
+        let buffered = (self.buf.len() - self.pos).min(size);
+        data.extend_from_slice(&self.buf[self.pos..self.pos + buffered]);
+        self.pos += buffered;
+
+        if buffered < size {
+            let start = data.len();
+            data.resize(start + (size - buffered), 0);
+            if let Err(e) = self.stream.read_exact(&mut data[start..]).await {
+                crate::ERROR!("[Request::read] Failed to read chunked payload from stream: {e}");
+                return Err(Response::BadRequest());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+#[test]
+fn test_parse_chunk_size() {
+    assert_eq!(parse_chunk_size(b"0"), Some(0));
+    assert_eq!(parse_chunk_size(b"1a"), Some(26));
+    assert_eq!(parse_chunk_size(b"FF"), Some(255));
+    assert_eq!(parse_chunk_size(b"10;name=value"), Some(16));
+    assert_eq!(parse_chunk_size(b"10 ;name"), Some(16));
+
+    assert_eq!(parse_chunk_size(b""), None);
+    assert_eq!(parse_chunk_size(b";ext"), None);
+    assert_eq!(parse_chunk_size(b"1g"), None);
+    assert_eq!(parse_chunk_size(b"-1"), None);
+    assert_eq!(parse_chunk_size(b"ffffffffffffffffffff"), None);
+}
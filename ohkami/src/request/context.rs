@@ -15,6 +15,11 @@ pub struct Context {
 
     #[cfg(feature = "rt_lambda")]
     lambda: Option<Box<crate::x_lambda::LambdaHTTPRequestContext>>,
+
+    #[cfg(feature = "__rt_native__")]
+    health: crate::Health,
+    #[cfg(feature = "__rt_native__")]
+    connection: Option<crate::ConnectionInfo>,
 }
 
 impl Context {
@@ -28,6 +33,11 @@ impl Context {
 
             #[cfg(feature = "rt_lambda")]
             lambda: None,
+
+            #[cfg(feature = "__rt_native__")]
+            health: crate::Health::always(),
+            #[cfg(feature = "__rt_native__")]
+            connection: None,
         }
     }
 
@@ -41,6 +51,12 @@ impl Context {
         self.lambda = Some(Box::new(request_context));
     }
 
+    #[cfg(feature = "__rt_native__")]
+    pub(crate) fn load_server(&mut self, health: crate::Health, connection: crate::ConnectionInfo) {
+        self.health = health;
+        self.connection = Some(connection);
+    }
+
     #[cfg(feature = "__rt_native__")]
     pub(super) fn clear(&mut self) {
         if let Some(map) = &mut self.store {
@@ -92,4 +108,30 @@ impl Context {
         // SAFETY: User can touch here **ONLY AFTER `Self::load`** called by `Request`
         unsafe { self.lambda.as_ref().unwrap_unchecked() }
     }
+
+    #[cfg(feature = "__rt_native__")]
+    #[inline(always)]
+    pub fn health(&self) -> &crate::Health {
+        &self.health
+    }
+
+    #[cfg(feature = "__rt_native__")]
+    #[inline(always)]
+    pub fn listen_addr(&self) -> Option<&crate::ListenAddr> {
+        self.connection
+            .as_ref()
+            .map(crate::ConnectionInfo::listen_addr)
+    }
+
+    #[cfg(feature = "__rt_native__")]
+    #[inline(always)]
+    pub fn proxy(&self) -> Option<&crate::ProxyHeader> {
+        self.connection.as_ref()?.proxy()
+    }
+
+    #[cfg(feature = "__rt_native__")]
+    #[inline(always)]
+    pub fn connection(&self) -> Option<&crate::ConnectionInfo> {
+        self.connection.as_ref()
+    }
 }
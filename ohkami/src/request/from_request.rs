@@ -50,6 +50,13 @@ pub trait FromRequest<'req>: Sized {
     fn n_pathparams() -> usize {
         0
     }
+
+    #[doc(hidden)]
+    /// intent to be used by `claw::content::BodyStream` : when `true`, the router
+    /// doesn't load the request payload before calling the handler
+    fn streams_payload() -> bool {
+        false
+    }
 }
 const _: () = {
     impl<'req> FromRequest<'req> for &'req Request {
@@ -73,6 +80,10 @@ const _: () = {
         fn openapi_inbound() -> openapi::Inbound {
             FR::openapi_inbound()
         }
+
+        fn streams_payload() -> bool {
+            FR::streams_payload()
+        }
     }
 };
 #[cfg(feature = "rt_worker")]
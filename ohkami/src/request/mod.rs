@@ -19,6 +19,12 @@ pub use context::Context;
 mod from_request;
 pub use from_request::FromRequest;
 
+#[cfg(feature = "__rt_native__")]
+mod chunked;
+
+#[cfg(feature = "__rt_native__")]
+pub(crate) mod payload;
+
 #[cfg(test)]
 mod _test_extract;
 #[cfg(test)]
@@ -95,12 +101,18 @@ pub struct Request {
     #[cfg(feature = "__rt_native__")]
     pub(super) __buf__: Box<[u8]>,
 
+    #[cfg(feature = "__rt_native__")]
+    pub(crate) __unread__: payload::UnreadPayload,
+
     #[cfg(feature = "rt_worker")]
     pub(super) __url__: std::mem::MaybeUninit<::worker::Url>,
 
     #[cfg(feature = "rt_lambda")]
     pub(super) __query__: std::mem::MaybeUninit<Box<str>>,
 
+    #[cfg(feature = "__rt_native__")]
+    pub(crate) version: Version,
+
     /// HTTP method of this request
     ///
     /// **Note** : In current version, custom HTTP methods are *not supported*,
@@ -113,9 +125,9 @@ pub struct Request {
     /// - `.params()` to iterate path params
     /// - `.str()` to ( URL-decode and ) get as `&str`
     ///
-    /// **Note** : In current version, path with schema and origin in request line
-    /// is *not supported*, in other words, now Ohkami just handles requests like
-    /// `GET /path HTTP/1.1`, not `GET http://whatthe.fxxx/path HTTP/1.1`
+    /// **Note** : For an absolute-form request target like
+    /// `GET http://example.com/path HTTP/1.1`, this is the path part ( `/path` )
+    /// and the authority part ( `example.com` ) replaces the `Host` header.
     pub path: Path,
 
     /// Query params of this request
@@ -159,6 +171,15 @@ pub struct Request {
     ///
     /// In `rt_lambda`:
     /// - `.lambda()` to get `requestContext` of Lambda request
+    ///
+    /// On native runtimes:
+    /// - `.health()` to get [`Health`](crate::Health) of the server
+    /// - `.listen_addr()` to get [`ListenAddr`](crate::ListenAddr) of the listener
+    ///   the request came in on
+    /// - `.proxy()` to get [`ProxyHeader`](crate::ProxyHeader) of the connection
+    ///   when `proxy_protocol` of `Config` is enabled
+    /// - `.connection()` to get [`ConnectionInfo`](crate::ConnectionInfo) like
+    ///   the local address and TLS session
     pub context: Context,
 
     /// Remote ( directly connected ) peer's IP address
@@ -166,8 +187,54 @@ pub struct Request {
     /// Default value is `0.0.0.0`. this will be seen in testing or when Cloudlare Workers
     /// doesn't show ip.
     ///
-    /// **NOTE** : If a proxy is in front of Ohkami, this will be the proxy's address
+    /// **NOTE** : If a proxy is in front of Ohkami, this will be the proxy's address,
+    /// unless the proxy sends PROXY protocol header and `proxy_protocol` of `Config` is enabled
+    ///
+    /// **NOTE** : For a connection over Unix domain socket, this is `127.0.0.1`
+    ///
+    /// **NOTE** : [`fang::TrustedProxy`](crate::fang::TrustedProxy) rewrites this
+    /// to the client's address from `Forwarded` or `X-Forwarded-For` header
     pub ip: std::net::IpAddr,
+
+    /// scheme and host of the original request resolved by `fang::TrustedProxy`
+    pub(crate) forwarded: Option<Box<Forwarded>>,
+}
+
+/// Scheme and host of the original request, reported by a trusted proxy
+#[derive(Default)]
+pub(crate) struct Forwarded {
+    pub(crate) proto: Option<Box<str>>,
+    pub(crate) host: Option<Box<str>>,
+}
+
+/// What `Request::read_head` has read from a connection
+#[cfg(feature = "__rt_native__")]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub(crate) enum Head {
+    /// a request head to be handled
+    Request,
+    /// the connection is closed, or doesn't send any request to be handled
+    None,
+    /// HTTP/2 connection preface ( h2c with prior knowledge ),
+    /// with the number of bytes read into the request buffer
+    #[cfg(feature = "http2")]
+    H2c(usize),
+}
+
+/// HTTP version of a request read by `Request::read_head`
+/// ( HTTP/2 requests are read as HTTP/1.1 messages )
+#[cfg(feature = "__rt_native__")]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub(crate) enum Version {
+    Http10,
+    Http11,
+}
+
+/// `tchar` of field names ( RFC 9110, Section 5.6.2 )
+#[cfg(feature = "__rt_native__")]
+#[inline]
+fn is_tchar(b: &u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(b)
 }
 
 impl Request {
@@ -219,10 +286,14 @@ impl Request {
 
             #[cfg(feature = "__rt_native__")]
             __buf__: vec![0u8; config.request_bufsize].into_boxed_slice(),
+            #[cfg(feature = "__rt_native__")]
+            __unread__: payload::UnreadPayload::default(),
             #[cfg(feature = "rt_worker")]
             __url__: std::mem::MaybeUninit::uninit(),
             #[cfg(feature = "rt_lambda")]
             __query__: std::mem::MaybeUninit::uninit(),
+            #[cfg(feature = "__rt_native__")]
+            version: Version::Http11,
 
             method: Method::GET,
             path: Path::uninit(),
@@ -230,6 +301,7 @@ impl Request {
             headers: RequestHeaders::new(),
             payload: None,
             context: Context::init(),
+            forwarded: None,
         }
     }
 
@@ -248,102 +320,518 @@ impl Request {
             self.headers.clear();
             self.payload = None;
             self.context.clear();
+            self.forwarded = None;
         } /* else: just after `init`ed or `clear`ed */
+        self.__unread__.clear();
+        self.version = Version::Http11;
     }
 
     #[cfg(feature = "__rt_native__")]
+    #[cfg(debug_assertions/* for `ohkami::testing` */)]
+    /// Read a request including its payload
     pub(crate) async fn read(
         mut self: Pin<&mut Self>,
-        stream: &mut (impl AsyncRead + Unpin),
+        stream: &mut impl payload::ReadStream,
         config: &crate::Config,
     ) -> Result<Option<()>, crate::Response> {
+        let read = self.as_mut().read_head(stream, config).await?;
+        self.load_payload().await?;
+        Ok(matches!(read, Head::Request).then_some(()))
+    }
+
+    /// Read a request head, leaving its payload on `stream` to be loaded
+    /// by `load_payload` or streamed by `claw::content::BodyStream`.
+    ///
+    /// `stream` must not be used by others until the request is cleared
+    /// by `clear` ( see `payload::StreamRef` ).
+    #[cfg(feature = "__rt_native__")]
+    pub(crate) async fn read_head<'s>(
+        mut self: Pin<&mut Self>,
+        stream: &'s mut (impl payload::ReadStream + 's),
+        config: &crate::Config,
+    ) -> Result<Head, crate::Response> {
         use crate::Response;
 
-        match stream.read(&mut self.__buf__).await {
-            Ok(0) => return Ok(None),
-            Err(e) => {
-                return match e.kind() {
-                    std::io::ErrorKind::ConnectionReset => Ok(None),
-                    _ => Err({
-                        crate::WARNING!("Failed to read stream: {e}");
-                        Response::InternalServerError()
-                    }),
-                };
-            }
-            _ => (),
+        // bytes of this request read with the previous one ( pipelining )
+        let leftover = self.__unread__.take_leftover();
+
+        if self.__buf__.len() > config.request_bufsize || self.__buf__.len() < leftover.len() {
+            // shrink the buffer grown for a previous request
+            self.__buf__ = vec![0u8; config.request_bufsize.max(leftover.len())].into_boxed_slice();
         }
+        self.__buf__[..leftover.len()].copy_from_slice(&leftover);
+
+        // wait for the first byte up to `keepalive_timeout`, and then
+        // for the whole head up to `request_head_timeout`
+        let head_timeout = std::time::Duration::from_secs(config.request_head_timeout);
+        let mut head_deadline =
+            (!leftover.is_empty()).then(|| std::time::Instant::now() + head_timeout);
+
+        // read until the end of the head ( an empty line ), growing the buffer
+        // up to `max_request_head_size` if needed
+        let mut read_len = leftover.len();
+        let mut scan_start = 0;
+        let head_len = loop {
+            // ignore empty lines before the request line ( RFC 9112, Section 2.2 )
+            while self.__buf__[..read_len].starts_with(b"\r\n") {
+                self.__buf__.copy_within(2..read_len, 0);
+                read_len -= 2;
+            }
+            scan_start = scan_start.min(read_len.saturating_sub(3));
+
+            if let Some(pos) = self.__buf__[scan_start..read_len]
+                .windows(4)
+                .position(|w| w == b"\r\n\r\n")
+            {
+                break scan_start + pos + 4;
+            }
+            scan_start = read_len.saturating_sub(3);
+
+            if read_len == self.__buf__.len() {
+                if read_len >= config.max_request_head_size {
+                    crate::WARNING!(
+                        "\
+                        [Request::read] Request head exceeded `max_request_head_size` ({} bytes). \
+                        Try setting `max_request_head_size` of Config, \
+                        or `OHKAMI_MAX_REQUEST_HEAD_SIZE` environment variable, \
+                        to a larger value (default: {}).\
+                    ",
+                        config.max_request_head_size,
+                        crate::Config::default().max_request_head_size
+                    );
+                    return Err(Response::RequestHeaderFieldsTooLarge());
+                }
+                let mut grown = vec![0u8; (read_len * 2).min(config.max_request_head_size)];
+                grown[..read_len].copy_from_slice(&self.__buf__[..read_len]);
+                self.__buf__ = grown.into_boxed_slice();
+            }
+
+            let timeout = match head_deadline {
+                None => std::time::Duration::from_secs(config.keepalive_timeout),
+                Some(deadline) => deadline.saturating_duration_since(std::time::Instant::now()),
+            };
+            let Some(read) =
+                crate::util::with_timeout(timeout, stream.read(&mut self.__buf__[read_len..]))
+                    .await
+            else {
+                if head_deadline.is_none() {
+                    crate::DEBUG!(
+                        "\
+                        Reached Keep-Alive timeout ({} secs). \
+                        The timeout can be configured via `keepalive_timeout` of `Config`, \
+                        or `OHKAMI_KEEPALIVE_TIMEOUT` environment variable \
+                        (default: {}).\
+                    ",
+                        config.keepalive_timeout,
+                        crate::Config::default().keepalive_timeout
+                    );
+                    return Ok(Head::None);
+                }
+                crate::WARNING!(
+                    "\
+                    [Request::read] Request head didn't complete within `request_head_timeout` ({} secs). \
+                    The timeout can be configured via `request_head_timeout` of `Config`, \
+                    or `OHKAMI_REQUEST_HEAD_TIMEOUT` environment variable \
+                    (default: {}).\
+                ",
+                    config.request_head_timeout,
+                    crate::Config::default().request_head_timeout
+                );
+                return Err(Response::RequestTimeout());
+            };
+            match read {
+                Ok(0) if read_len == 0 => return Ok(Head::None),
+                Ok(0) => {
+                    crate::WARNING!(
+                        "[Request::read] Connection closed in the middle of request head"
+                    );
+                    return Err(Response::BadRequest());
+                }
+                Ok(n) => {
+                    read_len += n;
+                    head_deadline.get_or_insert_with(|| std::time::Instant::now() + head_timeout);
+                }
+                Err(e) => {
+                    return match e.kind() {
+                        std::io::ErrorKind::ConnectionReset => Ok(Head::None),
+                        _ => Err({
+                            crate::WARNING!("Failed to read stream: {e}");
+                            Response::InternalServerError()
+                        }),
+                    };
+                }
+            }
+        };
 
         let mut r = Reader::new(unsafe {
             // pass detouched bytes
             // to resolve immutable/mutable borrowing
             //
             // SAFETY: `self.__buf__` itself is immutable
-            Slice::from_bytes(&self.__buf__).as_bytes()
+            Slice::from_bytes(&self.__buf__[..head_len]).as_bytes()
         });
 
+        #[cfg(feature = "http2")]
+        if r.remaining().starts_with(b"PRI * HTTP/2.0\r\n") {
+            return Ok(Head::H2c(read_len));
+        }
+
         match Method::from_bytes(r.read_while(|b| b != &b' ')) {
-            None => return Ok(None),
+            None => return Ok(Head::None),
             Some(method) => self.method = method,
         }
 
         r.next_if(|b| *b == b' ').ok_or_else(Response::BadRequest)?;
 
-        self.path
-            .init_with_request_bytes(r.read_while(|b| !matches!(b, b' ' | b'?')))?;
+        // absolute-form ( RFC 9112, Section 3.2.2 ), mainly sent to proxies
+        let authority = {
+            let target = r.remaining();
+            let scheme_len = if target.len() >= 7 && target[..7].eq_ignore_ascii_case(b"http://") {
+                7
+            } else if target.len() >= 8 && target[..8].eq_ignore_ascii_case(b"https://") {
+                8
+            } else {
+                0
+            };
+            if scheme_len > 0 {
+                r.advance_by(scheme_len);
+                let authority = r.read_while(|b| !matches!(b, b'/' | b'?' | b' '));
+                if authority.is_empty() {
+                    return Err(Response::BadRequest());
+                }
+                Some(authority)
+            } else {
+                None
+            }
+        };
 
-        if r.consume_oneof([" ", "?"]).unwrap() == 1 {
-            self.query = QueryParams::new(r.read_while(|b| b != &b' '));
-            r.advance_by(1);
+        let path = r.read_while(|b| !matches!(b, b' ' | b'?' | b'\r' | b'\n'));
+        self.path.init_with_request_bytes(match path {
+            b"" if authority.is_some() => b"/",
+            _ => path,
+        })?;
+
+        if r.consume_oneof([" ", "?"])
+            .ok_or_else(Response::BadRequest)?
+            == 1
+        {
+            self.query = QueryParams::new(r.read_while(|b| !matches!(b, b' ' | b'\r' | b'\n')));
+            r.consume(" ").ok_or_else(Response::BadRequest)?;
         }
 
-        r.consume("HTTP/1.1\r\n")
-            .ok_or_else(Response::HTTPVersionNotSupported)?;
+        self.version = match r.consume_oneof(["HTTP/1.1\r\n", "HTTP/1.0\r\n"]) {
+            Some(0) => Version::Http11,
+            Some(_) => Version::Http10,
+            None => return Err(Response::HTTPVersionNotSupported()),
+        };
+
+        let malformed = |reason: &str, line: &[u8]| {
+            let line = &line[..line.iter().position(|b| *b == b'\r').unwrap_or(line.len())];
+            crate::WARNING!(
+                "[Request::read] Malformed header field ({reason}): `{}`",
+                line.escape_ascii()
+            );
+            Response::BadRequest()
+        };
 
+        // field-line = field-name ":" OWS field-value OWS ( RFC 9112, Section 5 )
+        let mut n_headers = 0;
         while r.consume("\r\n").is_none() {
-            let key_bytes = r.read_while(|b| b != &b':');
-            r.consume(": ").ok_or_else(|| {
+            n_headers += 1;
+            if n_headers > config.max_request_headers {
                 crate::WARNING!(
                     "\
-                    [Request::read] Unexpected end of headers! \
-                    Maybe request buffer size is not enough. \
-                    Try setting `request_bufsize` of Config, \
-                    or `OHKAMI_REQUEST_BUFSIZE` environment variable, \
+                    [Request::read] Request has more header fields than `max_request_headers` ({}). \
+                    Try setting `max_request_headers` of Config, \
+                    or `OHKAMI_MAX_REQUEST_HEADERS` environment variable, \
                     to a larger value (default: {}).\
                 ",
-                    crate::Config::default().request_bufsize
+                    config.max_request_headers,
+                    crate::Config::default().max_request_headers
                 );
-                Response::RequestHeaderFieldsTooLarge()
-            })?;
+                return Err(Response::RequestHeaderFieldsTooLarge());
+            }
 
-            let value = CowSlice::Ref(Slice::from_bytes(r.read_while(|b| b != &b'\r')));
-            r.consume("\r\n").ok_or_else(|| {
-                crate::WARNING!(
-                    "\
-                    [Request::read] Unexpected end of headers! \
-                    Maybe request buffer size is not enough. \
-                    Try setting `request_bufsize` of Config, \
-                    or `OHKAMI_REQUEST_BUFSIZE` environment variable, \
-                    to a larger value (default: {}).\
-                ",
-                    crate::Config::default().request_bufsize
-                );
-                Response::RequestHeaderFieldsTooLarge()
-            })?;
+            // SAFETY: `r.remaining()` is a part of `self.__buf__`
+            let line = unsafe { Slice::from_bytes(r.remaining()).as_bytes() };
 
+            if matches!(r.peek(), Some(b' ' | b'\t')) {
+                // obs-fold, or whitespace between the start line and
+                // the first field line ( RFC 9112, Section 2.2, 5.2 )
+                return Err(malformed("obsolete line folding", line));
+            }
+
+            let key_bytes = r.read_while(|b| !matches!(b, b':' | b'\r' | b'\n'));
+            if r.consume(":").is_none() {
+                return Err(malformed("no colon", line));
+            }
+            if key_bytes.is_empty() || !key_bytes.iter().all(is_tchar) {
+                // including whitespace between the name and colon
+                return Err(malformed("invalid name", line));
+            }
+
+            r.skip_while(|b| matches!(b, b' ' | b'\t'));
+            let value_bytes = r.read_while(|b| !matches!(b, b'\r' | b'\n'));
+            if r.consume("\r\n").is_none() {
+                return Err(malformed("bare CR or LF", line));
+            }
+            if value_bytes.contains(&b'\0') {
+                return Err(malformed("NUL in value", line));
+            }
+            let value = CowSlice::Ref(Slice::from_bytes(value_bytes.trim_ascii_end()));
+
+            // repeated field lines are combined into a comma-separated list
+            // ( RFC 9110, Section 5.3 )
             if let Some(key) = RequestHeader::from_bytes(key_bytes) {
                 self.headers.append(key, value);
             } else {
                 self.headers
-                    .insert_custom(Slice::from_bytes(key_bytes), value)
+                    .append_custom(Slice::from_bytes(key_bytes), value)
             }
         }
 
-        if let Some(payload_size) = self.get_payload_size(config)? {
-            self.payload =
-                Some(Request::read_payload(stream, r.remaining(), payload_size.get()).await?);
+        if let Some(authority) = authority {
+            // the target's authority takes precedence over `Host` header
+            self.headers.insert(
+                RequestHeader::Host,
+                CowSlice::Ref(Slice::from_bytes(authority)),
+            );
         }
 
-        Ok(Some(()))
+        let framing = if self.headers.transfer_encoding().is_some() {
+            if self.version == Version::Http10 {
+                crate::WARNING!("[Request::read] HTTP/1.0 request with `Transfer-Encoding`");
+                return Err(Response::BadRequest());
+            }
+            self.check_transfer_encoding()?;
+            Some(payload::Framing::Chunked)
+        } else {
+            (self.get_payload_size(config)?).map(|size| payload::Framing::Length(size.get()))
+        };
+        if let Some(framing) = framing {
+            self.__unread__.set(payload::Unread {
+                // SAFETY: the stream outlives handling this request
+                // ( see the doc comment )
+                stream: unsafe { payload::StreamRef::new(stream) },
+                buffered: Slice::from_bytes(&self.__buf__[head_len..read_len]),
+                read_len,
+                framing,
+                limit: config.request_payload_limit,
+                timeout: config.request_body_timeout,
+                // HTTP/1.0 clients don't understand `100 Continue`, and
+                // no need to send it when the payload is already arriving
+                expect_continue: self.version == Version::Http11
+                    && head_len == read_len
+                    && self
+                        .headers
+                        .expect()
+                        .is_some_and(|e| e.eq_ignore_ascii_case("100-continue")),
+            });
+        } else {
+            // the next pipelined request
+            self.__unread__
+                .set_leftover(&self.__buf__[head_len..read_len]);
+        }
+
+        Ok(Head::Request)
+    }
+
+    /// Load the payload left on the stream by `read_head` into `self.payload`,
+    /// unless it has been taken by `claw::content::BodyStream`
+    #[cfg(feature = "__rt_native__")]
+    pub(crate) async fn load_payload(mut self: Pin<&mut Self>) -> Result<(), crate::Response> {
+        let Some(payload::Unread {
+            mut stream,
+            buffered,
+            read_len,
+            framing,
+            limit,
+            timeout,
+            expect_continue,
+        }) = self.__unread__.take()
+        else {
+            return Ok(());
+        };
+
+        // SAFETY: `stream` is valid while handling this request ( see `read_head` )
+        let stream = &mut unsafe { stream.get() }.get_mut();
+        let remaining_buf = unsafe { buffered.as_bytes() };
+
+        if expect_continue {
+            let mut written = 0;
+            if let Err(e) = std::future::poll_fn(|cx| {
+                Pin::new(&mut **stream).poll_write_continue(cx, &mut written)
+            })
+            .await
+            {
+                crate::WARNING!("[Request::read] Failed to send `100 Continue`: {e}");
+                self.__unread__.set_partial();
+                return Err(crate::Response::InternalServerError());
+            }
+        }
+
+        let read = async {
+            match framing {
+                payload::Framing::Length(size) => {
+                    Request::read_payload(stream, remaining_buf, size)
+                        .await
+                        .map(|payload| {
+                            if let Some(leftover) = remaining_buf.get(size..) {
+                                self.__unread__.set_leftover(leftover);
+                            }
+                            self.payload = Some(payload)
+                        })
+                }
+                payload::Framing::Chunked => {
+                    self.as_mut()
+                        .read_chunked_payload(stream, remaining_buf, read_len, limit)
+                        .await
+                }
+            }
+        };
+        let result = match timeout {
+            0 => read.await,
+            secs => crate::util::with_timeout(std::time::Duration::from_secs(secs), read)
+                .await
+                .unwrap_or_else(|| {
+                    crate::WARNING!(
+                        "\
+                        [Request::read] Request payload didn't complete within `request_body_timeout` ({} secs). \
+                        The timeout can be configured via `request_body_timeout` of `Config`, \
+                        or `OHKAMI_REQUEST_BODY_TIMEOUT` environment variable \
+                        (default: 0, no timeout).\
+                    ",
+                        secs
+                    );
+                    Err(crate::Response::RequestTimeout())
+                }),
+        };
+        if result.is_err() {
+            self.__unread__.set_partial();
+        }
+        result
+    }
+
+    /// Validate `Transfer-Encoding` of a request
+    #[cfg(feature = "__rt_native__")]
+    fn check_transfer_encoding(&self) -> Result<(), crate::Response> {
+        use crate::Response;
+
+        let transfer_encoding = self.headers.transfer_encoding().unwrap_or_default();
+
+        // reject `Transfer-Encoding` with `Content-Length`, that is
+        // a typical way of request smuggling
+        if self.headers.content_length().is_some() {
+            crate::WARNING!(
+                "[Request::read] Request has both `Transfer-Encoding` and `Content-Length`"
+            );
+            return Err(Response::BadRequest());
+        }
+
+        // `chunked` must be the final transfer coding of a request
+        let mut codings = transfer_encoding.rsplit(',').map(str::trim);
+        if !codings
+            .next()
+            .is_some_and(|last| last.eq_ignore_ascii_case("chunked"))
+        {
+            crate::WARNING!(
+                "[Request::read] Transfer-Encoding `{transfer_encoding}` doesn't end with `chunked`"
+            );
+            return Err(Response::BadRequest());
+        }
+        if codings.next().is_some() {
+            return Err(Response::NotImplemented()
+                .with_text("Transfer codings other than `chunked` are not supported"));
+        }
+
+        // same as positive `Content-Length`
+        if matches!(self.method, Method::GET | Method::HEAD | Method::OPTIONS) {
+            return Err(Response::BadRequest());
+        }
+
+        Ok(())
+    }
+
+    /// Read `Transfer-Encoding: chunked` payload and merge its trailer fields
+    /// into `self.headers`.
+    ///
+    /// `remaining_buf` is the bytes already read into `self.__buf__` after the head,
+    /// ending at `read_len`.
+    #[cfg(feature = "__rt_native__")]
+    async fn read_chunked_payload(
+        mut self: Pin<&mut Self>,
+        stream: &mut (impl AsyncRead + Unpin),
+        remaining_buf: &[u8],
+        read_len: usize,
+        payload_limit: usize,
+    ) -> Result<(), crate::Response> {
+        use crate::Response;
+
+        let chunked::Chunked {
+            data,
+            trailers,
+            leftover,
+        } = chunked::decode(
+            stream,
+            remaining_buf,
+            payload_limit,
+            self.__buf__.len() - read_len,
+        )
+        .await?;
+
+        self.__unread__.set_leftover(&leftover);
+
+        if !data.is_empty() {
+            self.payload = Some(CowSlice::Own(data.into_boxed_slice()));
+        }
+
+        if !trailers.is_empty() {
+            // hold the trailer section in the unused space of `self.__buf__`
+            // so that the fields can be referenced just like headers
+            let trailers = {
+                let space = &mut self.__buf__[read_len..read_len + trailers.len()];
+                space.copy_from_slice(&trailers);
+                unsafe { Slice::from_bytes(space).as_bytes() }
+            };
+
+            let mut r = Reader::new(trailers);
+            while !r.remaining().is_empty() {
+                let key_bytes = r.read_while(|b| b != &b':');
+                r.consume(":").ok_or_else(Response::BadRequest)?;
+                let value = r.read_while(|b| b != &b'\r').trim_ascii();
+                r.consume("\r\n").ok_or_else(Response::BadRequest)?;
+
+                match RequestHeader::from_bytes(key_bytes) {
+                    // fields that must not be sent in trailers
+                    // ( https://httpwg.org/specs/rfc9110.html#trailers.limitations )
+                    Some(
+                        RequestHeader::TransferEncoding
+                        | RequestHeader::ContentLength
+                        | RequestHeader::ContentType
+                        | RequestHeader::ContentEncoding
+                        | RequestHeader::Trailer
+                        | RequestHeader::Host
+                        | RequestHeader::Authorization
+                        | RequestHeader::Cookie
+                        | RequestHeader::Expect,
+                    ) => {
+                        crate::DEBUG!(
+                            "[Request::read] Ignoring prohibited trailer field `{}`",
+                            key_bytes.escape_ascii()
+                        );
+                    }
+                    Some(key) => self
+                        .headers
+                        .append(key, CowSlice::Ref(Slice::from_bytes(value))),
+                    None => self.headers.append_custom(
+                        Slice::from_bytes(key_bytes),
+                        CowSlice::Ref(Slice::from_bytes(value)),
+                    ),
+                }
+            }
+        }
+
+        Ok(())
     }
 
     #[cfg(feature = "__rt_native__")]
@@ -355,10 +843,8 @@ impl Request {
     ) -> Result<CowSlice, crate::Response> {
         let remaining_buf_len = remaining_buf.len();
 
-        if remaining_buf_len == 0 || *unsafe { remaining_buf.get_unchecked(0) } == 0 {
-            crate::DEBUG!(
-                "\n[read_payload] case: remaining_buf.is_empty() || remaining_buf[0] == 0\n"
-            );
+        if remaining_buf_len == 0 {
+            crate::DEBUG!("\n[read_payload] case: remaining_buf.is_empty()\n");
 
             let mut bytes = vec![0; size].into_boxed_slice();
             if let Err(err) = stream.read_exact(&mut bytes).await {
@@ -465,7 +951,7 @@ impl Request {
             if let Some(key) = RequestHeader::from_bytes(key_bytes) {
                 self.headers.append(key, value);
             } else {
-                self.headers.insert_custom(
+                self.headers.append_custom(
                     Slice::from_bytes(Box::leak(key_bytes.to_owned().into_boxed_slice())),
                     value,
                 )
@@ -576,6 +1062,66 @@ impl Request {
     pub fn payload(&self) -> Option<&[u8]> {
         self.payload.as_deref()
     }
+
+    /// Scheme of the original request : `"http"` or `"https"`.
+    ///
+    /// This is the one reported by a trusted proxy when
+    /// [`fang::TrustedProxy`](crate::fang::TrustedProxy) is used,
+    /// and otherwise the one of the connection to Ohkami.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use ohkami::{Request, claw::status};
+    ///
+    /// async fn to_home(req: &Request) -> status::Found {
+    ///     status::Found::at(format!(
+    ///         "{}://{}/home",
+    ///         req.scheme(),
+    ///         req.host().unwrap_or("localhost"),
+    ///     ))
+    /// }
+    /// ```
+    pub fn scheme(&self) -> &str {
+        if let Some(proto) = self.forwarded.as_ref().and_then(|f| f.proto.as_deref()) {
+            return proto;
+        }
+
+        #[cfg(feature = "__rt_native__")]
+        {
+            if self.context.connection().is_some_and(|c| c.is_tls()) {
+                "https"
+            } else {
+                "http"
+            }
+        }
+        #[cfg(feature = "rt_worker")]
+        {
+            // SAFETY: `__url__` is initialized when the request is loaded
+            unsafe { self.__url__.assume_init_ref() }.scheme()
+        }
+        #[cfg(feature = "rt_lambda")]
+        {
+            // Lambda function URLs and API Gateway HTTP APIs are served over HTTPS
+            "https"
+        }
+        #[cfg(not(feature = "__rt__"))]
+        {
+            "http"
+        }
+    }
+
+    /// Host ( and port, if any ) of the original request.
+    ///
+    /// This is the one reported by a trusted proxy when
+    /// [`fang::TrustedProxy`](crate::fang::TrustedProxy) is used,
+    /// and otherwise `Host` header ( or the authority of the request target ).
+    pub fn host(&self) -> Option<&str> {
+        match self.forwarded.as_ref().and_then(|f| f.host.as_deref()) {
+            Some(host) => Some(host),
+            None => self.headers.host(),
+        }
+    }
 }
 
 const _: () = {
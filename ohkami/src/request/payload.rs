@@ -0,0 +1,407 @@
+#![cfg(feature = "__rt_native__")]
+
+//! Request payload left on the connection after reading the head,
+//! loaded by the router or streamed by `claw::content::BodyStream`
+
+use super::chunked::{MAX_CHUNK_SIZE_LINE, parse_chunk_size};
+use ohkami_lib::Slice;
+use std::io;
+use std::pin::Pin;
+use std::ptr::NonNull;
+use std::sync::{Mutex, MutexGuard};
+use std::task::{Context, Poll, ready};
+
+#[cfg(feature = "__io_futures__")]
+use futures_util::{AsyncRead, AsyncWrite};
+#[cfg(feature = "__io_tokio__")]
+use tokio::io::{AsyncRead, AsyncWrite};
+
+pub(crate) trait ReadStream: AsyncRead + Unpin + crate::fang::SendOnThreaded {
+    /// Write `100 Continue` interim response for `Expect: 100-continue`,
+    /// tracking the written bytes in `written`.
+    ///
+    /// Streams not connected to a client, like the bytes of a test request,
+    /// just skip it.
+    fn poll_write_continue(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        written: &mut usize,
+    ) -> Poll<io::Result<()>> {
+        let _ = (cx, written);
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl ReadStream for &[u8] {}
+
+const CONTINUE: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n";
+
+/// `ReadStream::poll_write_continue` for a writable stream
+pub(crate) fn poll_write_continue(
+    mut stream: Pin<&mut (impl AsyncWrite + Unpin)>,
+    cx: &mut Context<'_>,
+    written: &mut usize,
+) -> Poll<io::Result<()>> {
+    while *written < CONTINUE.len() {
+        match ready!(stream.as_mut().poll_write(cx, &CONTINUE[*written..]))? {
+            0 => return Poll::Ready(Err(io::ErrorKind::WriteZero.into())),
+            n => *written += n,
+        }
+    }
+    stream.poll_flush(cx)
+}
+
+pub(crate) type Stream<'s> = dyn ReadStream + 's;
+
+#[derive(Clone, Copy)]
+pub(super) enum Framing {
+    Length(usize),
+    Chunked,
+}
+
+/// The connection a request is read from, held by the request until it's cleared
+/// for the next one.
+///
+/// `Request` has no lifetime to borrow the connection with, so the borrow given
+/// to `Request::read_head` is erased here. It's valid until the session calls
+/// `Request::clear`, which requires `&mut Request` and so no `Reader` ( borrowing
+/// the request ) is alive then. The pointer is only dereferenced by the single
+/// owner of `Unread` taken out of `UnreadPayload`, so never accessed concurrently.
+pub(super) struct StreamRef(NonNull<Stream<'static>>);
+
+impl StreamRef {
+    /// SAFETY: `stream` must be valid until the request is cleared
+    pub(super) unsafe fn new<'s>(stream: &'s mut Stream<'s>) -> Self {
+        let stream = NonNull::from(stream);
+        Self(unsafe {
+            std::mem::transmute::<NonNull<Stream<'s>>, NonNull<Stream<'static>>>(stream)
+        })
+    }
+
+    /// SAFETY: the request must not be cleared yet ( see `StreamRef` )
+    pub(super) unsafe fn get(&mut self) -> Pin<&mut Stream<'static>> {
+        Pin::new(unsafe { self.0.as_mut() })
+    }
+}
+
+// SAFETY: the stream is `Send` on threaded runtimes ( `ReadStream: SendOnThreaded` ),
+// and `StreamRef` is `&mut`-like: it's moved to one owner and never shared
+#[cfg(feature = "__rt_threaded__")]
+unsafe impl Send for StreamRef {}
+
+pub(crate) struct Unread {
+    /// connection of the request
+    pub(super) stream: StreamRef,
+    /// bytes already read into the request buffer after the head
+    pub(super) buffered: Slice,
+    /// end of the read bytes in the request buffer
+    pub(super) read_len: usize,
+    pub(super) framing: Framing,
+    pub(super) limit: usize,
+    /// [secs] deadline to load the whole payload
+    pub(super) timeout: u64,
+    /// the client sent `Expect: 100-continue` and waits for
+    /// `100 Continue` before sending the payload
+    pub(super) expect_continue: bool,
+}
+
+#[derive(Default)]
+enum State {
+    #[default]
+    None,
+    Unread(Unread),
+    /// taken by a `BodyStream` or failed to load, and not read to the end
+    Partial,
+}
+
+/// Payload of a request that isn't read from the connection yet,
+/// and the bytes read beyond the payload ( the start of the next
+/// pipelined request )
+#[derive(Default)]
+pub(crate) struct UnreadPayload {
+    state: Mutex<State>,
+    leftover: Mutex<Vec<u8>>,
+}
+
+impl UnreadPayload {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn leftover(&self) -> MutexGuard<'_, Vec<u8>> {
+        self.leftover.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(super) fn set(&self, unread: Unread) {
+        *self.state() = State::Unread(unread);
+    }
+
+    /// reset the state for the next request, keeping the leftover.
+    ///
+    /// This releases the connection held by the unread payload
+    /// ( see `StreamRef` ).
+    pub(super) fn clear(&mut self) {
+        *self.state.get_mut().unwrap_or_else(|e| e.into_inner()) = State::None;
+    }
+
+    pub(super) fn set_partial(&self) {
+        *self.state() = State::Partial;
+    }
+
+    /// mark the payload as read to the end, with the bytes following it
+    pub(super) fn done(&self, leftover: &[u8]) {
+        *self.state() = State::None;
+        self.set_leftover(leftover);
+    }
+
+    pub(super) fn set_leftover(&self, leftover: &[u8]) {
+        if !leftover.is_empty() {
+            *self.leftover() = leftover.to_vec();
+        }
+    }
+
+    pub(super) fn take_leftover(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.leftover())
+    }
+
+    /// `true` if the client waits for `100 Continue` to send the payload
+    pub(crate) fn expects_continue(&self) -> bool {
+        matches!(&*self.state(), State::Unread(unread) if unread.expect_continue)
+    }
+
+    /// take the unread payload to load it eagerly
+    pub(super) fn take(&self) -> Option<Unread> {
+        let mut state = self.state();
+        match std::mem::take(&mut *state) {
+            State::Unread(unread) => Some(unread),
+            other => {
+                *state = other;
+                None
+            }
+        }
+    }
+
+    /// `true` if the connection doesn't hold any unread part of the payload,
+    /// otherwise the connection can't be reused for the next request
+    pub(crate) fn is_done(&self) -> bool {
+        matches!(&*self.state(), State::None)
+    }
+
+    /// take the unread payload to stream it. The returned `Reader` is the only
+    /// one reading the connection until the request is cleared.
+    pub(crate) fn stream(&self) -> Option<Reader<'_>> {
+        let Unread {
+            stream,
+            buffered,
+            framing,
+            limit,
+            expect_continue,
+            ..
+        } = {
+            let mut state = self.state();
+            match std::mem::replace(&mut *state, State::Partial) {
+                State::Unread(unread) => unread,
+                other => {
+                    *state = other;
+                    return None;
+                }
+            }
+        };
+
+        let decode = match framing {
+            Framing::Length(remaining) => Decode::Length { remaining },
+            Framing::Chunked => Decode::ChunkSize,
+        };
+        Some(Reader {
+            slot: self,
+            stream,
+            buf: unsafe { buffered.as_bytes() }.to_vec(),
+            pos: 0,
+            decode,
+            total: 0,
+            limit,
+            continue_written: expect_continue.then_some(0),
+        })
+    }
+}
+
+enum Decode {
+    Length { remaining: usize },
+    ChunkSize,
+    ChunkData { remaining: usize },
+    ChunkDataEnd,
+    Trailers,
+    Done,
+}
+
+/// Lazy reader of the unread payload, decoding `Transfer-Encoding: chunked`
+/// and discarding its trailer section
+pub(crate) struct Reader<'p> {
+    slot: &'p UnreadPayload,
+    stream: StreamRef,
+    buf: Vec<u8>,
+    pos: usize,
+    decode: Decode,
+    total: usize,
+    limit: usize,
+    /// progress of writing `100 Continue`, `None` if not needed or done
+    continue_written: Option<usize>,
+}
+
+impl Reader<'_> {
+    pub(crate) fn poll_read(
+        &mut self,
+        cx: &mut Context<'_>,
+        out: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if let Some(written) = &mut self.continue_written {
+            // SAFETY: same as `poll_stream`
+            let stream = unsafe { self.stream.get() };
+            ready!(stream.poll_write_continue(cx, written))?;
+            self.continue_written = None;
+        }
+
+        loop {
+            match self.decode {
+                Decode::Done => return Poll::Ready(Ok(0)),
+
+                Decode::Length { remaining } | Decode::ChunkData { remaining } => {
+                    if out.is_empty() {
+                        return Poll::Ready(Ok(0));
+                    }
+                    let want = remaining.min(out.len());
+                    let n = if self.pos < self.buf.len() {
+                        let n = want.min(self.buf.len() - self.pos);
+                        out[..n].copy_from_slice(&self.buf[self.pos..self.pos + n]);
+                        self.pos += n;
+                        n
+                    } else {
+                        match ready!(self.poll_stream(cx, &mut out[..want]))? {
+                            0 => return Poll::Ready(Err(closed())),
+                            n => n,
+                        }
+                    };
+
+                    let remaining = remaining - n;
+                    self.decode = match (&self.decode, remaining) {
+                        (Decode::Length { .. }, 0) => Decode::Done,
+                        (Decode::Length { .. }, _) => Decode::Length { remaining },
+                        (_, 0) => Decode::ChunkDataEnd,
+                        (_, _) => Decode::ChunkData { remaining },
+                    };
+                    if matches!(self.decode, Decode::Done) {
+                        self.slot.done(&self.buf[self.pos..]);
+                    }
+                    return Poll::Ready(Ok(n));
+                }
+
+                Decode::ChunkSize => {
+                    let line = ready!(self.poll_line(cx))?;
+                    let size = parse_chunk_size(line).ok_or_else(|| {
+                        crate::WARNING!(
+                            "[BodyStream] Invalid chunk size: `{}`",
+                            line.escape_ascii()
+                        );
+                        invalid("invalid chunk size")
+                    })?;
+                    if size == 0 {
+                        self.decode = Decode::Trailers;
+                    } else {
+                        self.total = self.total.saturating_add(size);
+                        if self.total > self.limit {
+                            crate::WARNING!(
+                                "[BodyStream] Chunked payload exceeded `request_payload_limit` ({} bytes)",
+                                self.limit
+                            );
+                            return Poll::Ready(Err(invalid("payload too large")));
+                        }
+                        self.decode = Decode::ChunkData { remaining: size };
+                    }
+                }
+
+                Decode::ChunkDataEnd => {
+                    if !ready!(self.poll_line(cx))?.is_empty() {
+                        crate::WARNING!("[BodyStream] Chunk data is not followed by CRLF");
+                        return Poll::Ready(Err(invalid("chunk data is not followed by CRLF")));
+                    }
+                    self.decode = Decode::ChunkSize;
+                }
+
+                Decode::Trailers => {
+                    let line_len = ready!(self.poll_line(cx))?.len();
+                    if line_len == 0 {
+                        self.decode = Decode::Done;
+                        self.slot.done(&self.buf[self.pos..]);
+                    } else {
+                        // count the discarded trailer fields not to read them endlessly
+                        self.total = self.total.saturating_add(line_len);
+                        if self.total > self.limit {
+                            crate::WARNING!(
+                                "[BodyStream] Trailer section exceeded `request_payload_limit` ({} bytes)",
+                                self.limit
+                            );
+                            return Poll::Ready(Err(invalid("payload too large")));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// read a line terminated by CRLF and return it without the CRLF
+    fn poll_line(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let len = loop {
+            let unread = &self.buf[self.pos..];
+            let scan = &unread[..unread.len().min(MAX_CHUNK_SIZE_LINE + 2)];
+            if let Some(len) = scan.windows(2).position(|w| w == b"\r\n") {
+                break len;
+            }
+            if scan.len() == MAX_CHUNK_SIZE_LINE + 2 {
+                crate::WARNING!("[BodyStream] Too long line in chunked payload");
+                return Poll::Ready(Err(invalid("too long line in chunked payload")));
+            }
+
+            if self.pos > 0 {
+                self.buf.drain(..self.pos);
+                self.pos = 0;
+            }
+            let mut chunk = [0; 1 << 10];
+            match ready!(self.poll_stream(cx, &mut chunk))? {
+                0 => return Poll::Ready(Err(closed())),
+                n => self.buf.extend_from_slice(&chunk[..n]),
+            }
+        };
+
+        let start = self.pos;
+        self.pos += len + 2;
+        Poll::Ready(Ok(&self.buf[start..start + len]))
+    }
+
+    fn poll_stream(&mut self, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        // SAFETY: `self` borrows the request, so the request is not cleared yet,
+        // and the session doesn't touch the connection until the handler returns
+        let stream = unsafe { self.stream.get() };
+
+        #[cfg(feature = "__io_tokio__")]
+        {
+            let mut buf = tokio::io::ReadBuf::new(buf);
+            ready!(stream.poll_read(cx, &mut buf))?;
+            Poll::Ready(Ok(buf.filled().len()))
+        }
+        #[cfg(feature = "__io_futures__")]
+        {
+            stream.poll_read(cx, buf)
+        }
+    }
+}
+
+#[cold]
+fn closed() -> io::Error {
+    crate::WARNING!("[BodyStream] Connection closed in the middle of payload");
+    io::Error::from(io::ErrorKind::UnexpectedEof)
+}
+
+#[cold]
+fn invalid(msg: &'static str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
@@ -14,7 +14,7 @@ macro_rules! assert_response_bytes_eq {
 
             let mut res_bytes = Vec::new();
             crate::__rt__::testing::block_on(
-                $res.send(&mut res_bytes)
+                $res.send(&mut res_bytes, crate::request::Version::Http11)
             ).expect("`Response::send` failed");
 
             if res_bytes != format!($expected, NOW = now).into_bytes() {
@@ -238,3 +238,134 @@ fn test_stream_response() {
     "
     );
 }
+
+#[test]
+fn test_byte_stream_response() {
+    let mut res = Response::OK()
+        .with_byte_stream(
+            "application/x-ndjson",
+            ohkami_lib::stream::queue(|mut q| async move {
+                for i in 0..3 {
+                    q.push(Ok::<_, std::io::Error>(format!("{{\"id\":{i}}}\n")));
+                }
+                q.add(String::new());
+                q.add("{\"id\":\"\u{3042}\"}\n".into());
+            }),
+        )
+        .with_headers(|h| h.server("ohkami"));
+    assert_response_bytes_eq!(
+        res,
+        "\
+        HTTP/1.1 200 OK\r\n\
+        Date: {NOW}\r\n\
+        Content-Type: application/x-ndjson\r\n\
+        Transfer-Encoding: chunked\r\n\
+        Server: ohkami\r\n\
+        \r\n\
+        9\r\n\
+        {{\"id\":0}}\n\
+        \r\n\
+        9\r\n\
+        {{\"id\":1}}\n\
+        \r\n\
+        9\r\n\
+        {{\"id\":2}}\n\
+        \r\n\
+        d\r\n\
+        {{\"id\":\"\u{3042}\"}}\n\
+        \r\n\
+        0\r\n\
+        \r\n\
+    "
+    );
+
+    let res = Response::OK().with_byte_stream(
+        "text/csv",
+        ohkami_lib::stream::queue(|mut q| async move {
+            q.add(b"id,name\n".to_vec());
+            q.push(Err(std::io::Error::other("database is down")));
+            q.add(b"1,alice\n".to_vec());
+        }),
+    );
+    let mut res_bytes = Vec::new();
+    assert!(
+        crate::__rt__::testing::block_on(res.send(&mut res_bytes, crate::request::Version::Http11))
+            .is_err()
+    );
+    assert!(res_bytes.ends_with(b"8\r\nid,name\n\r\n"));
+}
+
+#[test]
+fn test_http10_response() {
+    use crate::request::Version;
+
+    fn send_http10(mut res: Response, keep_alive: bool) -> (bool, String) {
+        res.complete();
+        res.headers.set().date(None);
+
+        let keep_alive = res.downgrade_to_http10(keep_alive);
+        let mut res_bytes = Vec::new();
+        crate::__rt__::testing::block_on(res.send(&mut res_bytes, Version::Http10))
+            .expect("`Response::send` failed");
+        (keep_alive, String::from_utf8(res_bytes).unwrap())
+    }
+
+    assert_eq!(
+        send_http10(Response::OK().with_text("Hello!"), true),
+        (
+            true,
+            String::from(
+                "\
+                HTTP/1.0 200 OK\r\n\
+                Content-Type: text/plain; charset=UTF-8\r\n\
+                Content-Length: 6\r\n\
+                Connection: keep-alive\r\n\
+                \r\n\
+                Hello!\
+            "
+            )
+        )
+    );
+
+    assert_eq!(
+        send_http10(Response::NotFound(), false),
+        (
+            false,
+            String::from(
+                "\
+                HTTP/1.0 404 Not Found\r\n\
+                Content-Length: 0\r\n\
+                Connection: close\r\n\
+                \r\n\
+            "
+            )
+        )
+    );
+
+    // streamed without chunked transfer coding, delimited by closing the connection
+    assert_eq!(
+        send_http10(
+            Response::OK().with_byte_stream(
+                "text/csv",
+                ohkami_lib::stream::queue(|mut q| async move {
+                    q.add(b"id,name\n".to_vec());
+                    q.add(b"1,alice\n".to_vec());
+                }),
+            ),
+            true
+        ),
+        (
+            false,
+            String::from(
+                "\
+                HTTP/1.0 200 OK\r\n\
+                Content-Type: text/csv\r\n\
+                Connection: close\r\n\
+                \r\n\
+                id,name\n\
+                1,alice\n\
+            "
+            )
+        )
+    );
+}
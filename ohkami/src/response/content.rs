@@ -1,6 +1,6 @@
 use ohkami_lib::CowSlice;
 
-#[cfg(feature = "sse")]
+#[cfg(any(feature = "sse", feature = "__rt__"))]
 use ohkami_lib::Stream;
 
 #[cfg(not(feature="rt_lambda"/* currently */))]
@@ -17,6 +17,9 @@ pub enum Content {
     #[cfg(feature = "sse")]
     Stream(std::pin::Pin<Box<dyn Stream<Item = String> + Send>>),
 
+    #[cfg(feature = "__rt__")]
+    ByteStream(std::pin::Pin<Box<dyn Stream<Item = Result<Vec<u8>, std::io::Error>> + Send>>),
+
     #[cfg(not(feature="rt_lambda"/* currently */))]
     #[cfg(all(feature = "ws", feature = "__rt__"))]
     WebSocket(Session),
@@ -44,6 +47,9 @@ const _: () = {
                 #[cfg(feature = "sse")]
                 Self::Stream(_) => f.write_str("{stream}"),
 
+                #[cfg(feature = "__rt__")]
+                Self::ByteStream(_) => f.write_str("{byte stream}"),
+
                 #[cfg(not(feature="rt_lambda"/* currently */))]
                 #[cfg(all(feature = "ws", feature = "__rt__"))]
                 Self::WebSocket(_) => f.write_str("{websocket}"),
@@ -91,6 +97,8 @@ impl Content {
                 stream.map(Result::<_, Infallible>::Ok)
             }),
 
+            Self::ByteStream(stream) => ::worker::Response::from_stream(stream),
+
             #[cfg(feature = "ws")]
             Self::WebSocket(ws) => ::worker::Response::from_websocket(ws),
         }
@@ -209,7 +209,7 @@ macro_rules! Header {
         }
     };
 }
-Header! {48;
+Header! {49;
     accept_ranges(AcceptRanges): b"Accept-Ranges",
     access_control_allow_credentials(AccessControlAllowCredentials): b"Access-Control-Allow-Credentials",
     access_control_allow_headers(AccessControlAllowHeaders): b"Access-Control-Allow-Headers",
@@ -246,6 +246,7 @@ Header! {48;
     refresh(Refresh): b"Refresh",
     retry_after(RetryAfter): b"Retry-After",
     sec_websocket_accept(SecWebSocketAccept): b"Sec-WebSocket-Accept",
+    sec_websocket_extensions(SecWebSocketExtensions): b"Sec-WebSocket-Extensions",
     sec_websocket_protocol(SecWebSocketProtocol): b"Sec-WebSocket-Protocol",
     sec_websocket_version(SecWebSocketVersion): b"Sec-WebSocket-Version",
     server(Server): b"Server",
@@ -22,11 +22,12 @@ use std::borrow::Cow;
 
 #[cfg(feature = "__rt_native__")]
 use crate::__rt__::AsyncWrite;
+#[cfg(feature = "__rt_native__")]
+use crate::request::Version;
 #[cfg(feature = "sse")]
-use crate::{
-    sse,
-    util::{Stream, StreamExt},
-};
+use crate::sse;
+#[cfg(any(feature = "sse", feature = "__rt__"))]
+use crate::util::{Stream, StreamExt};
 
 /// # HTTP Response
 ///
@@ -146,6 +147,9 @@ impl Response {
                     self.headers.set().content_length(None);
                 }
             }
+            (Content::ByteStream(_), _) if self.headers.content_length().is_some() => {
+                self.headers.set().content_length(None);
+            }
             #[cfg(not(feature="rt_lambda"/* currently */))]
             #[cfg(all(feature = "ws", feature = "__rt__"))]
             (Content::WebSocket(_), _) => {
@@ -313,12 +317,58 @@ impl Response {
     }
 }
 
+#[cfg(feature = "__rt__")]
+impl Response {
+    /// Set a body streamed from `stream` with the `content_type`,
+    /// sent by chunked transfer coding.
+    ///
+    /// If `stream` yields an error, the response is aborted and the
+    /// connection is closed.
+    ///
+    /// ### example
+    ///
+    /// ```
+    /// use ohkami::{Response, util::stream};
+    ///
+    /// async fn export_csv() -> Response {
+    ///     let rows = stream::queue(|mut q| async move {
+    ///         q.push(Ok::<_, std::io::Error>(String::from("id,name\n")));
+    ///         for (id, name) in [(1, "alice"), (2, "bob")] {
+    ///             q.add(format!("{id},{name}\n"));
+    ///         }
+    ///     });
+    ///     Response::OK().with_byte_stream("text/csv", rows)
+    /// }
+    /// ```
+    pub fn set_byte_stream<B: Into<Vec<u8>>>(
+        &mut self,
+        content_type: &'static str,
+        stream: impl Stream<Item = Result<B, std::io::Error>> + Send + 'static,
+    ) {
+        self.headers
+            .set()
+            .content_length(None)
+            .content_type(content_type)
+            .transfer_encoding("chunked");
+        self.content = Content::ByteStream(Box::pin(stream.map(|chunk| chunk.map(Into::into))));
+    }
+
+    pub fn with_byte_stream<B: Into<Vec<u8>>>(
+        mut self,
+        content_type: &'static str,
+        stream: impl Stream<Item = Result<B, std::io::Error>> + Send + 'static,
+    ) -> Self {
+        self.set_byte_stream(content_type, stream);
+        self
+    }
+}
+
 #[cfg(feature = "__rt_native__")]
 pub(super) enum Upgrade {
     None,
 
     #[cfg(feature = "ws")]
-    WebSocket(mews::WebSocket<crate::session::Connection>),
+    WebSocket(crate::ws::Session),
 }
 #[cfg(feature = "__rt_native__")]
 impl Upgrade {
@@ -329,17 +379,44 @@ impl Upgrade {
 }
 #[cfg(feature = "__rt_native__")]
 impl Response {
+    /// Set `Connection` header for an HTTP/1.0 client, and drop chunked
+    /// transfer coding it doesn't know, delimiting the streamed body
+    /// by closing the connection instead.
+    ///
+    /// Returns whether the connection is kept alive after this response.
+    pub(crate) fn downgrade_to_http10(&mut self, keep_alive: bool) -> bool {
+        if self.status == Status::SwitchingProtocols {
+            return false;
+        }
+
+        let streamed = self.headers.transfer_encoding().is_some();
+        if streamed {
+            self.headers.set().transfer_encoding(None);
+        }
+
+        let keep_alive = keep_alive && !streamed;
+        self.headers
+            .set()
+            .connection(if keep_alive { "keep-alive" } else { "close" });
+        keep_alive
+    }
+
     #[cfg_attr(not(feature = "sse"), inline)]
     pub(crate) async fn send(
         self,
         conn: &mut (impl AsyncWrite + Unpin),
+        version: Version,
     ) -> std::io::Result<Upgrade> {
+        let (status_line, chunked) = match version {
+            Version::Http11 => (self.status.line(), true),
+            Version::Http10 => (self.status.line_http10(), false),
+        };
+
         match self.content {
             Content::None => {
-                let mut buf =
-                    Vec::<u8>::with_capacity(self.status.line().len() + self.headers.size);
+                let mut buf = Vec::<u8>::with_capacity(status_line.len() + self.headers.size);
                 unsafe {
-                    crate::push_unchecked!(buf <- self.status.line());
+                    crate::push_unchecked!(buf <- status_line);
                     self.headers.write_unchecked_to(&mut buf);
                 }
                 conn.write_all(&buf).await?;
@@ -349,11 +426,10 @@ impl Response {
             }
 
             Content::Payload(bytes) => {
-                let mut buf = Vec::<u8>::with_capacity(
-                    self.status.line().len() + self.headers.size + bytes.len(),
-                );
+                let mut buf =
+                    Vec::<u8>::with_capacity(status_line.len() + self.headers.size + bytes.len());
                 unsafe {
-                    crate::push_unchecked!(buf <- self.status.line());
+                    crate::push_unchecked!(buf <- status_line);
                     self.headers.write_unchecked_to(&mut buf);
                     crate::push_unchecked!(buf <- bytes);
                 }
@@ -365,53 +441,73 @@ impl Response {
 
             #[cfg(feature = "sse")]
             Content::Stream(mut stream) => {
-                let mut buf =
-                    Vec::<u8>::with_capacity(self.status.line().len() + self.headers.size);
+                let mut buf = Vec::<u8>::with_capacity(status_line.len() + self.headers.size);
                 unsafe {
-                    crate::push_unchecked!(buf <- self.status.line());
+                    crate::push_unchecked!(buf <- status_line);
                     self.headers.write_unchecked_to(&mut buf);
                 }
                 conn.write_all(&buf).await?;
                 conn.flush().await?;
 
                 while let Some(chunk) = stream.next().await {
-                    let mut message = Vec::with_capacity(
-                        /* capacity for a single line */
-                        "data: ".len() + chunk.len() + "\n\n".len(),
-                    );
-                    for line in chunk.split('\n') {
-                        message.extend_from_slice(b"data: ");
-                        message.extend_from_slice(line.as_bytes());
-                        message.push(b'\n');
-                    }
-                    message.push(b'\n');
-
-                    let size_hex_bytes = ohkami_lib::num::hexized_bytes(message.len());
-
-                    let mut chunk = Vec::from(
-                        &size_hex_bytes[size_hex_bytes.iter().position(|b| *b != b'0').unwrap()..],
-                    );
-                    chunk.extend_from_slice(b"\r\n");
-                    chunk.append(&mut message);
-                    chunk.extend_from_slice(b"\r\n");
+                    let message = encode_sse_message(&chunk);
+                    let chunk = if chunked {
+                        encode_chunk(&message)
+                    } else {
+                        message
+                    };
 
                     crate::DEBUG!("\n[sending chunk]\n{}", chunk.escape_ascii());
 
                     conn.write_all(&chunk).await?;
                     conn.flush().await?;
                 }
-                conn.write_all(b"0\r\n\r\n").await?;
+                if chunked {
+                    conn.write_all(b"0\r\n\r\n").await?;
+                    conn.flush().await?;
+                }
+
+                Ok(Upgrade::None)
+            }
+
+            Content::ByteStream(mut stream) => {
+                let mut buf = Vec::<u8>::with_capacity(status_line.len() + self.headers.size);
+                unsafe {
+                    crate::push_unchecked!(buf <- status_line);
+                    self.headers.write_unchecked_to(&mut buf);
+                }
+                conn.write_all(&buf).await?;
                 conn.flush().await?;
 
+                while let Some(chunk) = stream.next().await {
+                    let chunk = chunk.inspect_err(|e| {
+                        crate::ERROR!("[Response::send] Aborting byte stream by error: {e}");
+                    })?;
+                    if chunk.is_empty() {
+                        /* an empty chunk would mean the end of the body */
+                        continue;
+                    }
+
+                    if chunked {
+                        conn.write_all(&encode_chunk(&chunk)).await?;
+                    } else {
+                        conn.write_all(&chunk).await?;
+                    }
+                    conn.flush().await?;
+                }
+                if chunked {
+                    conn.write_all(b"0\r\n\r\n").await?;
+                    conn.flush().await?;
+                }
+
                 Ok(Upgrade::None)
             }
 
             #[cfg(all(feature = "ws", feature = "__rt_native__"))]
             Content::WebSocket(ws) => {
-                let mut buf =
-                    Vec::<u8>::with_capacity(self.status.line().len() + self.headers.size);
+                let mut buf = Vec::<u8>::with_capacity(status_line.len() + self.headers.size);
                 unsafe {
-                    crate::push_unchecked!(buf <- self.status.line());
+                    crate::push_unchecked!(buf <- status_line);
                     self.headers.write_unchecked_to(&mut buf);
                 }
                 conn.write_all(&buf).await?;
@@ -423,6 +519,37 @@ impl Response {
     }
 }
 
+/// `data: ` lines of a Server-Sent Events message
+#[cfg(all(feature = "sse", feature = "__rt_native__"))]
+pub(crate) fn encode_sse_message(data: &str) -> Vec<u8> {
+    let mut message = Vec::with_capacity(
+        /* capacity for a single line */
+        "data: ".len() + data.len() + "\n\n".len(),
+    );
+    for line in data.split('\n') {
+        message.extend_from_slice(b"data: ");
+        message.extend_from_slice(line.as_bytes());
+        message.push(b'\n');
+    }
+    message.push(b'\n');
+    message
+}
+
+/// `chunk-size CRLF chunk-data CRLF` of chunked transfer coding
+#[cfg(feature = "__rt_native__")]
+fn encode_chunk(data: &[u8]) -> Vec<u8> {
+    let size_hex_bytes = ohkami_lib::num::hexized_bytes(data.len());
+
+    let mut chunk = Vec::with_capacity(size_hex_bytes.len() + 2 + data.len() + 2);
+    chunk.extend_from_slice(
+        &size_hex_bytes[size_hex_bytes.iter().position(|b| *b != b'0').unwrap()..],
+    );
+    chunk.extend_from_slice(b"\r\n");
+    chunk.extend_from_slice(data);
+    chunk.extend_from_slice(b"\r\n");
+    chunk
+}
+
 const _: () = {
     impl std::fmt::Debug for Response {
         fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -490,20 +617,12 @@ const _: () = {
     use crate::x_lambda::LambdaResponse;
     use ::lambda_runtime::FunctionResponse;
     use ohkami_lib::Stream;
-    use std::{convert::Infallible, pin::Pin};
-
-    impl From<Response>
-        for FunctionResponse<
-            LambdaResponse,
-            Pin<Box<dyn Stream<Item = Result<String, Infallible>> + Send>>,
-        >
-    {
-        fn from(
-            this: Response,
-        ) -> FunctionResponse<
-            LambdaResponse,
-            Pin<Box<dyn Stream<Item = Result<String, Infallible>> + Send>>,
-        > {
+    use std::pin::Pin;
+
+    type ByteStream = Pin<Box<dyn Stream<Item = Result<Vec<u8>, std::io::Error>> + Send>>;
+
+    impl From<Response> for FunctionResponse<LambdaResponse, ByteStream> {
+        fn from(this: Response) -> FunctionResponse<LambdaResponse, ByteStream> {
             let mut headers = this.headers;
 
             let cookies = headers
@@ -517,14 +636,16 @@ const _: () = {
                     vec_string
                 });
 
-            match this.content {
-                Content::None => FunctionResponse::BufferedResponse(LambdaResponse {
-                    statusCode: this.status.code(),
-                    headers,
-                    cookies,
-                    body: None,
-                    isBase64Encoded: None,
-                }),
+            let stream: ByteStream = match this.content {
+                Content::None => {
+                    return FunctionResponse::BufferedResponse(LambdaResponse {
+                        statusCode: this.status.code(),
+                        headers,
+                        cookies,
+                        body: None,
+                        isBase64Encoded: None,
+                    });
+                }
 
                 Content::Payload(p) => {
                     let (encoded, body) = if let Ok(s) = std::str::from_utf8(&p) {
@@ -533,41 +654,43 @@ const _: () = {
                         (true, crate::util::base64_encode(&*p))
                     };
 
-                    FunctionResponse::BufferedResponse(LambdaResponse {
+                    return FunctionResponse::BufferedResponse(LambdaResponse {
                         statusCode: this.status.code(),
                         headers,
                         cookies,
                         body: Some(body),
                         isBase64Encoded: Some(encoded),
-                    })
+                    });
                 }
 
                 #[cfg(feature = "sse")]
-                Content::Stream(stream) => {
-                    FunctionResponse::StreamingResponse(::lambda_runtime::StreamResponse {
-                        stream: Box::pin(stream.map(Result::<_, Infallible>::Ok)),
-                        metadata_prelude: ::lambda_runtime::MetadataPrelude {
-                            // `StatusCode` of `http` crate
-                            status_code: unsafe {
-                                TryFrom::<u16>::try_from(this.status.code()).unwrap_unchecked()
-                            },
-                            // `HeaderMap` of `http` crate
-                            headers: FromIterator/*::<HeaderName, HeaderValue>*/::from_iter(
-                                headers.into_iter().map(
-                                    |(n, v): (&'static str, Cow<'static, str>)| {
-                                        (
-                                            TryFrom::<&str>::try_from(n).unwrap(),
-                                            TryFrom::<String>::try_from(v.into_owned()).unwrap(),
-                                        )
-                                    },
-                                ),
-                            ),
-                            #[allow(clippy::unwrap_or_default)]
-                            cookies: cookies.unwrap_or_else(Vec::new),
-                        },
-                    })
-                }
-            }
+                Content::Stream(stream) => Box::pin(stream.map(|s| Ok(s.into_bytes()))),
+
+                Content::ByteStream(stream) => stream,
+            };
+
+            FunctionResponse::StreamingResponse(::lambda_runtime::StreamResponse {
+                stream,
+                metadata_prelude: ::lambda_runtime::MetadataPrelude {
+                    // `StatusCode` of `http` crate
+                    status_code: unsafe {
+                        TryFrom::<u16>::try_from(this.status.code()).unwrap_unchecked()
+                    },
+                    // `HeaderMap` of `http` crate
+                    headers: FromIterator/*::<HeaderName, HeaderValue>*/::from_iter(
+                        headers
+                            .into_iter()
+                            .map(|(n, v): (&'static str, Cow<'static, str>)| {
+                                (
+                                    TryFrom::<&str>::try_from(n).unwrap(),
+                                    TryFrom::<String>::try_from(v.into_owned()).unwrap(),
+                                )
+                            }),
+                    ),
+                    #[allow(clippy::unwrap_or_default)]
+                    cookies: cookies.unwrap_or_else(Vec::new),
+                },
+            })
         }
     }
 };
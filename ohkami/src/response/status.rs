@@ -29,6 +29,12 @@ macro_rules! status {
                     $( Self::$name => concat!("HTTP/1.1 ", $message, "\r\n").as_bytes(), )*
                 }
             }
+            #[cfg(feature="__rt_native__")]
+            #[inline(always)] pub(crate) const fn line_http10(&self) -> &'static [u8] {
+                match self {
+                    $( Self::$name => concat!("HTTP/1.0 ", $message, "\r\n").as_bytes(), )*
+                }
+            }
         }
 
         impl From<u16> for Status {
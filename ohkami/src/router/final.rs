@@ -20,6 +20,10 @@ pub(super) struct Node {
     catch: BoxedFPC,
     children: &'static [Node],
 
+    /// the handler reads the request payload by itself
+    #[cfg(feature = "__rt_native__")]
+    streams_payload: bool,
+
     #[cfg(feature = "openapi")]
     openapi_operation: Option<crate::openapi::Operation>,
 }
@@ -34,7 +38,7 @@ impl Router {
     #[inline(always)]
     pub(crate) async fn handle(&self, req: &mut Request) -> Response {
         let mut res = 'handle: {
-            (match req.method {
+            let (target, hit) = (match req.method {
                 Method::GET => &self.GET,
                 Method::PUT => &self.PUT,
                 Method::POST => &self.POST,
@@ -51,9 +55,22 @@ impl Router {
                     break 'handle res;
                 }
             })
-            .search(&mut req.path)
-            .call_bite(req)
-            .await
+            .search_target(&mut req.path);
+
+            // loaded before fangs so that they can see the payload, except for
+            // a request not routed, whose client waits for `100 Continue`
+            // and is rejected without sending the payload
+            #[cfg(feature = "__rt_native__")]
+            if !(hit && target.streams_payload)
+                && (hit || !req.__unread__.expects_continue())
+                && let Err(res) = std::pin::Pin::new(&mut *req).load_payload().await
+            {
+                break 'handle res;
+            }
+
+            (if hit { &target.proc } else { &target.catch })
+                .call_bite(req)
+                .await
         };
 
         res.complete();
@@ -305,6 +322,7 @@ const _: (/* conversions */) = {
             });
 
             #[cfg(feature="openapi")] let has_handler = base.handler.is_some();
+            #[cfg(feature="__rt_native__")] let streams_payload = base.handler.as_ref().is_some_and(|h| h.streams_payload);
 
             let proc = base.fangses.clone().into_proc_with(base.handler.unwrap_or(Handler::default_not_found()));
             #[cfg(feature="openapi")] let (proc, openapi_operation) = (proc.0, has_handler.then_some(proc.1));
@@ -319,6 +337,9 @@ const _: (/* conversions */) = {
                 proc,
                 catch,
 
+                #[cfg(feature="__rt_native__")]
+                streams_payload,
+
                 #[cfg(feature="openapi")]
                 openapi_operation
             }
@@ -5,6 +5,8 @@ pub enum Connection {
     /// in order to keep the difference between the smallest and largest variant small.
     #[cfg(feature = "tls")]
     Tls(Box<anysc_rustls::server::TlsStream<crate::__rt__::TcpStream>>),
+    #[cfg(all(unix, not(feature = "rt_nio")))]
+    Unix(crate::__rt__::UnixStream),
 }
 
 impl From<crate::__rt__::TcpStream> for Connection {
@@ -13,6 +15,13 @@ impl From<crate::__rt__::TcpStream> for Connection {
         Self::Tcp(stream)
     }
 }
+#[cfg(all(unix, not(feature = "rt_nio")))]
+impl From<crate::__rt__::UnixStream> for Connection {
+    #[inline]
+    fn from(stream: crate::__rt__::UnixStream) -> Self {
+        Self::Unix(stream)
+    }
+}
 #[cfg(feature = "tls")]
 impl From<anysc_rustls::server::TlsStream<crate::__rt__::TcpStream>> for Connection {
     #[inline]
@@ -21,6 +30,29 @@ impl From<anysc_rustls::server::TlsStream<crate::__rt__::TcpStream>> for Connect
     }
 }
 
+#[cfg(all(feature = "http2", feature = "tls"))]
+impl Connection {
+    /// `h2` is selected by ALPN in TLS handshake
+    pub(crate) fn is_alpn_h2(&self) -> bool {
+        match self {
+            Self::Tcp(_) => false,
+            #[cfg(all(unix, not(feature = "rt_nio")))]
+            Self::Unix(_) => false,
+            Self::Tls(stream) => stream.get_ref().1.alpn_protocol() == Some(b"h2"),
+        }
+    }
+}
+
+impl crate::request::payload::ReadStream for Connection {
+    fn poll_write_continue(
+        self: std::pin::Pin<&mut Self>,
+        cx: &mut std::task::Context<'_>,
+        written: &mut usize,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        crate::request::payload::poll_write_continue(self, cx, written)
+    }
+}
+
 #[cfg(feature = "__io_tokio__")]
 const _: () = {
     impl tokio::io::AsyncRead for Connection {
@@ -34,6 +66,8 @@ const _: () = {
                 Self::Tcp(stream) => std::pin::Pin::new(stream).poll_read(cx, buf),
                 #[cfg(feature = "tls")]
                 Self::Tls(stream) => std::pin::Pin::new(stream).poll_read(cx, buf),
+                #[cfg(all(unix, not(feature = "rt_nio")))]
+                Self::Unix(stream) => std::pin::Pin::new(stream).poll_read(cx, buf),
             }
         }
     }
@@ -49,6 +83,8 @@ const _: () = {
                 Self::Tcp(stream) => std::pin::Pin::new(stream).poll_write(cx, buf),
                 #[cfg(feature = "tls")]
                 Self::Tls(stream) => std::pin::Pin::new(stream).poll_write(cx, buf),
+                #[cfg(all(unix, not(feature = "rt_nio")))]
+                Self::Unix(stream) => std::pin::Pin::new(stream).poll_write(cx, buf),
             }
         }
 
@@ -61,6 +97,8 @@ const _: () = {
                 Self::Tcp(stream) => std::pin::Pin::new(stream).poll_flush(cx),
                 #[cfg(feature = "tls")]
                 Self::Tls(stream) => std::pin::Pin::new(stream).poll_flush(cx),
+                #[cfg(all(unix, not(feature = "rt_nio")))]
+                Self::Unix(stream) => std::pin::Pin::new(stream).poll_flush(cx),
             }
         }
 
@@ -72,6 +110,8 @@ const _: () = {
                 Self::Tcp(stream) => std::pin::Pin::new(stream).poll_shutdown(cx),
                 #[cfg(feature = "tls")]
                 Self::Tls(stream) => std::pin::Pin::new(stream).poll_shutdown(cx),
+                #[cfg(all(unix, not(feature = "rt_nio")))]
+                Self::Unix(stream) => std::pin::Pin::new(stream).poll_shutdown(cx),
             }
         }
     }
@@ -90,6 +130,8 @@ const _: () = {
                 Self::Tcp(stream) => std::pin::Pin::new(stream).poll_read(cx, buf),
                 #[cfg(feature = "tls")]
                 Self::Tls(stream) => std::pin::Pin::new(stream).poll_read(cx, buf),
+                #[cfg(all(unix, not(feature = "rt_nio")))]
+                Self::Unix(stream) => std::pin::Pin::new(stream).poll_read(cx, buf),
             }
         }
     }
@@ -105,6 +147,8 @@ const _: () = {
                 Self::Tcp(stream) => std::pin::Pin::new(stream).poll_write(cx, buf),
                 #[cfg(feature = "tls")]
                 Self::Tls(stream) => std::pin::Pin::new(stream).poll_write(cx, buf),
+                #[cfg(all(unix, not(feature = "rt_nio")))]
+                Self::Unix(stream) => std::pin::Pin::new(stream).poll_write(cx, buf),
             }
         }
 
@@ -117,6 +161,8 @@ const _: () = {
                 Self::Tcp(stream) => std::pin::Pin::new(stream).poll_flush(cx),
                 #[cfg(feature = "tls")]
                 Self::Tls(stream) => std::pin::Pin::new(stream).poll_flush(cx),
+                #[cfg(all(unix, not(feature = "rt_nio")))]
+                Self::Unix(stream) => std::pin::Pin::new(stream).poll_flush(cx),
             }
         }
 
@@ -128,6 +174,8 @@ const _: () = {
                 Self::Tcp(stream) => std::pin::Pin::new(stream).poll_close(cx),
                 #[cfg(feature = "tls")]
                 Self::Tls(stream) => std::pin::Pin::new(stream).poll_close(cx),
+                #[cfg(all(unix, not(feature = "rt_nio")))]
+                Self::Unix(stream) => std::pin::Pin::new(stream).poll_close(cx),
             }
         }
     }
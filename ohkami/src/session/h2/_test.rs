@@ -0,0 +1,643 @@
+#![cfg(all(test, feature = "__rt_native__", feature = "DEBUG"))]
+
+use super::frame::{self, ErrorCode, Frame, flag, kind};
+use super::{H2, PREFACE, hpack};
+use crate::claw::content::{BodyStream, Text};
+use crate::ohkami::sync::Shutdown;
+use crate::{Ohkami, Route};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// in-memory connection reading the prepared client frames and then EOF
+struct Connection {
+    input: Vec<u8>,
+    read: usize,
+    output: Vec<u8>,
+}
+
+impl Connection {
+    fn poll_read_bytes(&mut self, buf: &mut [u8]) -> usize {
+        let n = buf.len().min(self.input.len() - self.read);
+        buf[..n].copy_from_slice(&self.input[self.read..self.read + n]);
+        self.read += n;
+        n
+    }
+}
+
+#[cfg(feature = "__io_tokio__")]
+const _: () = {
+    impl tokio::io::AsyncRead for Connection {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            let n = self.get_mut().poll_read_bytes(buf.initialize_unfilled());
+            buf.advance(n);
+            Poll::Ready(Ok(()))
+        }
+    }
+    impl tokio::io::AsyncWrite for Connection {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.get_mut().output.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+        fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+};
+
+#[cfg(feature = "__io_futures__")]
+const _: () = {
+    impl futures_util::AsyncRead for Connection {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            Poll::Ready(Ok(self.get_mut().poll_read_bytes(buf)))
+        }
+    }
+    impl futures_util::AsyncWrite for Connection {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.get_mut().output.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+        fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+};
+
+fn ohkami() -> Ohkami {
+    Ohkami::new((
+        "/hello".GET(async || "Hello, HTTP/2!"),
+        "/echo".POST(async |Text(body): Text<String>| body),
+        "/large".GET(async || "a".repeat(100_000)),
+        "/panic".GET(async || -> &'static str { panic!("panicking in handling") }),
+        "/stall".POST(stall),
+    ))
+}
+
+/// never reads the payload
+async fn stall(_: BodyStream<'_>) -> &'static str {
+    std::future::pending().await
+}
+
+/// serve the client frames and return the server frames
+fn serve(config: crate::Config, client_frames: &[Vec<u8>]) -> Vec<Frame> {
+    serve_with_shutdown(config, client_frames, Shutdown::new())
+}
+
+fn serve_with_shutdown(
+    config: crate::Config,
+    client_frames: &[Vec<u8>],
+    shutdown: Shutdown,
+) -> Vec<Frame> {
+    let mut conn = Connection {
+        input: client_frames.concat(),
+        read: 0,
+        output: Vec::new(),
+    };
+
+    let (router, _) = ohkami().into_router().finalize();
+    crate::__rt__::testing::block_on(
+        H2::new(
+            &mut conn,
+            Arc::new(router),
+            config,
+            crate::util::IP_0000,
+            shutdown,
+            super::super::ConnectionInfo::new(
+                Arc::new(crate::ListenAddr::Tcp((crate::util::IP_0000, 0).into())),
+                None,
+            ),
+        )
+        .serve(&[]),
+    );
+
+    let (mut output, mut frames) = (&conn.output[..], Vec::new());
+    while let Some((frame, size)) = Frame::parse(output).unwrap() {
+        frames.push(frame);
+        output = &output[size..];
+    }
+    assert!(output.is_empty());
+    frames
+}
+
+fn client_frame(kind: u8, flags: u8, stream_id: u32, payload: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    frame::write(&mut buf, kind, flags, stream_id, payload);
+    buf
+}
+
+fn request_headers(stream_id: u32, end_stream: bool, fields: &[(&str, &str)]) -> Vec<u8> {
+    let mut block = Vec::new();
+    for (name, value) in fields {
+        hpack::encode_field(&mut block, name.as_bytes(), value.as_bytes());
+    }
+    let flags = flag::END_HEADERS | if end_stream { flag::END_STREAM } else { 0 };
+    client_frame(kind::HEADERS, flags, stream_id, &block)
+}
+
+/// `(status, payload)` of the response on the stream, with checking the stream is ended
+fn response(frames: &[Frame], stream_id: u32) -> (String, Vec<u8>) {
+    let mut decoder = hpack::Decoder::new();
+    let (mut status, mut payload, mut ended) = (None, Vec::new(), false);
+    for frame in frames {
+        if frame.kind == kind::HEADERS || frame.kind == kind::CONTINUATION {
+            let fields = decoder.decode(&frame.payload, 1 << 16).unwrap();
+            if frame.stream_id == stream_id {
+                for (name, value) in fields.list {
+                    if name == b":status" {
+                        status = Some(String::from_utf8(value).unwrap());
+                    }
+                }
+            }
+        }
+        if frame.stream_id == stream_id {
+            if frame.kind == kind::DATA {
+                assert!(frame.payload.len() <= frame::MAX_FRAME_SIZE);
+                payload.extend_from_slice(&frame.payload);
+            }
+            if matches!(frame.kind, kind::HEADERS | kind::DATA) && frame.has(flag::END_STREAM) {
+                ended = true;
+            }
+        }
+    }
+    assert!(ended, "stream {stream_id} is not ended");
+    (status.expect("no `:status`"), payload)
+}
+
+fn rst_stream(frames: &[Frame], stream_id: u32) -> Option<u32> {
+    frames
+        .iter()
+        .find(|f| f.kind == kind::RST_STREAM && f.stream_id == stream_id)
+        .map(|f| u32::from_be_bytes(f.payload[..].try_into().unwrap()))
+}
+
+fn goaway(frames: &[Frame]) -> Option<u32> {
+    frames
+        .iter()
+        .find(|f| f.kind == kind::GOAWAY)
+        .map(|f| u32::from_be_bytes(f.payload[4..8].try_into().unwrap()))
+}
+
+#[test]
+fn test_multiplexed_requests() {
+    let frames = serve(
+        crate::Config::default(),
+        &[
+            PREFACE.to_vec(),
+            client_frame(kind::SETTINGS, 0, 0, &[]),
+            request_headers(
+                1,
+                false,
+                &[
+                    (":method", "POST"),
+                    (":scheme", "http"),
+                    (":path", "/echo"),
+                    (":authority", "localhost"),
+                    ("content-type", "text/plain"),
+                ],
+            ),
+            request_headers(
+                3,
+                true,
+                &[
+                    (":method", "GET"),
+                    (":scheme", "http"),
+                    (":path", "/hello"),
+                    (":authority", "localhost"),
+                ],
+            ),
+            client_frame(kind::DATA, 0, 1, b"Hello, "),
+            client_frame(kind::PING, 0, 0, b"ohkami!!"),
+            client_frame(kind::DATA, flag::END_STREAM, 1, b"echo!"),
+            request_headers(
+                5,
+                true,
+                &[
+                    (":method", "GET"),
+                    (":scheme", "http"),
+                    (":path", "/unknown"),
+                ],
+            ),
+        ],
+    );
+
+    // our SETTINGS first, and ACK to the client's
+    assert_eq!(frames[0].kind, kind::SETTINGS);
+    assert!(!frames[0].has(flag::ACK));
+    assert!(
+        frames
+            .iter()
+            .any(|f| f.kind == kind::SETTINGS && f.has(flag::ACK))
+    );
+    assert!(
+        frames
+            .iter()
+            .any(|f| f.kind == kind::PING && f.has(flag::ACK) && f.payload == b"ohkami!!")
+    );
+
+    assert_eq!(
+        response(&frames, 1),
+        ("200".into(), b"Hello, echo!".to_vec())
+    );
+    assert_eq!(
+        response(&frames, 3),
+        ("200".into(), b"Hello, HTTP/2!".to_vec())
+    );
+    assert_eq!(response(&frames, 5), ("404".into(), Vec::new()));
+
+    // the connection was just closed by the client
+    assert_eq!(goaway(&frames), None);
+}
+
+#[test]
+fn test_flow_control() {
+    let request = request_headers(
+        1,
+        true,
+        &[
+            (":method", "GET"),
+            (":scheme", "https"),
+            (":path", "/large"),
+        ],
+    );
+
+    // only the initial window ( 65535 bytes ) is sent without WINDOW_UPDATE
+    let frames = serve(
+        crate::Config::default(),
+        &[PREFACE.to_vec(), request.clone()],
+    );
+    let sent = frames
+        .iter()
+        .filter(|f| f.kind == kind::DATA)
+        .map(|f| f.payload.len())
+        .sum::<usize>();
+    assert_eq!(sent, 65535);
+
+    let frames = serve(
+        crate::Config::default(),
+        &[
+            PREFACE.to_vec(),
+            request,
+            client_frame(kind::WINDOW_UPDATE, 0, 0, &40000u32.to_be_bytes()),
+            client_frame(kind::WINDOW_UPDATE, 0, 1, &40000u32.to_be_bytes()),
+        ],
+    );
+    assert_eq!(
+        response(&frames, 1),
+        ("200".into(), "a".repeat(100_000).into_bytes())
+    );
+}
+
+#[test]
+fn test_request_flow_control() {
+    let echo = request_headers(
+        1,
+        false,
+        &[
+            (":method", "POST"),
+            (":scheme", "http"),
+            (":path", "/echo"),
+            ("content-type", "text/plain"),
+        ],
+    );
+    let stall = request_headers(
+        3,
+        false,
+        &[
+            (":method", "POST"),
+            (":scheme", "http"),
+            (":path", "/stall"),
+        ],
+    );
+
+    // the windows are given back as the handler reads the payload,
+    // so a payload larger than the initial window is received
+    let mut client_frames = vec![PREFACE.to_vec(), echo];
+    for _ in 0..5 {
+        client_frames.push(client_frame(kind::DATA, 0, 1, &[b'a'; 16000]));
+    }
+    client_frames.push(client_frame(kind::DATA, flag::END_STREAM, 1, &[]));
+    client_frames.push(client_frame(
+        kind::WINDOW_UPDATE,
+        0,
+        0,
+        &40000u32.to_be_bytes(),
+    ));
+    client_frames.push(client_frame(
+        kind::WINDOW_UPDATE,
+        0,
+        1,
+        &40000u32.to_be_bytes(),
+    ));
+    let frames = serve(crate::Config::default(), &client_frames);
+    assert_eq!(response(&frames, 1), ("200".into(), vec![b'a'; 80000]));
+    assert!(
+        frames
+            .iter()
+            .any(|f| f.kind == kind::WINDOW_UPDATE && f.stream_id == 1)
+    );
+
+    // the stream window isn't given back for the payload not read,
+    // and the peer exceeding it is reset
+    let mut client_frames = vec![PREFACE.to_vec(), stall];
+    for _ in 0..5 {
+        client_frames.push(client_frame(kind::DATA, 0, 3, &[b'a'; 16000]));
+    }
+    let frames = serve(crate::Config::default(), &client_frames);
+    assert!(
+        !frames
+            .iter()
+            .any(|f| f.kind == kind::WINDOW_UPDATE && f.stream_id == 3)
+    );
+    assert_eq!(
+        rst_stream(&frames, 3),
+        Some(ErrorCode::FlowControlError as u32)
+    );
+    assert_eq!(goaway(&frames), None);
+}
+
+#[test]
+fn test_rapid_reset() {
+    let mut client_frames = vec![PREFACE.to_vec()];
+    for i in 0..=super::MAX_RESET_STREAMS as u32 {
+        client_frames.push(request_headers(
+            2 * i + 1,
+            false,
+            &[
+                (":method", "POST"),
+                (":scheme", "http"),
+                (":path", "/stall"),
+            ],
+        ));
+        // CANCEL
+        client_frames.push(client_frame(
+            kind::RST_STREAM,
+            0,
+            2 * i + 1,
+            &0x8u32.to_be_bytes(),
+        ));
+    }
+
+    let frames = serve(crate::Config::default(), &client_frames);
+    assert_eq!(goaway(&frames), Some(ErrorCode::EnhanceYourCalm as u32));
+}
+
+#[test]
+fn test_stream_errors() {
+    let frames = serve(
+        crate::Config {
+            request_payload_limit: 4,
+            ..Default::default()
+        },
+        &[
+            PREFACE.to_vec(),
+            // uppercase field name
+            request_headers(
+                1,
+                true,
+                &[
+                    (":method", "GET"),
+                    (":scheme", "http"),
+                    (":path", "/hello"),
+                    ("X-Custom", "value"),
+                ],
+            ),
+            // connection-specific field
+            request_headers(
+                3,
+                true,
+                &[
+                    (":method", "GET"),
+                    (":scheme", "http"),
+                    (":path", "/hello"),
+                    ("connection", "keep-alive"),
+                ],
+            ),
+            // too large payload
+            request_headers(
+                5,
+                false,
+                &[(":method", "POST"), (":scheme", "http"), (":path", "/echo")],
+            ),
+            client_frame(kind::DATA, 0, 5, b"too large"),
+            // panic in a handler
+            request_headers(
+                7,
+                true,
+                &[(":method", "GET"), (":scheme", "http"), (":path", "/panic")],
+            ),
+            // payload exceeding `content-length`
+            request_headers(
+                9,
+                false,
+                &[
+                    (":method", "POST"),
+                    (":scheme", "http"),
+                    (":path", "/echo"),
+                    ("content-type", "text/plain"),
+                    ("content-length", "3"),
+                ],
+            ),
+            client_frame(kind::DATA, flag::END_STREAM, 9, b"abcd"),
+            // the connection is still available
+            request_headers(
+                11,
+                true,
+                &[(":method", "GET"), (":scheme", "http"), (":path", "/hello")],
+            ),
+        ],
+    );
+
+    assert_eq!(
+        rst_stream(&frames, 1),
+        Some(ErrorCode::ProtocolError as u32)
+    );
+    assert_eq!(
+        rst_stream(&frames, 3),
+        Some(ErrorCode::ProtocolError as u32)
+    );
+    assert_eq!(response(&frames, 5), ("413".into(), Vec::new()));
+    assert_eq!(rst_stream(&frames, 5), Some(ErrorCode::NoError as u32));
+    assert_eq!(response(&frames, 7), ("500".into(), Vec::new()));
+    assert_eq!(
+        rst_stream(&frames, 9),
+        Some(ErrorCode::ProtocolError as u32)
+    );
+    assert_eq!(
+        response(&frames, 11),
+        ("200".into(), b"Hello, HTTP/2!".to_vec())
+    );
+}
+
+#[test]
+fn test_connection_errors() {
+    // invalid preface
+    let frames = serve(
+        crate::Config::default(),
+        &[b"GET / HTTP/1.1\r\n\r\n".to_vec()],
+    );
+    assert_eq!(goaway(&frames), Some(ErrorCode::ProtocolError as u32));
+
+    // client-initiated stream of even id
+    let frames = serve(
+        crate::Config::default(),
+        &[
+            PREFACE.to_vec(),
+            request_headers(
+                2,
+                true,
+                &[(":method", "GET"), (":scheme", "http"), (":path", "/hello")],
+            ),
+        ],
+    );
+    assert_eq!(goaway(&frames), Some(ErrorCode::ProtocolError as u32));
+
+    // broken header block
+    let frames = serve(
+        crate::Config::default(),
+        &[
+            PREFACE.to_vec(),
+            client_frame(kind::HEADERS, flag::END_HEADERS, 1, &[0xff]),
+        ],
+    );
+    assert_eq!(goaway(&frames), Some(ErrorCode::CompressionError as u32));
+
+    // other frames in the middle of a header block
+    let frames = serve(
+        crate::Config::default(),
+        &[
+            PREFACE.to_vec(),
+            client_frame(kind::HEADERS, 0, 1, &[0x82]),
+            client_frame(kind::PING, 0, 0, b"ohkami!!"),
+        ],
+    );
+    assert_eq!(goaway(&frames), Some(ErrorCode::ProtocolError as u32));
+}
+
+#[test]
+fn test_header_block_with_continuation() {
+    let mut block = Vec::new();
+    for (name, value) in [(":method", "GET"), (":scheme", "http"), (":path", "/hello")] {
+        hpack::encode_field(&mut block, name.as_bytes(), value.as_bytes());
+    }
+    let (first, rest) = block.split_at(5);
+
+    let frames = serve(
+        crate::Config::default(),
+        &[
+            PREFACE.to_vec(),
+            client_frame(kind::HEADERS, flag::END_STREAM, 1, first),
+            client_frame(kind::CONTINUATION, flag::END_HEADERS, 1, rest),
+        ],
+    );
+    assert_eq!(
+        response(&frames, 1),
+        ("200".into(), b"Hello, HTTP/2!".to_vec())
+    );
+}
+
+#[test]
+fn test_expect_continue() {
+    let statuses = |frames: &[Frame], stream_id: u32| {
+        let mut decoder = hpack::Decoder::new();
+        frames
+            .iter()
+            .filter(|f| f.kind == kind::HEADERS)
+            .filter_map(|f| {
+                let fields = decoder.decode(&f.payload, 1 << 16).unwrap();
+                (f.stream_id == stream_id).then(|| {
+                    let (_, status) = fields.list.into_iter().find(|(n, _)| n == b":status")?;
+                    Some((String::from_utf8(status).unwrap(), f.has(flag::END_STREAM)))
+                })?
+            })
+            .collect::<Vec<_>>()
+    };
+    let upload = |stream_id: u32, content_length: &'static str| {
+        request_headers(
+            stream_id,
+            false,
+            &[
+                (":method", "POST"),
+                (":scheme", "http"),
+                (":path", "/echo"),
+                ("content-type", "text/plain"),
+                ("content-length", content_length),
+                ("expect", "100-continue"),
+            ],
+        )
+    };
+
+    let frames = serve(
+        crate::Config {
+            request_payload_limit: 8,
+            ..Default::default()
+        },
+        &[
+            PREFACE.to_vec(),
+            upload(1, "6"),
+            client_frame(kind::DATA, flag::END_STREAM, 1, b"Hello!"),
+            upload(3, "100"),
+        ],
+    );
+
+    // interim `100` without END_STREAM, then the final response
+    assert_eq!(
+        statuses(&frames, 1),
+        [("100".into(), false), ("200".into(), false)]
+    );
+    assert_eq!(response(&frames, 1), ("200".into(), b"Hello!".to_vec()));
+
+    // rejected by `request_payload_limit` without `100`
+    assert_eq!(statuses(&frames, 3), [("413".into(), true)]);
+    assert_eq!(rst_stream(&frames, 3), Some(ErrorCode::NoError as u32));
+}
+
+#[test]
+fn test_goaway_on_shutdown() {
+    let shutdown = Shutdown::new();
+    shutdown.drain();
+
+    let frames = serve_with_shutdown(
+        crate::Config::default(),
+        &[
+            PREFACE.to_vec(),
+            request_headers(
+                1,
+                true,
+                &[(":method", "GET"), (":scheme", "http"), (":path", "/hello")],
+            ),
+        ],
+        shutdown,
+    );
+
+    // GOAWAY without error, and the stream opened after it is not processed
+    assert_eq!(goaway(&frames), Some(ErrorCode::NoError as u32));
+    assert!(
+        frames
+            .iter()
+            .filter(|f| f.kind == kind::GOAWAY)
+            .all(|f| f.payload[..4] == [0; 4] /* last stream id */)
+    );
+    assert!(!frames.iter().any(|f| f.kind == kind::HEADERS));
+}
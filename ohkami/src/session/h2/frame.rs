@@ -0,0 +1,178 @@
+//! HTTP/2 frames ( RFC 9113, Section 4 and 6 )
+
+/// client connection preface
+pub(crate) const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+pub(super) const HEADER_SIZE: usize = 9;
+
+/// `SETTINGS_MAX_FRAME_SIZE` of ours, that is just the default value
+pub(super) const MAX_FRAME_SIZE: usize = 1 << 14;
+
+pub(super) mod kind {
+    pub(in super::super) const DATA: u8 = 0x0;
+    pub(in super::super) const HEADERS: u8 = 0x1;
+    pub(in super::super) const PRIORITY: u8 = 0x2;
+    pub(in super::super) const RST_STREAM: u8 = 0x3;
+    pub(in super::super) const SETTINGS: u8 = 0x4;
+    pub(in super::super) const PUSH_PROMISE: u8 = 0x5;
+    pub(in super::super) const PING: u8 = 0x6;
+    pub(in super::super) const GOAWAY: u8 = 0x7;
+    pub(in super::super) const WINDOW_UPDATE: u8 = 0x8;
+    pub(in super::super) const CONTINUATION: u8 = 0x9;
+}
+
+pub(super) mod flag {
+    pub(in super::super) const END_STREAM: u8 = 0x1;
+    pub(in super::super) const ACK: u8 = 0x1;
+    pub(in super::super) const END_HEADERS: u8 = 0x4;
+    pub(in super::super) const PADDED: u8 = 0x8;
+    pub(in super::super) const PRIORITY: u8 = 0x20;
+}
+
+pub(super) mod setting {
+    pub(in super::super) const ENABLE_PUSH: u16 = 0x2;
+    pub(in super::super) const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+    pub(in super::super) const INITIAL_WINDOW_SIZE: u16 = 0x4;
+    pub(in super::super) const MAX_FRAME_SIZE: u16 = 0x5;
+    pub(in super::super) const MAX_HEADER_LIST_SIZE: u16 = 0x6;
+}
+
+/// error codes ( RFC 9113, Section 7 )
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(super) enum ErrorCode {
+    NoError = 0x0,
+    ProtocolError = 0x1,
+    InternalError = 0x2,
+    FlowControlError = 0x3,
+    StreamClosed = 0x5,
+    FrameSizeError = 0x6,
+    RefusedStream = 0x7,
+    CompressionError = 0x9,
+    EnhanceYourCalm = 0xb,
+}
+
+pub(super) struct Frame {
+    pub(super) kind: u8,
+    pub(super) flags: u8,
+    pub(super) stream_id: u32,
+    pub(super) payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Parse a frame at the head of `buf`, returning it with its size in `buf`,
+    /// or `None` if `buf` doesn't hold the whole frame yet.
+    pub(super) fn parse(buf: &[u8]) -> Result<Option<(Frame, usize)>, ErrorCode> {
+        let Some(header) = buf.get(..HEADER_SIZE) else {
+            return Ok(None);
+        };
+
+        let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+        if len > MAX_FRAME_SIZE {
+            crate::WARNING!("[http2] Too large frame ({len} bytes)");
+            return Err(ErrorCode::FrameSizeError);
+        }
+        let Some(payload) = buf.get(HEADER_SIZE..HEADER_SIZE + len) else {
+            return Ok(None);
+        };
+
+        Ok(Some((
+            Frame {
+                kind: header[3],
+                flags: header[4],
+                // ignoring the reserved bit
+                stream_id: u32::from_be_bytes([header[5], header[6], header[7], header[8]])
+                    & 0x7fff_ffff,
+                payload: payload.to_vec(),
+            },
+            HEADER_SIZE + len,
+        )))
+    }
+
+    #[inline]
+    pub(super) fn has(&self, flag: u8) -> bool {
+        self.flags & flag != 0
+    }
+
+    /// payload of DATA or HEADERS without the padding
+    pub(super) fn unpadded(&self) -> Result<&[u8], ErrorCode> {
+        if !self.has(flag::PADDED) {
+            return Ok(&self.payload);
+        }
+        let (&pad_len, rest) = self
+            .payload
+            .split_first()
+            .ok_or(ErrorCode::FrameSizeError)?;
+        rest.len()
+            .checked_sub(pad_len as usize)
+            .map(|len| &rest[..len])
+            .ok_or(ErrorCode::ProtocolError)
+    }
+}
+
+pub(super) fn write(buf: &mut Vec<u8>, kind: u8, flags: u8, stream_id: u32, payload: &[u8]) {
+    buf.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+    buf.push(kind);
+    buf.push(flags);
+    buf.extend_from_slice(&stream_id.to_be_bytes());
+    buf.extend_from_slice(payload);
+}
+
+pub(super) fn write_settings(buf: &mut Vec<u8>, settings: &[(u16, u32)]) {
+    let mut payload = Vec::with_capacity(6 * settings.len());
+    for (id, value) in settings {
+        payload.extend_from_slice(&id.to_be_bytes());
+        payload.extend_from_slice(&value.to_be_bytes());
+    }
+    write(buf, kind::SETTINGS, 0, 0, &payload);
+}
+
+pub(super) fn write_window_update(buf: &mut Vec<u8>, stream_id: u32, increment: u32) {
+    write(
+        buf,
+        kind::WINDOW_UPDATE,
+        0,
+        stream_id,
+        &increment.to_be_bytes(),
+    );
+}
+
+pub(super) fn write_rst_stream(buf: &mut Vec<u8>, stream_id: u32, error: ErrorCode) {
+    write(
+        buf,
+        kind::RST_STREAM,
+        0,
+        stream_id,
+        &(error as u32).to_be_bytes(),
+    );
+}
+
+pub(super) fn write_goaway(buf: &mut Vec<u8>, last_stream_id: u32, error: ErrorCode) {
+    let mut payload = [0; 8];
+    payload[..4].copy_from_slice(&last_stream_id.to_be_bytes());
+    payload[4..].copy_from_slice(&(error as u32).to_be_bytes());
+    write(buf, kind::GOAWAY, 0, 0, &payload);
+}
+
+/// HEADERS followed by CONTINUATIONs if `block` is larger than `max_frame_size`
+pub(super) fn write_headers(
+    buf: &mut Vec<u8>,
+    stream_id: u32,
+    block: &[u8],
+    end_stream: bool,
+    max_frame_size: usize,
+) {
+    let mut fragments = block.chunks(max_frame_size).peekable();
+    let mut kind = kind::HEADERS;
+    let mut flags = if end_stream { flag::END_STREAM } else { 0 };
+    loop {
+        let fragment = fragments.next().unwrap_or_default();
+        if fragments.peek().is_none() {
+            flags |= flag::END_HEADERS;
+        }
+        write(buf, kind, flags, stream_id, fragment);
+        if flags & flag::END_HEADERS != 0 {
+            break;
+        }
+        (kind, flags) = (kind::CONTINUATION, 0);
+    }
+}
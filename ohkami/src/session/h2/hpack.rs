@@ -0,0 +1,453 @@
+//! HPACK header compression ( RFC 7541 )
+//!
+//! The decoder fully supports the dynamic table, while the encoder just uses
+//! literal representations without indexing, not to make the peer's
+//! decoder to keep any state for us.
+
+use super::huffman;
+use std::collections::VecDeque;
+
+/// ( RFC 7541, Appendix A )
+const STATIC_TABLE: [(&[u8], &[u8]); 61] = [
+    (b":authority", b""),
+    (b":method", b"GET"),
+    (b":method", b"POST"),
+    (b":path", b"/"),
+    (b":path", b"/index.html"),
+    (b":scheme", b"http"),
+    (b":scheme", b"https"),
+    (b":status", b"200"),
+    (b":status", b"204"),
+    (b":status", b"206"),
+    (b":status", b"304"),
+    (b":status", b"400"),
+    (b":status", b"404"),
+    (b":status", b"500"),
+    (b"accept-charset", b""),
+    (b"accept-encoding", b"gzip, deflate"),
+    (b"accept-language", b""),
+    (b"accept-ranges", b""),
+    (b"accept", b""),
+    (b"access-control-allow-origin", b""),
+    (b"age", b""),
+    (b"allow", b""),
+    (b"authorization", b""),
+    (b"cache-control", b""),
+    (b"content-disposition", b""),
+    (b"content-encoding", b""),
+    (b"content-language", b""),
+    (b"content-length", b""),
+    (b"content-location", b""),
+    (b"content-range", b""),
+    (b"content-type", b""),
+    (b"cookie", b""),
+    (b"date", b""),
+    (b"etag", b""),
+    (b"expect", b""),
+    (b"expires", b""),
+    (b"from", b""),
+    (b"host", b""),
+    (b"if-match", b""),
+    (b"if-modified-since", b""),
+    (b"if-none-match", b""),
+    (b"if-range", b""),
+    (b"if-unmodified-since", b""),
+    (b"last-modified", b""),
+    (b"link", b""),
+    (b"location", b""),
+    (b"max-forwards", b""),
+    (b"proxy-authenticate", b""),
+    (b"proxy-authorization", b""),
+    (b"range", b""),
+    (b"referer", b""),
+    (b"refresh", b""),
+    (b"retry-after", b""),
+    (b"server", b""),
+    (b"set-cookie", b""),
+    (b"strict-transport-security", b""),
+    (b"transfer-encoding", b""),
+    (b"user-agent", b""),
+    (b"vary", b""),
+    (b"via", b""),
+    (b"www-authenticate", b""),
+];
+
+/// index of `:status` in `STATIC_TABLE` ( 1-origin )
+const STATUS: usize = 8;
+
+/// `SETTINGS_HEADER_TABLE_SIZE` of ours, that is just the default value
+pub(super) const HEADER_TABLE_SIZE: usize = 4096;
+
+/// Any malformed header block, to be a connection error of `COMPRESSION_ERROR`
+#[derive(Debug, PartialEq)]
+pub(super) struct CompressionError;
+
+pub(super) type Field = (Vec<u8>, Vec<u8>);
+
+pub(super) struct Fields {
+    pub(super) list: Vec<Field>,
+    /// The decoded list exceeded the limit, then the rest of the fields
+    /// were decoded just to keep the dynamic table in sync and discarded.
+    pub(super) too_large: bool,
+}
+
+pub(super) struct Decoder {
+    dynamic_table: VecDeque<Field>,
+    /// sum of the entry sizes in `dynamic_table`
+    size: usize,
+    max_size: usize,
+}
+
+impl Decoder {
+    pub(super) fn new() -> Self {
+        Self {
+            dynamic_table: VecDeque::new(),
+            size: 0,
+            max_size: HEADER_TABLE_SIZE,
+        }
+    }
+
+    /// Decode a header block into the field list, whose size is limited to
+    /// `max_list_size` as `SETTINGS_MAX_HEADER_LIST_SIZE`.
+    pub(super) fn decode(
+        &mut self,
+        mut block: &[u8],
+        max_list_size: usize,
+    ) -> Result<Fields, CompressionError> {
+        let mut fields = Fields {
+            list: Vec::new(),
+            too_large: false,
+        };
+        let mut list_size = 0;
+        let mut is_head = true;
+
+        while let Some(&first) = block.first() {
+            let field = if first & 0b1000_0000 != 0 {
+                // indexed header field
+                let index = decode_int(&mut block, 7)?;
+                self.get(index)?
+            } else if first & 0b1100_0000 == 0b0100_0000 {
+                // literal header field with incremental indexing
+                let field = self.decode_literal(&mut block, 6)?;
+                self.insert(field.clone());
+                field
+            } else if first & 0b1110_0000 == 0b0010_0000 {
+                // dynamic table size update, only allowed at the head of a block
+                if !is_head {
+                    return Err(CompressionError);
+                }
+                let max_size = decode_int(&mut block, 5)?;
+                if max_size > HEADER_TABLE_SIZE {
+                    return Err(CompressionError);
+                }
+                self.max_size = max_size;
+                self.evict(0);
+                continue;
+            } else {
+                // literal header field without indexing / never indexed
+                self.decode_literal(&mut block, 4)?
+            };
+            is_head = false;
+
+            list_size += field.0.len() + field.1.len() + 32;
+            if list_size > max_list_size {
+                fields.too_large = true;
+            }
+            if !fields.too_large {
+                fields.list.push(field);
+            }
+        }
+
+        Ok(fields)
+    }
+
+    fn decode_literal(
+        &self,
+        block: &mut &[u8],
+        prefix_bits: u8,
+    ) -> Result<Field, CompressionError> {
+        let name = match decode_int(block, prefix_bits)? {
+            0 => decode_string(block)?,
+            index => self.get(index)?.0,
+        };
+        let value = decode_string(block)?;
+        Ok((name, value))
+    }
+
+    fn get(&self, index: usize) -> Result<Field, CompressionError> {
+        match index {
+            0 => Err(CompressionError),
+            1..=61 => {
+                let (name, value) = STATIC_TABLE[index - 1];
+                Ok((name.to_vec(), value.to_vec()))
+            }
+            _ => self
+                .dynamic_table
+                .get(index - 62)
+                .cloned()
+                .ok_or(CompressionError),
+        }
+    }
+
+    fn insert(&mut self, field: Field) {
+        let size = field.0.len() + field.1.len() + 32;
+        self.evict(size);
+        if size <= self.max_size {
+            self.size += size;
+            self.dynamic_table.push_front(field);
+        }
+    }
+
+    /// evict the oldest entries to make room for `incoming` size
+    fn evict(&mut self, incoming: usize) {
+        while self.size + incoming > self.max_size {
+            let Some((name, value)) = self.dynamic_table.pop_back() else {
+                break;
+            };
+            self.size -= name.len() + value.len() + 32;
+        }
+    }
+}
+
+fn decode_int(block: &mut &[u8], prefix_bits: u8) -> Result<usize, CompressionError> {
+    let (&first, rest) = block.split_first().ok_or(CompressionError)?;
+    *block = rest;
+
+    let max_prefix = (1 << prefix_bits) - 1;
+    let mut int = (first & max_prefix) as usize;
+    if int < max_prefix as usize {
+        return Ok(int);
+    }
+
+    for shift in (0..28).step_by(7) {
+        let (&b, rest) = block.split_first().ok_or(CompressionError)?;
+        *block = rest;
+        int += ((b & 0b0111_1111) as usize) << shift;
+        if b & 0b1000_0000 == 0 {
+            return Ok(int);
+        }
+    }
+    // too large integer for any field
+    Err(CompressionError)
+}
+
+fn decode_string(block: &mut &[u8]) -> Result<Vec<u8>, CompressionError> {
+    let huffman = block.first().ok_or(CompressionError)? & 0b1000_0000 != 0;
+    let len = decode_int(block, 7)?;
+    if block.len() < len {
+        return Err(CompressionError);
+    }
+    let (string, rest) = block.split_at(len);
+    *block = rest;
+
+    if huffman {
+        huffman::decode(string).ok_or(CompressionError)
+    } else {
+        Ok(string.to_vec())
+    }
+}
+
+fn encode_int(buf: &mut Vec<u8>, first: u8, prefix_bits: u8, mut int: usize) {
+    let max_prefix = (1 << prefix_bits) - 1;
+    if int < max_prefix as usize {
+        buf.push(first | int as u8);
+        return;
+    }
+    buf.push(first | max_prefix);
+    int -= max_prefix as usize;
+    while int >= 0b1000_0000 {
+        buf.push((int & 0b0111_1111) as u8 | 0b1000_0000);
+        int >>= 7;
+    }
+    buf.push(int as u8);
+}
+
+fn encode_string(buf: &mut Vec<u8>, string: &[u8]) {
+    encode_int(buf, 0, 7, string.len());
+    buf.extend_from_slice(string);
+}
+
+pub(super) fn encode_status(buf: &mut Vec<u8>, status: u16) {
+    let status = status.to_string();
+    match STATIC_TABLE[STATUS - 1..STATUS + 6]
+        .iter()
+        .position(|(_, value)| *value == status.as_bytes())
+    {
+        Some(i) => encode_int(buf, 0b1000_0000, 7, STATUS + i),
+        None => {
+            encode_int(buf, 0, 4, STATUS);
+            encode_string(buf, status.as_bytes());
+        }
+    }
+}
+
+/// `name` must be lowercase
+pub(super) fn encode_field(buf: &mut Vec<u8>, name: &[u8], value: &[u8]) {
+    buf.push(0);
+    encode_string(buf, name);
+    encode_string(buf, value);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn hex(s: &str) -> Vec<u8> {
+        let s = s.replace(' ', "");
+        (0..s.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&s[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    fn fields(list: &[(&str, &str)]) -> Vec<Field> {
+        list.iter()
+            .map(|(name, value)| (name.as_bytes().to_vec(), value.as_bytes().to_vec()))
+            .collect()
+    }
+
+    #[test]
+    fn test_decode_requests() {
+        /* RFC 7541, Appendix C.3 ( without Huffman ) and C.4 ( with Huffman ) */
+        for blocks in [
+            [
+                "8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d",
+                "8286 84be 5808 6e6f 2d63 6163 6865",
+                "8287 85bf 400a 6375 7374 6f6d 2d6b 6579 0c63 7573 746f 6d2d 7661 6c75 65",
+            ],
+            [
+                "8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff",
+                "8286 84be 5886 a8eb 1064 9cbf",
+                "8287 85bf 4088 25a8 49e9 5ba9 7d7f 8925 a849 e95b b8e8 b4bf",
+            ],
+        ] {
+            let mut decoder = Decoder::new();
+
+            let decoded = decoder.decode(&hex(blocks[0]), 1024).unwrap();
+            assert!(!decoded.too_large);
+            assert_eq!(
+                decoded.list,
+                fields(&[
+                    (":method", "GET"),
+                    (":scheme", "http"),
+                    (":path", "/"),
+                    (":authority", "www.example.com"),
+                ])
+            );
+            assert_eq!(decoder.size, 57);
+
+            let decoded = decoder.decode(&hex(blocks[1]), 1024).unwrap();
+            assert_eq!(
+                decoded.list,
+                fields(&[
+                    (":method", "GET"),
+                    (":scheme", "http"),
+                    (":path", "/"),
+                    (":authority", "www.example.com"),
+                    ("cache-control", "no-cache"),
+                ])
+            );
+            assert_eq!(decoder.size, 110);
+
+            let decoded = decoder.decode(&hex(blocks[2]), 1024).unwrap();
+            assert_eq!(
+                decoded.list,
+                fields(&[
+                    (":method", "GET"),
+                    (":scheme", "https"),
+                    (":path", "/index.html"),
+                    (":authority", "www.example.com"),
+                    ("custom-key", "custom-value"),
+                ])
+            );
+            assert_eq!(decoder.size, 164);
+        }
+    }
+
+    #[test]
+    fn test_decode_eviction() {
+        /* RFC 7541, Appendix C.5 ( `SETTINGS_HEADER_TABLE_SIZE` is 256 ) */
+        let mut decoder = Decoder::new();
+        decoder.max_size = 256;
+
+        for block in [
+            "4803 3330 3258 0770 7269 7661 7465 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133 2032 303a 3133 3a32 3120 474d 546e 1768 7474 7073 3a2f 2f77 7777 2e65 7861 6d70 6c65 2e63 6f6d",
+            "4803 3330 37c1 c0bf",
+        ] {
+            decoder.decode(&hex(block), 1024).unwrap();
+        }
+        assert_eq!(decoder.size, 222);
+        assert_eq!(
+            decoder.dynamic_table,
+            VecDeque::from(fields(&[
+                (":status", "307"),
+                ("location", "https://www.example.com"),
+                ("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                ("cache-control", "private"),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_decode_limits() {
+        let mut decoder = Decoder::new();
+
+        // the rest fields over the limit are discarded,
+        // but still inserted into the dynamic table
+        let decoded = decoder
+            .decode(&hex("8286 4003 6b65 7905 76616c7565 84"), 50)
+            .unwrap();
+        assert!(decoded.too_large);
+        assert_eq!(decoded.list, fields(&[(":method", "GET")]));
+        assert_eq!(
+            decoder.dynamic_table,
+            VecDeque::from(fields(&[("key", "value")]))
+        );
+
+        // index out of the tables
+        assert_eq!(
+            decoder.decode(&hex("c0"), 1024).err(),
+            Some(CompressionError)
+        );
+        assert_eq!(
+            decoder.decode(&hex("80"), 1024).err(),
+            Some(CompressionError)
+        );
+        // table size update after a field
+        assert_eq!(
+            decoder.decode(&hex("82 20"), 1024).err(),
+            Some(CompressionError)
+        );
+        // table size update over our setting
+        assert_eq!(
+            decoder.decode(&hex("3fe2 1f"), 1024).err(),
+            Some(CompressionError)
+        );
+        // truncated string
+        assert_eq!(
+            decoder.decode(&hex("0003 6b65"), 1024).err(),
+            Some(CompressionError)
+        );
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut buf = Vec::new();
+        encode_status(&mut buf, 200);
+        encode_status(&mut buf, 418);
+        encode_field(&mut buf, b"content-type", b"text/plain");
+        encode_field(&mut buf, b"x-long", &[b'a'; 200]);
+
+        let decoded = Decoder::new().decode(&buf, 1024).unwrap();
+        assert_eq!(
+            decoded.list,
+            fields(&[
+                (":status", "200"),
+                (":status", "418"),
+                ("content-type", "text/plain"),
+                ("x-long", &"a".repeat(200)),
+            ])
+        );
+        assert_eq!(buf[0], 0x88);
+    }
+}
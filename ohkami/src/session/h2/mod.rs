@@ -0,0 +1,1106 @@
+#![cfg(feature = "http2")]
+
+//! HTTP/2 ( RFC 9113 ) connection driver, multiplexing the streams onto
+//! the same `Router::handle` as HTTP/1.1.
+//!
+//! Each request is converted into an HTTP/1.1 message and read by
+//! `Request::read_head` just like HTTP/1.1, so that fangs and handlers can't
+//! tell the difference. The handler is called when the request head arrives,
+//! and the flow-control windows for its payload are given back as it's read.
+
+mod frame;
+mod hpack;
+mod huffman;
+mod recv;
+
+#[cfg(test)]
+mod _test;
+
+pub(crate) use frame::PREFACE;
+
+use self::frame::{ErrorCode, Frame, flag, kind, setting};
+use self::recv::{Message, SharedPipe};
+use crate::fang::SendOnThreaded;
+use crate::ohkami::sync::{Phase, Shutdown};
+use crate::request::Head;
+use crate::response::Content;
+use crate::router::r#final::Router;
+use crate::util::with_timeout;
+use crate::{Request, Response};
+use ohkami_lib::Stream;
+use std::collections::BTreeMap;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::task::{Context, Poll, ready};
+use std::{future::Future, io, pin::Pin, sync::Arc, time::Duration};
+
+#[cfg(feature = "__io_futures__")]
+use futures_util::{AsyncRead, AsyncWrite};
+#[cfg(feature = "__io_tokio__")]
+use tokio::io::{AsyncRead, AsyncWrite};
+
+/// `SETTINGS_MAX_CONCURRENT_STREAMS` of ours
+const MAX_CONCURRENT_STREAMS: usize = 128;
+
+const DEFAULT_WINDOW_SIZE: i64 = 65535;
+const MAX_WINDOW_SIZE: i64 = (1 << 31) - 1;
+
+/// connection-level flow-control window for receiving DATA, bounding the
+/// request payloads received but not read by the handlers yet on a connection.
+/// Each stream's one is `DEFAULT_WINDOW_SIZE`.
+const CONNECTION_WINDOW_SIZE: i64 = 1 << 20;
+
+/// streams reset by the peer or refused on a connection, beyond which
+/// the connection is closed ( against "rapid reset", CVE-2023-44487 )
+const MAX_RESET_STREAMS: usize = 1000;
+
+trait Handling: Future<Output = Response> + SendOnThreaded {}
+impl<F: Future<Output = Response> + SendOnThreaded> Handling for F {}
+
+type Body = Pin<Box<dyn Stream<Item = io::Result<Vec<u8>>> + Send>>;
+
+pub(crate) struct H2<'c, C> {
+    conn: &'c mut C,
+    router: Arc<Router>,
+    config: crate::Config,
+    ip: std::net::IpAddr,
+
+    read_buf: Vec<u8>,
+    write_buf: Vec<u8>,
+    /// the peer has closed its writing side
+    eof: bool,
+
+    decoder: hpack::Decoder,
+    /// a header block waiting for its CONTINUATION frames
+    continuation: Option<Continuation>,
+
+    streams: BTreeMap<u32, H2Stream>,
+    handlings: Vec<(u32, Pin<Box<dyn Handling>>)>,
+    last_stream_id: u32,
+    /// the peer has sent GOAWAY and won't open any more streams
+    goaway: bool,
+    /// streams reset by the peer or refused so far
+    reset_streams: usize,
+    /// resolves when the server starts shutting down, then `None`
+    /// after GOAWAY is sent
+    shutdown: Option<Phase>,
+    health: crate::Health,
+    info: super::ConnectionInfo,
+
+    /// connection-level flow-control window for sending DATA
+    send_window: i64,
+    /// connection-level flow-control window for receiving DATA
+    recv_window: i64,
+    peer_initial_window_size: i64,
+    peer_max_frame_size: usize,
+}
+
+struct Continuation {
+    stream_id: u32,
+    end_stream: bool,
+    block: Vec<u8>,
+}
+
+struct H2Stream {
+    /// the request payload passed to the handler
+    pipe: SharedPipe,
+    /// the request being received, `None` once it's complete
+    recv: Option<Recv>,
+    /// the response payload being sent, `None` while the request is
+    /// received or handled
+    send: Option<Sending>,
+    send_window: i64,
+}
+
+struct Recv {
+    /// `content-length` of the request
+    content_length: Option<usize>,
+    /// size of the payload received so far
+    received: usize,
+    /// flow-control window for receiving DATA
+    window: i64,
+}
+
+struct Sending {
+    buf: Vec<u8>,
+    pos: usize,
+    /// rest of the payload, `None` when `buf` is the last part
+    body: Option<Body>,
+}
+
+enum Event {
+    Frame(Frame),
+    Response(u32, Response),
+    Chunk(u32, Option<io::Result<Vec<u8>>>),
+    /// the handlers have read some payloads, and the windows are given back
+    Consumed,
+    Shutdown,
+}
+
+impl<'c, C: AsyncRead + AsyncWrite + Unpin> H2<'c, C> {
+    pub(crate) fn new(
+        conn: &'c mut C,
+        router: Arc<Router>,
+        config: crate::Config,
+        ip: std::net::IpAddr,
+        shutdown: Shutdown,
+        info: super::ConnectionInfo,
+    ) -> Self {
+        Self {
+            conn,
+            router,
+            config,
+            ip,
+            read_buf: Vec::new(),
+            write_buf: Vec::new(),
+            eof: false,
+            decoder: hpack::Decoder::new(),
+            continuation: None,
+            streams: BTreeMap::new(),
+            handlings: Vec::new(),
+            last_stream_id: 0,
+            goaway: false,
+            reset_streams: 0,
+            shutdown: Some(shutdown.draining()),
+            health: crate::Health::of(&shutdown),
+            info,
+            send_window: DEFAULT_WINDOW_SIZE,
+            recv_window: CONNECTION_WINDOW_SIZE,
+            peer_initial_window_size: DEFAULT_WINDOW_SIZE,
+            peer_max_frame_size: frame::MAX_FRAME_SIZE,
+        }
+    }
+
+    /// Serve HTTP/2 on the connection until closed. `preread` is the bytes
+    /// already read from the connection, starting with the connection preface
+    /// in h2c with prior knowledge, or empty after ALPN negotiation.
+    pub(crate) async fn serve(mut self, preread: &[u8]) {
+        use crate::__rt__::AsyncWrite as _;
+
+        self.read_buf.extend_from_slice(preread);
+
+        frame::write_settings(
+            &mut self.write_buf,
+            &[
+                (
+                    setting::MAX_CONCURRENT_STREAMS,
+                    MAX_CONCURRENT_STREAMS as u32,
+                ),
+                (
+                    setting::MAX_HEADER_LIST_SIZE,
+                    self.config.max_request_head_size as u32,
+                ),
+            ],
+        );
+        frame::write_window_update(
+            &mut self.write_buf,
+            0,
+            (CONNECTION_WINDOW_SIZE - DEFAULT_WINDOW_SIZE) as u32,
+        );
+
+        let error = match with_timeout(
+            Duration::from_secs(self.config.keepalive_timeout),
+            self.read_preface(),
+        )
+        .await
+        {
+            Some(true) => self.run().await,
+            Some(false) | None => Some(ErrorCode::ProtocolError),
+        };
+
+        if let Some(error) = error {
+            frame::write_goaway(&mut self.write_buf, self.last_stream_id, error);
+            if self.conn.write_all(&self.write_buf).await.is_ok() {
+                let _ = self.conn.flush().await;
+            }
+        }
+    }
+
+    async fn read_preface(&mut self) -> bool {
+        while self.read_buf.len() < PREFACE.len() {
+            if !PREFACE.starts_with(&self.read_buf) {
+                break;
+            }
+            match std::future::poll_fn(|cx| self.poll_read(cx)).await {
+                Ok(0) | Err(_) => return false,
+                Ok(_) => (),
+            }
+        }
+        if !self.read_buf.starts_with(PREFACE) {
+            crate::WARNING!("[http2] Invalid connection preface");
+            return false;
+        }
+        self.read_buf.drain(..PREFACE.len());
+        true
+    }
+
+    /// Returns an error code to be sent by GOAWAY, or `None` if
+    /// the connection is lost.
+    async fn run(&mut self) -> Option<ErrorCode> {
+        use crate::__rt__::AsyncWrite as _;
+
+        loop {
+            self.write_data();
+            if !self.write_buf.is_empty() {
+                let written = async {
+                    self.conn.write_all(&self.write_buf).await?;
+                    self.conn.flush().await
+                };
+                if let Err(e) = written.await {
+                    crate::WARNING!("[http2] Failed to write to the connection: {e}");
+                    return None;
+                }
+                self.write_buf.clear();
+            }
+
+            // without the peer, responses waiting for WINDOW_UPDATE never progress
+            if self.eof
+                && self.handlings.is_empty()
+                && (self.send_window <= 0 || self.streams.values().all(|s| s.send_window <= 0))
+            {
+                return None;
+            }
+            if (self.goaway || self.shutdown.is_none()) && self.streams.is_empty() {
+                return Some(ErrorCode::NoError);
+            }
+
+            let event = if self.streams.is_empty() {
+                match with_timeout(
+                    Duration::from_secs(self.config.keepalive_timeout),
+                    std::future::poll_fn(|cx| self.poll_event(cx)),
+                )
+                .await
+                {
+                    Some(event) => event,
+                    None => {
+                        crate::DEBUG!(
+                            "[http2] Reached Keep-Alive timeout ({} secs)",
+                            self.config.keepalive_timeout
+                        );
+                        return Some(ErrorCode::NoError);
+                    }
+                }
+            } else {
+                std::future::poll_fn(|cx| self.poll_event(cx)).await
+            };
+
+            let result = match event {
+                Ok(Some(Event::Frame(frame))) => self.on_frame(frame),
+                Ok(Some(Event::Response(stream_id, res))) => {
+                    self.on_response(stream_id, res);
+                    Ok(())
+                }
+                Ok(Some(Event::Chunk(stream_id, chunk))) => {
+                    self.on_chunk(stream_id, chunk);
+                    Ok(())
+                }
+                Ok(Some(Event::Consumed)) => Ok(()),
+                Ok(Some(Event::Shutdown)) => {
+                    crate::DEBUG!("[http2] Sending GOAWAY for shutdown");
+                    // let the peer retry the requests on streams not to be processed
+                    frame::write_goaway(
+                        &mut self.write_buf,
+                        self.last_stream_id,
+                        ErrorCode::NoError,
+                    );
+                    self.shutdown = None;
+                    Ok(())
+                }
+                Ok(None) => {
+                    self.eof = true;
+                    // requests never to be complete
+                    let receiving = (self.streams.iter())
+                        .filter_map(|(&id, stream)| stream.recv.is_some().then_some(id))
+                        .collect::<Vec<_>>();
+                    for stream_id in receiving {
+                        self.close(stream_id);
+                    }
+                    Ok(())
+                }
+                Err(error) => Err(error),
+            };
+            if let Err(error) = result {
+                return Some(error);
+            }
+        }
+    }
+
+    /// `Ok(None)` when no more frames come from the peer
+    fn poll_event(&mut self, cx: &mut Context<'_>) -> Poll<Result<Option<Event>, ErrorCode>> {
+        if let Some(shutdown) = &mut self.shutdown
+            && Pin::new(shutdown).poll(cx).is_ready()
+        {
+            return Poll::Ready(Ok(Some(Event::Shutdown)));
+        }
+
+        for i in 0..self.handlings.len() {
+            if let Poll::Ready(res) = self.handlings[i].1.as_mut().poll(cx) {
+                let (stream_id, _) = self.handlings.swap_remove(i);
+                return Poll::Ready(Ok(Some(Event::Response(stream_id, res))));
+            }
+        }
+        if self.on_consumed() {
+            return Poll::Ready(Ok(Some(Event::Consumed)));
+        }
+
+        if self.send_window > 0 {
+            for (&stream_id, stream) in &mut self.streams {
+                if let Some(Sending {
+                    buf,
+                    pos,
+                    body: Some(body),
+                }) = &mut stream.send
+                    && *pos == buf.len()
+                    && stream.send_window > 0
+                    && let Poll::Ready(chunk) = body.as_mut().poll_next(cx)
+                {
+                    return Poll::Ready(Ok(Some(Event::Chunk(stream_id, chunk))));
+                }
+            }
+        }
+
+        if self.eof {
+            return Poll::Pending;
+        }
+        loop {
+            if let Some((frame, size)) = Frame::parse(&self.read_buf)? {
+                self.read_buf.drain(..size);
+                return Poll::Ready(Ok(Some(Event::Frame(frame))));
+            }
+            match ready!(self.poll_read(cx)) {
+                Ok(0) => return Poll::Ready(Ok(None)),
+                Ok(_) => (),
+                Err(e) => {
+                    crate::WARNING!("[http2] Failed to read from the connection: {e}");
+                    return Poll::Ready(Ok(None));
+                }
+            }
+        }
+    }
+
+    fn poll_read(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<usize>> {
+        let mut chunk = [0; 1 << 14];
+        let conn = Pin::new(&mut *self.conn);
+
+        #[cfg(feature = "__io_tokio__")]
+        let n = {
+            let mut chunk = tokio::io::ReadBuf::new(&mut chunk);
+            ready!(conn.poll_read(cx, &mut chunk))?;
+            chunk.filled().len()
+        };
+        #[cfg(feature = "__io_futures__")]
+        let n = ready!(conn.poll_read(cx, &mut chunk))?;
+
+        self.read_buf.extend_from_slice(&chunk[..n]);
+        Poll::Ready(Ok(n))
+    }
+
+    fn on_frame(&mut self, frame: Frame) -> Result<(), ErrorCode> {
+        // a header block must be sent as a contiguous sequence of frames
+        if let Some(continuation) = &self.continuation
+            && !(frame.kind == kind::CONTINUATION && frame.stream_id == continuation.stream_id)
+        {
+            return Err(ErrorCode::ProtocolError);
+        }
+
+        match frame.kind {
+            kind::DATA => self.on_data(frame),
+            kind::HEADERS => self.on_headers(frame),
+            kind::CONTINUATION => self.on_continuation(frame),
+            kind::PRIORITY => {
+                if frame.stream_id == 0 {
+                    return Err(ErrorCode::ProtocolError);
+                }
+                if frame.payload.len() != 5 {
+                    self.reset(frame.stream_id, ErrorCode::FrameSizeError);
+                }
+                Ok(())
+            }
+            kind::RST_STREAM => {
+                if frame.stream_id == 0 || frame.stream_id > self.last_stream_id {
+                    return Err(ErrorCode::ProtocolError);
+                }
+                if frame.payload.len() != 4 {
+                    return Err(ErrorCode::FrameSizeError);
+                }
+                if self.streams.contains_key(&frame.stream_id) {
+                    self.close(frame.stream_id);
+                    self.count_reset()?;
+                }
+                Ok(())
+            }
+            kind::SETTINGS => self.on_settings(frame),
+            kind::PUSH_PROMISE => Err(ErrorCode::ProtocolError),
+            kind::PING => {
+                if frame.stream_id != 0 {
+                    return Err(ErrorCode::ProtocolError);
+                }
+                if frame.payload.len() != 8 {
+                    return Err(ErrorCode::FrameSizeError);
+                }
+                if !frame.has(flag::ACK) {
+                    frame::write(
+                        &mut self.write_buf,
+                        kind::PING,
+                        flag::ACK,
+                        0,
+                        &frame.payload,
+                    );
+                }
+                Ok(())
+            }
+            kind::GOAWAY => {
+                if frame.stream_id != 0 {
+                    return Err(ErrorCode::ProtocolError);
+                }
+                self.goaway = true;
+                Ok(())
+            }
+            kind::WINDOW_UPDATE => self.on_window_update(frame),
+            _ => Ok(/* unknown frame types must be ignored */ ()),
+        }
+    }
+
+    fn on_settings(&mut self, frame: Frame) -> Result<(), ErrorCode> {
+        if frame.stream_id != 0 {
+            return Err(ErrorCode::ProtocolError);
+        }
+        if frame.has(flag::ACK) {
+            return match frame.payload.len() {
+                0 => Ok(()),
+                _ => Err(ErrorCode::FrameSizeError),
+            };
+        }
+        if !frame.payload.len().is_multiple_of(6) {
+            return Err(ErrorCode::FrameSizeError);
+        }
+
+        for param in frame.payload.chunks(6) {
+            let id = u16::from_be_bytes([param[0], param[1]]);
+            let value = u32::from_be_bytes([param[2], param[3], param[4], param[5]]);
+            match id {
+                setting::ENABLE_PUSH if value > 1 => return Err(ErrorCode::ProtocolError),
+                setting::INITIAL_WINDOW_SIZE => {
+                    let value = value as i64;
+                    if value > MAX_WINDOW_SIZE {
+                        return Err(ErrorCode::FlowControlError);
+                    }
+                    let delta = value - self.peer_initial_window_size;
+                    for stream in self.streams.values_mut() {
+                        stream.send_window += delta;
+                        if stream.send_window > MAX_WINDOW_SIZE {
+                            return Err(ErrorCode::FlowControlError);
+                        }
+                    }
+                    self.peer_initial_window_size = value;
+                }
+                setting::MAX_FRAME_SIZE => {
+                    if !(frame::MAX_FRAME_SIZE..(1 << 24)).contains(&(value as usize)) {
+                        return Err(ErrorCode::ProtocolError);
+                    }
+                    self.peer_max_frame_size = value as usize;
+                }
+                _ => (/* others don't matter to us */),
+            }
+        }
+
+        frame::write(&mut self.write_buf, kind::SETTINGS, flag::ACK, 0, &[]);
+        Ok(())
+    }
+
+    fn on_window_update(&mut self, frame: Frame) -> Result<(), ErrorCode> {
+        let Ok(increment) = <[u8; 4]>::try_from(&*frame.payload) else {
+            return Err(ErrorCode::FrameSizeError);
+        };
+        let increment = (u32::from_be_bytes(increment) & 0x7fff_ffff) as i64;
+
+        if frame.stream_id == 0 {
+            if increment == 0 {
+                return Err(ErrorCode::ProtocolError);
+            }
+            self.send_window += increment;
+            if self.send_window > MAX_WINDOW_SIZE {
+                return Err(ErrorCode::FlowControlError);
+            }
+        } else if let Some(stream) = self.streams.get_mut(&frame.stream_id) {
+            if increment == 0 {
+                self.reset(frame.stream_id, ErrorCode::ProtocolError);
+            } else {
+                stream.send_window += increment;
+                if stream.send_window > MAX_WINDOW_SIZE {
+                    self.reset(frame.stream_id, ErrorCode::FlowControlError);
+                }
+            }
+        } else if frame.stream_id > self.last_stream_id {
+            return Err(ErrorCode::ProtocolError);
+        }
+        Ok(())
+    }
+
+    fn on_headers(&mut self, frame: Frame) -> Result<(), ErrorCode> {
+        if frame.stream_id == 0 {
+            return Err(ErrorCode::ProtocolError);
+        }
+
+        let mut block = frame.unpadded()?;
+        if frame.has(flag::PRIORITY) {
+            block = block.get(5..).ok_or(ErrorCode::FrameSizeError)?;
+        }
+
+        let continuation = Continuation {
+            stream_id: frame.stream_id,
+            end_stream: frame.has(flag::END_STREAM),
+            block: block.to_vec(),
+        };
+        if frame.has(flag::END_HEADERS) {
+            self.on_header_block(continuation)
+        } else {
+            self.continuation = Some(continuation);
+            Ok(())
+        }
+    }
+
+    fn on_continuation(&mut self, frame: Frame) -> Result<(), ErrorCode> {
+        let Some(mut continuation) = self.continuation.take() else {
+            return Err(ErrorCode::ProtocolError);
+        };
+
+        continuation.block.extend_from_slice(&frame.payload);
+        // HPACK can't compress a header list into less than a quarter
+        if continuation.block.len() > self.config.max_request_head_size * 4 {
+            crate::WARNING!("[http2] Too large header block");
+            return Err(ErrorCode::EnhanceYourCalm);
+        }
+
+        if frame.has(flag::END_HEADERS) {
+            self.on_header_block(continuation)
+        } else {
+            self.continuation = Some(continuation);
+            Ok(())
+        }
+    }
+
+    fn on_header_block(
+        &mut self,
+        Continuation {
+            stream_id,
+            end_stream,
+            block,
+        }: Continuation,
+    ) -> Result<(), ErrorCode> {
+        // always decode the block to keep the dynamic table in sync
+        let fields = self
+            .decoder
+            .decode(&block, self.config.max_request_head_size)
+            .map_err(|_| {
+                crate::WARNING!("[http2] Failed to decode a header block");
+                ErrorCode::CompressionError
+            })?;
+
+        if let Some(stream) = self.streams.get_mut(&stream_id) {
+            // trailer section
+            if stream.recv.is_none() {
+                self.reset(stream_id, ErrorCode::StreamClosed);
+            } else if !end_stream {
+                self.reset(stream_id, ErrorCode::ProtocolError);
+            } else {
+                crate::DEBUG!("[http2] Ignoring trailer fields of stream {stream_id}");
+                self.end_request(stream_id);
+            }
+            return Ok(());
+        }
+
+        if stream_id <= self.last_stream_id {
+            // the stream is already closed
+            return Err(ErrorCode::StreamClosed);
+        }
+        if stream_id % 2 == 0 {
+            return Err(ErrorCode::ProtocolError);
+        }
+        if self.shutdown.is_none() {
+            // opened after our GOAWAY, to be retried by the peer
+            return Ok(());
+        }
+        self.last_stream_id = stream_id;
+
+        if self.streams.len() >= MAX_CONCURRENT_STREAMS {
+            frame::write_rst_stream(&mut self.write_buf, stream_id, ErrorCode::RefusedStream);
+            return self.count_reset();
+        }
+
+        if fields.too_large {
+            crate::WARNING!(
+                "[http2] Header list exceeded `max_request_head_size` ({} bytes)",
+                self.config.max_request_head_size
+            );
+            self.respond_early(stream_id, Response::RequestHeaderFieldsTooLarge());
+            return Ok(());
+        }
+        let (head, content_length) = match http1_head(&fields, end_stream) {
+            Ok(head) => head,
+            Err(Malformed(reason)) => {
+                crate::WARNING!("[http2] Malformed request: {reason}");
+                frame::write_rst_stream(&mut self.write_buf, stream_id, ErrorCode::ProtocolError);
+                return Ok(());
+            }
+        };
+
+        let pipe = SharedPipe::default();
+        self.streams.insert(
+            stream_id,
+            H2Stream {
+                pipe: pipe.clone(),
+                recv: Some(Recv {
+                    content_length,
+                    received: 0,
+                    window: DEFAULT_WINDOW_SIZE,
+                }),
+                send: None,
+                send_window: self.peer_initial_window_size,
+            },
+        );
+        // without `content-length`, the payload is passed in chunked encoding
+        let chunked = !end_stream && content_length.is_none();
+        self.handle(stream_id, Message::new(head, chunked, pipe));
+        if end_stream {
+            self.end_request(stream_id);
+        }
+        Ok(())
+    }
+
+    fn on_data(&mut self, frame: Frame) -> Result<(), ErrorCode> {
+        if frame.stream_id == 0 {
+            return Err(ErrorCode::ProtocolError);
+        }
+
+        let size = frame.payload.len();
+        self.recv_window -= size as i64;
+        if self.recv_window < 0 {
+            return Err(ErrorCode::FlowControlError);
+        }
+
+        let data = frame.unpadded()?;
+        let Some(stream) = self.streams.get_mut(&frame.stream_id) else {
+            self.give_back(0, size);
+            return match frame.stream_id > self.last_stream_id {
+                true => Err(ErrorCode::ProtocolError),
+                false => Ok(/* the stream is already closed */ ()),
+            };
+        };
+        let Some(recv) = &mut stream.recv else {
+            self.give_back(0, size);
+            self.reset(frame.stream_id, ErrorCode::StreamClosed);
+            return Ok(());
+        };
+
+        recv.window -= size as i64;
+        if recv.window < 0 {
+            self.give_back(0, size);
+            self.reset(frame.stream_id, ErrorCode::FlowControlError);
+            return Ok(());
+        }
+        recv.received += data.len();
+        if recv.content_length.is_some_and(|len| recv.received > len) {
+            crate::WARNING!("[http2] Malformed request: payload exceeds `content-length`");
+            self.give_back(0, size);
+            self.reset(frame.stream_id, ErrorCode::ProtocolError);
+            return Ok(());
+        }
+
+        // the windows are given back when the handler reads the payload,
+        // except for the padding and the payload after the handler is done
+        let unread = match stream.pipe.is_read() {
+            true => {
+                stream.pipe.lock().push(data);
+                size - data.len()
+            }
+            false => size,
+        };
+        if frame.has(flag::END_STREAM) {
+            self.end_request(frame.stream_id);
+        }
+        self.give_back(frame.stream_id, unread);
+        Ok(())
+    }
+
+    /// The request of the stream is completely received
+    fn end_request(&mut self, stream_id: u32) {
+        let Some(stream) = self.streams.get_mut(&stream_id) else {
+            return;
+        };
+        let Some(recv) = stream.recv.take() else {
+            return;
+        };
+
+        if recv.content_length.is_some_and(|len| len != recv.received) {
+            crate::WARNING!(
+                "[http2] Malformed request: `content-length` doesn't match the payload"
+            );
+            return self.reset(stream_id, ErrorCode::ProtocolError);
+        }
+        stream.pipe.lock().end();
+    }
+
+    /// Give back the flow-control windows of the connection, and of the stream
+    /// if it's still receiving the request ( `0` for only the connection )
+    fn give_back(&mut self, stream_id: u32, size: usize) {
+        if size == 0 {
+            return;
+        }
+        frame::write_window_update(&mut self.write_buf, 0, size as u32);
+        self.recv_window += size as i64;
+        if let Some(recv) = (self.streams.get_mut(&stream_id)).and_then(|s| s.recv.as_mut()) {
+            frame::write_window_update(&mut self.write_buf, stream_id, size as u32);
+            recv.window += size as i64;
+        }
+    }
+
+    /// Give back the windows for the payloads read by the handlers, and send
+    /// `100 Continue` asked by them. `true` if any frame is written.
+    fn on_consumed(&mut self) -> bool {
+        let written = self.write_buf.len();
+
+        let mut consumed = Vec::new();
+        for (&stream_id, stream) in &mut self.streams {
+            let mut pipe = stream.pipe.lock();
+            consumed.push((stream_id, pipe.take_consumed()));
+            if !stream.pipe.is_read() {
+                // the handler is done without reading the rest
+                consumed.push((0, pipe.discard()));
+            }
+            if pipe.take_continue_requested() && stream.recv.is_some() && stream.send.is_none() {
+                let mut block = Vec::new();
+                hpack::encode_status(&mut block, 100);
+                frame::write_headers(
+                    &mut self.write_buf,
+                    stream_id,
+                    &block,
+                    false,
+                    self.peer_max_frame_size,
+                );
+            }
+        }
+        for (stream_id, size) in consumed {
+            self.give_back(stream_id, size);
+        }
+
+        self.write_buf.len() > written
+    }
+
+    /// Start handling the request of the stream
+    fn handle(&mut self, stream_id: u32, mut message: Message) {
+        let (router, config, ip) = (self.router.clone(), self.config, self.ip);
+        let (health, info) = (self.health.clone(), self.info.clone());
+        self.handlings.push((
+            stream_id,
+            Box::pin(async move {
+                let mut req = Request::uninit(ip, &config);
+                req.context.load_server(health, info);
+                let mut req = Pin::new(&mut req);
+
+                match req.as_mut().read_head(&mut message, &config).await {
+                    Ok(Head::Request) => {
+                        match catch_unwind(AssertUnwindSafe({
+                            let req = req.as_mut();
+                            || router.handle(req.get_mut())
+                        })) {
+                            Ok(future) => {
+                                // catch a panic in polling too, not to abort
+                                // the other streams on the connection
+                                let mut future = std::pin::pin!(future);
+                                std::future::poll_fn(|cx| {
+                                    catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx)))
+                                        .unwrap_or_else(|panic| {
+                                            Poll::Ready(super::panicking(panic))
+                                        })
+                                })
+                                .await
+                            }
+                            Err(panic) => super::panicking(panic),
+                        }
+                    }
+                    Ok(_) => Response::NotImplemented(),
+                    Err(mut res) => {
+                        res.complete();
+                        res
+                    }
+                }
+            }),
+        ));
+    }
+
+    fn on_response(&mut self, stream_id: u32, res: Response) {
+        let Some(stream) = self.streams.get_mut(&stream_id) else {
+            return;
+        };
+
+        let (block, buf, body) = response_parts(res);
+        let end_stream = buf.is_empty() && body.is_none();
+        frame::write_headers(
+            &mut self.write_buf,
+            stream_id,
+            &block,
+            end_stream,
+            self.peer_max_frame_size,
+        );
+
+        if end_stream {
+            self.finish(stream_id);
+        } else {
+            stream.send = Some(Sending { buf, pos: 0, body });
+        }
+    }
+
+    fn on_chunk(&mut self, stream_id: u32, chunk: Option<io::Result<Vec<u8>>>) {
+        let Some(Sending { buf, pos, body }) = self
+            .streams
+            .get_mut(&stream_id)
+            .and_then(|stream| stream.send.as_mut())
+        else {
+            return;
+        };
+
+        match chunk {
+            Some(Ok(chunk)) => (*buf, *pos) = (chunk, 0),
+            None => *body = None,
+            Some(Err(e)) => {
+                crate::ERROR!("[http2] Aborting byte stream by error: {e}");
+                self.reset(stream_id, ErrorCode::InternalError);
+            }
+        }
+    }
+
+    /// Write DATA frames as much as the flow-control windows allow
+    fn write_data(&mut self) {
+        let mut finished = Vec::new();
+
+        for (&stream_id, stream) in &mut self.streams {
+            let Some(Sending { buf, pos, body }) = &mut stream.send else {
+                continue;
+            };
+
+            while *pos < buf.len() && self.send_window > 0 && stream.send_window > 0 {
+                let size = (buf.len() - *pos)
+                    .min(self.peer_max_frame_size)
+                    .min(self.send_window as usize)
+                    .min(stream.send_window as usize);
+                let end_stream = *pos + size == buf.len() && body.is_none();
+
+                frame::write(
+                    &mut self.write_buf,
+                    kind::DATA,
+                    if end_stream { flag::END_STREAM } else { 0 },
+                    stream_id,
+                    &buf[*pos..*pos + size],
+                );
+                *pos += size;
+                self.send_window -= size as i64;
+                stream.send_window -= size as i64;
+
+                if end_stream {
+                    finished.push(stream_id);
+                }
+            }
+
+            // a body stream has ended just after its last chunk is sent
+            if *pos == buf.len() && body.is_none() && !finished.contains(&stream_id) {
+                frame::write(
+                    &mut self.write_buf,
+                    kind::DATA,
+                    flag::END_STREAM,
+                    stream_id,
+                    &[],
+                );
+                finished.push(stream_id);
+            }
+        }
+
+        for stream_id in finished {
+            self.finish(stream_id);
+        }
+    }
+
+    /// Send a response without body before the request is complete,
+    /// and close the stream
+    fn respond_early(&mut self, stream_id: u32, mut res: Response) {
+        res.complete();
+        let (block, _, _) = response_parts(res);
+        frame::write_headers(
+            &mut self.write_buf,
+            stream_id,
+            &block,
+            true,
+            self.peer_max_frame_size,
+        );
+        // ask the peer to stop sending the request without error
+        self.reset(stream_id, ErrorCode::NoError);
+    }
+
+    fn reset(&mut self, stream_id: u32, error: ErrorCode) {
+        frame::write_rst_stream(&mut self.write_buf, stream_id, error);
+        self.close(stream_id);
+    }
+
+    /// Close the stream whose response is sent, asking the peer to stop
+    /// sending the rest of the request without error
+    fn finish(&mut self, stream_id: u32) {
+        if (self.streams.get(&stream_id)).is_some_and(|stream| stream.recv.is_some()) {
+            frame::write_rst_stream(&mut self.write_buf, stream_id, ErrorCode::NoError);
+        }
+        self.close(stream_id);
+    }
+
+    fn close(&mut self, stream_id: u32) {
+        if let Some(stream) = self.streams.remove(&stream_id) {
+            let unread = stream.pipe.lock().abort();
+            self.give_back(0, unread);
+        }
+        self.handlings.retain(|(id, _)| *id != stream_id);
+    }
+
+    /// Count a stream reset by the peer or refused, failing
+    /// beyond `MAX_RESET_STREAMS`
+    fn count_reset(&mut self) -> Result<(), ErrorCode> {
+        self.reset_streams += 1;
+        if self.reset_streams > MAX_RESET_STREAMS {
+            crate::WARNING!(
+                "[http2] More than {MAX_RESET_STREAMS} streams were reset on a connection, closing it"
+            );
+            return Err(ErrorCode::EnhanceYourCalm);
+        }
+        Ok(())
+    }
+}
+
+struct Malformed(&'static str);
+
+/// Convert a request head of HTTP/2 into HTTP/1.1 one to be read
+/// by `Request::read_head`, with its `content-length`
+fn http1_head(
+    fields: &hpack::Fields,
+    end_stream: bool,
+) -> Result<(Vec<u8>, Option<usize>), Malformed> {
+    let (mut method, mut path, mut authority) = (None, None, None);
+    let mut content_length = None;
+    let mut cookies = Vec::<&[u8]>::new();
+    let mut head = Vec::new();
+
+    let mut pseudo = true;
+    for (name, value) in &fields.list {
+        if value.iter().any(|b| matches!(b, b'\0' | b'\r' | b'\n')) {
+            return Err(Malformed("invalid field value"));
+        }
+
+        if let Some(pseudo_name) = name.strip_prefix(b":") {
+            if !pseudo {
+                return Err(Malformed("pseudo-header field after regular field"));
+            }
+            let slot = match pseudo_name {
+                b"method" => &mut method,
+                b"path" => &mut path,
+                b"authority" => &mut authority,
+                b"scheme" => continue,
+                _ => return Err(Malformed("unknown pseudo-header field")),
+            };
+            if slot.replace(value).is_some() {
+                return Err(Malformed("duplicate pseudo-header field"));
+            }
+            continue;
+        }
+        pseudo = false;
+
+        if name.is_empty()
+            || !name.iter().all(|b| {
+                b.is_ascii_lowercase() || b.is_ascii_digit() || b"!#$%&'*+-.^_`|~".contains(b)
+            })
+        {
+            return Err(Malformed("invalid field name"));
+        }
+        match &**name {
+            b"connection" | b"keep-alive" | b"proxy-connection" | b"transfer-encoding"
+            | b"upgrade" => return Err(Malformed("connection-specific field")),
+            b"te" if value != b"trailers" => return Err(Malformed("invalid `te`")),
+            b"content-length" => content_length = Some(value),
+            b"cookie" => cookies.push(value),
+            _ => {
+                head.extend_from_slice(name);
+                head.extend_from_slice(b": ");
+                head.extend_from_slice(value);
+                head.extend_from_slice(b"\r\n");
+            }
+        }
+    }
+
+    let (Some(method), Some(path)) = (method, path) else {
+        return Err(Malformed("missing `:method` or `:path`"));
+    };
+    if !path.starts_with(b"/") {
+        return Err(Malformed("invalid `:path`"));
+    }
+    let content_length = match content_length {
+        None => None,
+        Some(value) => Some(
+            (std::str::from_utf8(value).ok())
+                .and_then(|value| value.parse::<usize>().ok())
+                .ok_or(Malformed("invalid `content-length`"))?,
+        ),
+    };
+
+    let mut message = Vec::with_capacity(head.len() + (1 << 8));
+    for part in [&**method, b" ", path, b" HTTP/1.1\r\n"] {
+        message.extend_from_slice(part);
+    }
+    if let Some(authority) = authority
+        && !fields.list.iter().any(|(name, _)| name == b"host")
+    {
+        for part in [b"host: ", &**authority, b"\r\n"] {
+            message.extend_from_slice(part);
+        }
+    }
+    if !cookies.is_empty() {
+        message.extend_from_slice(b"cookie: ");
+        message.extend_from_slice(&cookies.join(&b"; "[..]));
+        message.extend_from_slice(b"\r\n");
+    }
+    message.extend_from_slice(&head);
+    if let Some(len) = content_length {
+        message.extend_from_slice(format!("content-length: {len}\r\n").as_bytes());
+    } else if !end_stream {
+        message.extend_from_slice(b"transfer-encoding: chunked\r\n");
+    }
+    message.extend_from_slice(b"\r\n");
+
+    Ok((message, content_length))
+}
+
+/// ( header block, payload, rest of the payload )
+fn response_parts(res: Response) -> (Vec<u8>, Vec<u8>, Option<Body>) {
+    let mut block = Vec::new();
+    hpack::encode_status(&mut block, res.status.code());
+    for (name, value) in res.headers.iter() {
+        let name = name.to_ascii_lowercase();
+        if matches!(
+            &*name,
+            "connection" | "keep-alive" | "proxy-connection" | "transfer-encoding" | "upgrade"
+        ) {
+            continue;
+        }
+        hpack::encode_field(&mut block, name.as_bytes(), value.as_bytes());
+    }
+
+    match res.content {
+        Content::None => (block, Vec::new(), None),
+        Content::Payload(payload) => (block, payload.to_vec(), None),
+        #[cfg(feature = "sse")]
+        Content::Stream(stream) => {
+            use ohkami_lib::stream::StreamExt;
+            let body = stream.map(|chunk| Ok(crate::response::encode_sse_message(&chunk)));
+            (block, Vec::new(), Some(Box::pin(body)))
+        }
+        Content::ByteStream(stream) => (block, Vec::new(), Some(stream)),
+        #[cfg(feature = "ws")]
+        Content::WebSocket(_) => {
+            crate::WARNING!("[http2] WebSocket is not supported over HTTP/2");
+            (block, Vec::new(), None)
+        }
+    }
+}
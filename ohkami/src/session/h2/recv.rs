@@ -0,0 +1,184 @@
+//! Request payload passed from the connection to the handler as it arrives,
+//! so that the flow-control windows are given back as the handler reads it
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::task::{Context, Poll, Waker};
+
+/// max bytes of the payload taken from `Pipe` at once
+const TAKE_SIZE: usize = 1 << 14;
+
+/// DATA payload of a stream received but not read by the handler yet
+#[derive(Default)]
+pub(super) struct Pipe {
+    buf: Vec<u8>,
+    /// bytes read by the handler, whose windows are not given back yet
+    consumed: usize,
+    /// the request is complete
+    end: bool,
+    /// the stream is closed before the request is complete
+    aborted: bool,
+    /// the handler waits for `100 Continue` to be sent
+    continue_requested: bool,
+    waker: Option<Waker>,
+}
+
+#[derive(Clone, Default)]
+pub(super) struct SharedPipe(Arc<Mutex<Pipe>>);
+
+impl SharedPipe {
+    pub(super) fn lock(&self) -> MutexGuard<'_, Pipe> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// `false` once the handler is done and drops its `Message`
+    pub(super) fn is_read(&self) -> bool {
+        Arc::strong_count(&self.0) > 1
+    }
+}
+
+impl Pipe {
+    pub(super) fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+        self.wake();
+    }
+
+    pub(super) fn end(&mut self) {
+        self.end = true;
+        self.wake();
+    }
+
+    /// close the pipe, returning the bytes whose windows are not given back
+    pub(super) fn abort(&mut self) -> usize {
+        if !self.end {
+            self.aborted = true;
+        }
+        self.wake();
+        self.discard()
+    }
+
+    /// drop the bytes not read, returning the bytes whose windows are not given back
+    pub(super) fn discard(&mut self) -> usize {
+        let n = self.buf.len() + self.consumed;
+        self.buf.clear();
+        self.consumed = 0;
+        n
+    }
+
+    pub(super) fn take_consumed(&mut self) -> usize {
+        std::mem::take(&mut self.consumed)
+    }
+
+    pub(super) fn take_continue_requested(&mut self) -> bool {
+        std::mem::take(&mut self.continue_requested)
+    }
+
+    fn wake(&mut self) {
+        if let Some(waker) = self.waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// HTTP/1.1 message of the request to be read by `Request::read_head`,
+/// the converted head followed by the payload from `Pipe`
+pub(super) struct Message {
+    pipe: SharedPipe,
+    /// encode the payload in `Transfer-Encoding: chunked` as it has no `content-length`
+    chunked: bool,
+    staged: Vec<u8>,
+    pos: usize,
+    done: bool,
+}
+
+impl Message {
+    pub(super) fn new(head: Vec<u8>, chunked: bool, pipe: SharedPipe) -> Self {
+        Self {
+            pipe,
+            chunked,
+            staged: head,
+            pos: 0,
+            done: false,
+        }
+    }
+
+    fn poll_read_bytes(&mut self, cx: &mut Context<'_>, out: &mut [u8]) -> Poll<io::Result<usize>> {
+        loop {
+            if self.pos < self.staged.len() {
+                let n = out.len().min(self.staged.len() - self.pos);
+                out[..n].copy_from_slice(&self.staged[self.pos..self.pos + n]);
+                self.pos += n;
+                return Poll::Ready(Ok(n));
+            }
+            if self.done {
+                return Poll::Ready(Ok(0));
+            }
+
+            let mut pipe = self.pipe.lock();
+            self.staged.clear();
+            self.pos = 0;
+            if !pipe.buf.is_empty() {
+                let n = pipe.buf.len().min(TAKE_SIZE);
+                if self.chunked {
+                    self.staged
+                        .extend_from_slice(format!("{n:x}\r\n").as_bytes());
+                }
+                self.staged.extend(pipe.buf.drain(..n));
+                if self.chunked {
+                    self.staged.extend_from_slice(b"\r\n");
+                }
+                pipe.consumed += n;
+            } else if pipe.end {
+                if self.chunked {
+                    self.staged.extend_from_slice(b"0\r\n\r\n");
+                }
+                self.done = true;
+            } else if pipe.aborted {
+                return Poll::Ready(Err(io::ErrorKind::UnexpectedEof.into()));
+            } else {
+                pipe.waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl crate::request::payload::ReadStream for Message {
+    /// ask the connection to send `:status 100`
+    fn poll_write_continue(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        _: &mut usize,
+    ) -> Poll<io::Result<()>> {
+        self.pipe.lock().continue_requested = true;
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "__io_tokio__")]
+impl tokio::io::AsyncRead for Message {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let n = std::task::ready!(
+            self.get_mut()
+                .poll_read_bytes(cx, buf.initialize_unfilled())
+        )?;
+        buf.advance(n);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "__io_futures__")]
+impl futures_util::AsyncRead for Message {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        self.get_mut().poll_read_bytes(cx, buf)
+    }
+}
@@ -0,0 +1,165 @@
+use super::ProxyHeader;
+use crate::{FromRequest, ListenAddr, Request};
+use std::{net::SocketAddr, sync::Arc};
+
+/// Information of the connection a request came in on, available in handlers
+/// as `&ConnectionInfo` ( or by `req.context.connection()` in fangs ).
+///
+/// This is not available for a `Request` not served by `howl`
+/// ( like in `ohkami::testing` ).
+///
+/// ## Example
+///
+/// ```
+/// use ohkami::{Ohkami, Route, ConnectionInfo};
+///
+/// async fn connection(conn: &ConnectionInfo) -> String {
+///     format!(
+///         "{} ( TLS: {} )",
+///         conn.local_addr().map_or("-".into(), |addr| addr.to_string()),
+///         conn.is_tls(),
+///     )
+/// }
+///
+/// # fn __() -> Ohkami {
+/// Ohkami::new((
+///     "/connection".GET(connection),
+/// ))
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConnectionInfo {
+    pub(crate) listen_addr: Arc<ListenAddr>,
+    pub(crate) local_addr: Option<SocketAddr>,
+    pub(crate) proxy: Option<Arc<ProxyHeader>>,
+    #[cfg(feature = "tls")]
+    pub(crate) tls: Option<Arc<TlsInfo>>,
+}
+
+impl ConnectionInfo {
+    pub(crate) fn new(listen_addr: Arc<ListenAddr>, local_addr: Option<SocketAddr>) -> Self {
+        Self {
+            listen_addr,
+            local_addr,
+            proxy: None,
+            #[cfg(feature = "tls")]
+            tls: None,
+        }
+    }
+
+    /// Local address of the listener the connection was accepted on
+    pub fn listen_addr(&self) -> &ListenAddr {
+        &self.listen_addr
+    }
+
+    /// Local socket address of the connection, which is a concrete address
+    /// even when the listener is bound to an unspecified one like `0.0.0.0`.
+    /// `None` for a Unix domain socket.
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr
+    }
+
+    /// PROXY protocol header of the connection when `proxy_protocol` of `Config` is enabled
+    pub fn proxy(&self) -> Option<&ProxyHeader> {
+        self.proxy.as_deref()
+    }
+
+    /// Whether the connection is over TLS
+    pub fn is_tls(&self) -> bool {
+        #[cfg(feature = "tls")]
+        {
+            self.tls.is_some()
+        }
+        #[cfg(not(feature = "tls"))]
+        {
+            false
+        }
+    }
+
+    /// TLS session of the connection, `None` if not over TLS
+    #[cfg(feature = "tls")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+    pub fn tls(&self) -> Option<&TlsInfo> {
+        self.tls.as_deref()
+    }
+}
+
+impl<'req> FromRequest<'req> for &'req ConnectionInfo {
+    type Error = std::convert::Infallible;
+
+    #[inline(always)]
+    fn from_request(req: &'req Request) -> Option<Result<Self, Self::Error>> {
+        req.context.connection().map(Ok)
+    }
+}
+
+/// TLS session negotiated on a connection, available in handlers as `&TlsInfo`
+/// ( or by `req.context.connection()` and `.tls()` in fangs ) for a connection
+/// over TLS.
+///
+/// When client authentication ( mTLS ) is configured on the `rustls::ServerConfig`
+/// passed to `howls`, [`peer_certificates`](TlsInfo::peer_certificates) provides the
+/// client's certificate chain, whose subject can be checked by an X.509 parser
+/// for authorization.
+#[cfg(feature = "tls")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tls")))]
+#[derive(Debug)]
+pub struct TlsInfo {
+    server_name: Option<String>,
+    alpn_protocol: Option<Vec<u8>>,
+    protocol_version: Option<rustls::ProtocolVersion>,
+    cipher_suite: Option<rustls::CipherSuite>,
+    peer_certificates: Vec<rustls::pki_types::CertificateDer<'static>>,
+}
+
+#[cfg(feature = "tls")]
+impl TlsInfo {
+    pub(crate) fn of(session: &rustls::ServerConnection) -> Self {
+        Self {
+            server_name: session.server_name().map(str::to_owned),
+            alpn_protocol: session.alpn_protocol().map(<[u8]>::to_vec),
+            protocol_version: session.protocol_version(),
+            cipher_suite: session.negotiated_cipher_suite().map(|s| s.suite()),
+            peer_certificates: session
+                .peer_certificates()
+                .map(<[_]>::to_vec)
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Hostname the client requested by SNI
+    pub fn server_name(&self) -> Option<&str> {
+        self.server_name.as_deref()
+    }
+
+    /// Protocol negotiated by ALPN, like `b"h2"` or `b"http/1.1"`
+    pub fn alpn_protocol(&self) -> Option<&[u8]> {
+        self.alpn_protocol.as_deref()
+    }
+
+    /// TLS version, like `TLSv1_3`
+    pub fn protocol_version(&self) -> Option<rustls::ProtocolVersion> {
+        self.protocol_version
+    }
+
+    /// Cipher suite, like `TLS13_AES_256_GCM_SHA384`
+    pub fn cipher_suite(&self) -> Option<rustls::CipherSuite> {
+        self.cipher_suite
+    }
+
+    /// DER-encoded certificate chain of the client, the end-entity first.
+    /// Empty when the client didn't present a certificate.
+    pub fn peer_certificates(&self) -> &[rustls::pki_types::CertificateDer<'static>] {
+        &self.peer_certificates
+    }
+}
+
+#[cfg(feature = "tls")]
+impl<'req> FromRequest<'req> for &'req TlsInfo {
+    type Error = std::convert::Infallible;
+
+    #[inline(always)]
+    fn from_request(req: &'req Request) -> Option<Result<Self, Self::Error>> {
+        req.context.connection()?.tls().map(Ok)
+    }
+}
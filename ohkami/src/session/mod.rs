@@ -1,52 +1,130 @@
 #![cfg(feature = "__rt_native__")]
 
 mod connection;
+mod info;
+mod proxy;
+
+#[cfg(feature = "http2")]
+mod h2;
 
 pub use self::connection::Connection;
+pub use self::info::ConnectionInfo;
+#[cfg(feature = "tls")]
+pub use self::info::TlsInfo;
+pub use self::proxy::ProxyHeader;
 
+use crate::ohkami::sync::{Shutdown, until};
+use crate::request::{Head, Version};
 use crate::response::Upgrade;
 use crate::router::r#final::Router;
-use crate::util::with_timeout;
 use crate::{Request, Response};
 use std::panic::{AssertUnwindSafe, catch_unwind};
-use std::{any::Any, pin::Pin, sync::Arc, time::Duration};
+use std::{any::Any, pin::Pin, sync::Arc};
+
+#[cold]
+#[inline(never)]
+fn panicking(panic: Box<dyn Any + Send>) -> Response {
+    if let Some(msg) = panic.downcast_ref::<String>() {
+        crate::WARNING!("[Panicked]: {msg}");
+    } else if let Some(msg) = panic.downcast_ref::<&str>() {
+        crate::WARNING!("[Panicked]: {msg}");
+    } else {
+        crate::WARNING!("[Panicked]");
+    }
+    crate::Response::InternalServerError()
+}
 
 pub(crate) struct Session {
     config: crate::Config,
     connection: Connection,
     router: Arc<Router>,
     ip: std::net::IpAddr,
+    shutdown: Shutdown,
+    info: ConnectionInfo,
 }
 
 impl Session {
-    pub(crate) fn new(
+    /// Set up a session on a newly accepted connection : reading PROXY protocol
+    /// header if `proxy_protocol` of `config` is enabled, and then performing
+    /// TLS handshake if `tls_acceptor` is given, to complete `info`. `None` if
+    /// the connection is rejected or closed in the meantime.
+    pub(crate) async fn accept(
         config: crate::Config,
         connection: impl Into<Connection>,
         ip: std::net::IpAddr,
+        mut info: ConnectionInfo,
+        #[cfg(feature = "tls")] tls_acceptor: Option<anysc_rustls::TlsAcceptor>,
         router: Arc<Router>,
-    ) -> Self {
-        Self {
+        shutdown: Shutdown,
+    ) -> Option<Self> {
+        let (mut connection, mut ip) = (connection.into(), ip);
+
+        info.proxy = if config.proxy_protocol {
+            let read = crate::util::with_timeout(
+                std::time::Duration::from_secs(config.request_head_timeout),
+                proxy::read(&mut connection),
+            );
+            match read.await {
+                Some(Ok(header)) => {
+                    if let Some(source) = header.source() {
+                        ip = source.ip();
+                    }
+                    Some(Arc::new(header))
+                }
+                Some(Err(reason)) => {
+                    crate::WARNING!("[proxy_protocol] Rejected connection from {ip}: {reason}");
+                    return None;
+                }
+                None => {
+                    crate::WARNING!(
+                        "\
+                        [proxy_protocol] Rejected connection from {ip}: PROXY protocol header \
+                        didn't complete within `request_head_timeout` ({} secs).\
+                    ",
+                        config.request_head_timeout
+                    );
+                    return None;
+                }
+            }
+        } else {
+            None
+        };
+
+        #[cfg(feature = "tls")]
+        let connection = match (tls_acceptor, connection) {
+            (Some(tls_acceptor), Connection::Tcp(tcp_stream)) => {
+                match tls_acceptor.accept(tcp_stream).await {
+                    Ok(tls_stream) => {
+                        info.tls = Some(Arc::new(TlsInfo::of(tls_stream.get_ref().1)));
+                        tls_stream.into()
+                    }
+                    Err(e) => {
+                        crate::ERROR!("TLS accept error: {e}");
+                        return None;
+                    }
+                }
+            }
+            (_, connection) => connection,
+        };
+
+        Some(Self {
+            info,
             config,
-            connection: connection.into(),
+            connection,
             ip,
             router,
-        }
+            shutdown,
+        })
     }
 
-    pub(crate) async fn manage(mut self) {
-        #[cold]
-        #[inline(never)]
-        fn panicking(panic: Box<dyn Any + Send>) -> Response {
-            if let Some(msg) = panic.downcast_ref::<String>() {
-                crate::WARNING!("[Panicked]: {msg}");
-            } else if let Some(msg) = panic.downcast_ref::<&str>() {
-                crate::WARNING!("[Panicked]: {msg}");
-            } else {
-                crate::WARNING!("[Panicked]");
-            }
-            crate::Response::InternalServerError()
+    pub(crate) async fn manage(self) {
+        let shutdown = self.shutdown.clone();
+        if until(shutdown.closing(), self.serve()).await.is_none() {
+            crate::DEBUG!("force-closed connection for shutdown");
         }
+    }
 
+    async fn serve(mut self) {
         #[cold]
         #[inline(never)]
         fn handle_send_failure(error: std::io::Error) {
@@ -61,42 +139,73 @@ impl Session {
             }
         }
 
+        #[cfg(all(feature = "http2", feature = "tls"))]
+        if self.connection.is_alpn_h2() {
+            crate::DEBUG!("HTTP/2 session started ( ALPN )");
+            return h2::H2::new(
+                &mut self.connection,
+                self.router,
+                self.config,
+                self.ip,
+                self.shutdown,
+                self.info,
+            )
+            .serve(&[])
+            .await;
+        }
+
         let mut req = Request::uninit(self.ip, &self.config);
+        req.context
+            .load_server(crate::Health::of(&self.shutdown), self.info.clone());
         let mut req = Pin::new(&mut req);
         let upgrade = loop {
             req.clear();
-            // Apply a fresh timeout for each read, thus resetting the timer on activity.
-            match with_timeout(
-                Duration::from_secs(self.config.keepalive_timeout),
-                req.as_mut().read(&mut self.connection, &self.config),
-            )
-            .await
-            {
+            // may have been rewritten by `fang::TrustedProxy` for the previous request
+            req.ip = self.ip;
+            // `read_head` applies `keepalive_timeout` until the next request
+            // comes, and then `request_head_timeout` for the rest of the head.
+            let read = until(
+                self.shutdown.draining(),
+                req.as_mut().read_head(&mut self.connection, &self.config),
+            );
+            match read.await {
                 None => {
-                    crate::DEBUG!(
-                        "\
-                        Reached Keep-Alive timeout ({} secs). \
-                        The timeout can be configured via `keepalive_timeout` of `Config`, \
-                        or `OHKAMI_KEEPALIVE_TIMEOUT` environment variable.\
-                        (default: {})\
-                    ",
-                        self.config.keepalive_timeout,
-                        crate::Config::default().keepalive_timeout
-                    );
+                    crate::DEBUG!("closing idle connection for shutdown");
                     break Upgrade::None;
                 }
                 Some(read_result) => match read_result {
-                    Ok(Some(())) => {
-                        let close = matches!(req.headers.connection(), Some("close" | "Close"));
+                    Ok(Head::Request) => {
+                        let mut close = match req.version {
+                            Version::Http11 => {
+                                matches!(req.headers.connection(), Some("close" | "Close"))
+                            }
+                            // HTTP/1.0 closes the connection by default
+                            Version::Http10 => !req
+                                .headers
+                                .connection()
+                                .is_some_and(|c| c.eq_ignore_ascii_case("keep-alive")),
+                        };
 
-                        let res = match catch_unwind(AssertUnwindSafe({
+                        let mut res = match catch_unwind(AssertUnwindSafe({
                             let req = req.as_mut();
                             || self.router.handle(req.get_mut())
                         })) {
                             Ok(future) => future.await,
                             Err(panic) => panicking(panic),
                         };
-                        let upgrade = match res.send(&mut self.connection).await {
+
+                        // the rest of the payload left on the connection
+                        // would be read as the next request
+                        let unread_payload = !req.__unread__.is_done();
+                        if unread_payload || self.shutdown.is_draining() {
+                            res.headers.set().connection("close");
+                            close = true;
+                        }
+                        if req.version == Version::Http10 {
+                            close = !res.downgrade_to_http10(!close);
+                        }
+
+                        let upgrade = match res.send(&mut self.connection, req.version).await {
                             Ok(upgrade) => upgrade,
                             Err(e) => {
                                 handle_send_failure(e);
@@ -111,12 +220,27 @@ impl Session {
                             break Upgrade::None;
                         }
                     }
-                    Ok(None) => {
+                    Ok(Head::None) => {
                         break Upgrade::None;
                     }
+                    #[cfg(feature = "http2")]
+                    Ok(Head::H2c(read_len)) => {
+                        crate::DEBUG!("HTTP/2 session started ( prior knowledge )");
+                        let preread = req.__buf__[..read_len].to_vec();
+                        return h2::H2::new(
+                            &mut self.connection,
+                            self.router,
+                            self.config,
+                            self.ip,
+                            self.shutdown,
+                            self.info,
+                        )
+                        .serve(&preread)
+                        .await;
+                    }
                     Err(mut res) => {
                         res.headers.set().connection("close");
-                        if let Err(e) = res.send(&mut self.connection).await {
+                        if let Err(e) = res.send(&mut self.connection, req.version).await {
                             handle_send_failure(e);
                         }
                         break Upgrade::None;
@@ -124,6 +248,8 @@ impl Session {
                 },
             }
         };
+        // release the connection held by the request before moving it
+        req.clear();
 
         match upgrade {
             Upgrade::None => {
@@ -134,9 +260,17 @@ impl Session {
             Upgrade::WebSocket(ws) => {
                 crate::DEBUG!("WebSocket session started");
 
+                let timeout = self.config.websocket_timeout;
                 let aborted = ws
                     .manage_with_timeout(
-                        crate::__rt__::sleep(Duration::from_secs(self.config.websocket_timeout)),
+                        async move {
+                            match timeout {
+                                0 => std::future::pending().await,
+                                secs => {
+                                    crate::__rt__::sleep(std::time::Duration::from_secs(secs)).await
+                                }
+                            }
+                        },
                         self.connection,
                     )
                     .await;
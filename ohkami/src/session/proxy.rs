@@ -0,0 +1,392 @@
+//! PROXY protocol ( v1 text header and v2 binary header ) sent by a proxy
+//! like HAProxy or AWS NLB at the start of a connection, carrying the
+//! address of the original client.
+//!
+//! See <https://www.haproxy.org/download/2.9/doc/proxy-protocol.txt>
+
+use crate::__rt__::AsyncRead;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const V1_PREFIX: &[u8] = b"PROXY ";
+/// including the trailing CRLF
+const V1_MAX_LEN: usize = 107;
+
+const V2_SIGNATURE: [u8; 12] = *b"\r\n\r\n\0\r\nQUIT\n";
+
+/// PROXY protocol header of the connection, available in handlers as
+/// `&ProxyHeader` ( or by `req.context.proxy()` in fangs ) when
+/// `proxy_protocol` of `Config` is enabled.
+///
+/// `Request::ip` is already the original client's one if `source` is known.
+///
+/// ## Example
+///
+/// ```
+/// use ohkami::{Ohkami, Route, ProxyHeader};
+///
+/// async fn whoami(proxy: Option<&ProxyHeader>) -> String {
+///     match proxy.and_then(ProxyHeader::destination) {
+///         Some(destination) => format!("you connected to {destination}"),
+///         None => String::from("no idea"),
+///     }
+/// }
+///
+/// # fn __() -> Ohkami {
+/// Ohkami::new((
+///     "/whoami".GET(whoami),
+/// ))
+/// # }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct ProxyHeader {
+    version: u8,
+    source: Option<SocketAddr>,
+    destination: Option<SocketAddr>,
+    tlvs: Vec<(u8, Box<[u8]>)>,
+}
+
+impl ProxyHeader {
+    /// `1` ( text header ) or `2` ( binary header )
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Address of the original client. `None` for `UNKNOWN` ( v1 ) or
+    /// `LOCAL` ( v2, like health checks by the proxy itself ) connections,
+    /// or for address families other than TCP/UDP over IPv4/IPv6.
+    pub fn source(&self) -> Option<SocketAddr> {
+        self.source
+    }
+
+    /// Address the original client connected to, typically the proxy's.
+    /// `None` in the same cases as [`source`](ProxyHeader::source).
+    pub fn destination(&self) -> Option<SocketAddr> {
+        self.destination
+    }
+
+    /// Type-Length-Value vectors of v2 header, like `0x01` ( ALPN ),
+    /// `0x02` ( authority ) or AWS's `0xEA` ( VPC endpoint ID ).
+    /// Always empty for v1 header.
+    pub fn tlvs(&self) -> impl Iterator<Item = (u8, &[u8])> {
+        self.tlvs.iter().map(|(kind, value)| (*kind, &**value))
+    }
+
+    /// Value of the first TLV of the type, if any
+    pub fn tlv(&self, kind: u8) -> Option<&[u8]> {
+        self.tlvs()
+            .find_map(|(k, value)| (k == kind).then_some(value))
+    }
+}
+
+impl<'req> crate::FromRequest<'req> for &'req ProxyHeader {
+    type Error = std::convert::Infallible;
+
+    #[inline(always)]
+    fn from_request(req: &'req crate::Request) -> Option<Result<Self, Self::Error>> {
+        req.context.proxy().map(Ok)
+    }
+}
+
+/// Read a PROXY protocol header at the start of `stream`, without consuming
+/// any byte after it ( TLS handshake or HTTP request )
+pub(crate) async fn read(
+    stream: &mut (impl AsyncRead + Unpin),
+) -> Result<ProxyHeader, &'static str> {
+    // both `PROXY UNKNOWN\r\n` ( 15 bytes ) and v2 header ( 16+ bytes ) are longer
+    let mut buf = vec![0; 8];
+    if stream.read_exact(&mut buf).await.is_err() {
+        return Err("connection closed before PROXY protocol header");
+    }
+
+    if buf.starts_with(V1_PREFIX) {
+        // read byte by byte not to consume the following request
+        while !buf.ends_with(b"\r\n") {
+            if buf.len() >= V1_MAX_LEN {
+                return Err("too long PROXY protocol v1 header");
+            }
+            let mut byte = [0];
+            if stream.read_exact(&mut byte).await.is_err() {
+                return Err("connection closed in PROXY protocol v1 header");
+            }
+            buf.push(byte[0]);
+        }
+        parse_v1(&buf)
+    } else if buf[..] == V2_SIGNATURE[..8] {
+        buf.resize(16, 0);
+        if stream.read_exact(&mut buf[8..]).await.is_err() {
+            return Err("connection closed in PROXY protocol v2 header");
+        }
+        let len = u16::from_be_bytes([buf[14], buf[15]]) as usize;
+        buf.resize(16 + len, 0);
+        if stream.read_exact(&mut buf[16..]).await.is_err() {
+            return Err("connection closed in PROXY protocol v2 header");
+        }
+        parse_v2(&buf)
+    } else {
+        Err("connection doesn't start with PROXY protocol header")
+    }
+}
+
+/// `PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\n`
+fn parse_v1(line: &[u8]) -> Result<ProxyHeader, &'static str> {
+    const INVALID: &str = "invalid PROXY protocol v1 header";
+
+    let line = line
+        .strip_prefix(V1_PREFIX)
+        .and_then(|line| line.strip_suffix(b"\r\n"))
+        .and_then(|line| std::str::from_utf8(line).ok())
+        .ok_or(INVALID)?;
+
+    let mut parts = line.split(' ');
+    let (source, destination) = match parts.next() {
+        // the rest of the line is to be ignored
+        Some("UNKNOWN") => (None, None),
+        Some(family @ ("TCP4" | "TCP6")) => {
+            let (Some(src), Some(dst), Some(sport), Some(dport), None) = (
+                parts.next(),
+                parts.next(),
+                parts.next(),
+                parts.next(),
+                parts.next(),
+            ) else {
+                return Err(INVALID);
+            };
+            let ip = |s: &str| match family {
+                "TCP4" => s.parse::<Ipv4Addr>().map(IpAddr::from).ok(),
+                _ => s.parse::<Ipv6Addr>().map(IpAddr::from).ok(),
+            };
+            // no leading zeros nor sign
+            let port = |s: &str| {
+                (s == "0" || !s.starts_with(['0', '+']))
+                    .then(|| s.parse::<u16>().ok())
+                    .flatten()
+            };
+            (
+                Some(SocketAddr::new(
+                    ip(src).ok_or(INVALID)?,
+                    port(sport).ok_or(INVALID)?,
+                )),
+                Some(SocketAddr::new(
+                    ip(dst).ok_or(INVALID)?,
+                    port(dport).ok_or(INVALID)?,
+                )),
+            )
+        }
+        _ => return Err(INVALID),
+    };
+
+    Ok(ProxyHeader {
+        version: 1,
+        source,
+        destination,
+        tlvs: Vec::new(),
+    })
+}
+
+/// 16 bytes of signature, version/command, family/protocol and length,
+/// followed by the addresses and TLVs
+fn parse_v2(header: &[u8]) -> Result<ProxyHeader, &'static str> {
+    const INVALID: &str = "invalid PROXY protocol v2 header";
+
+    let (version_command, family, body) = (header[12], header[13], &header[16..]);
+    if version_command >> 4 != 2 {
+        return Err("unsupported PROXY protocol version");
+    }
+
+    let local = match version_command & 0x0F {
+        0x0 => true,
+        0x1 => false,
+        _ => return Err(INVALID),
+    };
+
+    let addresses_len = match family >> 4 {
+        0x0 /* AF_UNSPEC */ => 0,
+        0x1 /* AF_INET   */ => 12,
+        0x2 /* AF_INET6  */ => 36,
+        0x3 /* AF_UNIX   */ => 216,
+        _ => return Err(INVALID),
+    };
+    if body.len() < addresses_len {
+        return Err(INVALID);
+    }
+    let (addresses, mut tlvs_bytes) = body.split_at(addresses_len);
+
+    let (source, destination) = match (local, family) {
+        // the connection is established by the proxy itself
+        (true, _) => (None, None),
+        (false, 0x11 | 0x12 /* TCP/UDP over IPv4 */) => {
+            let ip = |i: usize| IpAddr::from(<[u8; 4]>::try_from(&addresses[i..i + 4]).unwrap());
+            let port = |i: usize| u16::from_be_bytes([addresses[i], addresses[i + 1]]);
+            (
+                Some(SocketAddr::new(ip(0), port(8))),
+                Some(SocketAddr::new(ip(4), port(10))),
+            )
+        }
+        (false, 0x21 | 0x22 /* TCP/UDP over IPv6 */) => {
+            let ip = |i: usize| IpAddr::from(<[u8; 16]>::try_from(&addresses[i..i + 16]).unwrap());
+            let port = |i: usize| u16::from_be_bytes([addresses[i], addresses[i + 1]]);
+            (
+                Some(SocketAddr::new(ip(0), port(32))),
+                Some(SocketAddr::new(ip(16), port(34))),
+            )
+        }
+        _ => (None, None),
+    };
+
+    let mut tlvs = Vec::new();
+    while !tlvs_bytes.is_empty() {
+        let [kind, len_hi, len_lo, rest @ ..] = tlvs_bytes else {
+            return Err(INVALID);
+        };
+        let len = u16::from_be_bytes([*len_hi, *len_lo]) as usize;
+        if rest.len() < len {
+            return Err(INVALID);
+        }
+        tlvs.push((*kind, Box::from(&rest[..len])));
+        tlvs_bytes = &rest[len..];
+    }
+
+    Ok(ProxyHeader {
+        version: 2,
+        source,
+        destination,
+        tlvs,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn read(bytes: &[u8]) -> (Result<ProxyHeader, &'static str>, &[u8]) {
+        let mut stream = bytes;
+        let result = crate::__rt__::testing::block_on(super::read(&mut stream));
+        (result, stream)
+    }
+
+    fn addr(s: &str) -> Option<SocketAddr> {
+        Some(s.parse().unwrap())
+    }
+
+    #[test]
+    fn test_read_v1() {
+        assert_eq!(
+            read(b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\r\nGET / HTTP/1.1\r\n\r\n"),
+            (
+                Ok(ProxyHeader {
+                    version: 1,
+                    source: addr("192.0.2.1:56324"),
+                    destination: addr("198.51.100.1:443"),
+                    tlvs: vec![],
+                }),
+                &b"GET / HTTP/1.1\r\n\r\n"[..]
+            )
+        );
+        assert_eq!(
+            read(b"PROXY TCP6 2001:db8::1 2001:db8::2 56324 443\r\n"),
+            (
+                Ok(ProxyHeader {
+                    version: 1,
+                    source: addr("[2001:db8::1]:56324"),
+                    destination: addr("[2001:db8::2]:443"),
+                    tlvs: vec![],
+                }),
+                &b""[..]
+            )
+        );
+        assert_eq!(
+            read(b"PROXY UNKNOWN ffff::1 ffff::2 1 2\r\n").0,
+            Ok(ProxyHeader {
+                version: 1,
+                source: None,
+                destination: None,
+                tlvs: vec![],
+            })
+        );
+
+        for invalid in [
+            &b"GET / HTTP/1.1\r\n\r\n"[..],
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 56324\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443 0\r\n",
+            b"PROXY TCP4 2001:db8::1 2001:db8::2 56324 443\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 056324 443\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 65536 443\r\n",
+            b"PROXY UDP4 192.0.2.1 198.51.100.1 56324 443\r\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443\n",
+            b"PROXY TCP4 192.0.2.1 198.51.100.1 56324 443",
+        ] {
+            assert!(read(invalid).0.is_err(), "{}", invalid.escape_ascii());
+        }
+
+        let too_long = format!("PROXY UNKNOWN {}\r\n", "a".repeat(100));
+        assert!(read(too_long.as_bytes()).0.is_err());
+    }
+
+    #[test]
+    fn test_read_v2() {
+        let header = |command: u8, family: u8, body: &[u8]| {
+            let mut header = V2_SIGNATURE.to_vec();
+            header.extend([0x20 | command, family]);
+            header.extend((body.len() as u16).to_be_bytes());
+            header.extend(body);
+            header
+        };
+
+        let ipv4_with_tlvs = header(
+            0x1,
+            0x11,
+            &[
+                192, 0, 2, 1, /* source */
+                198, 51, 100, 1, /* destination */
+                0xDC, 0x04, /* 56324 */
+                0x01, 0xBB, /* 443 */
+                0x02, 0x00, 0x0B, b'e', b'x', b'a', b'm', b'p', b'l', b'e', b'.', b'c', b'o', b'm',
+                0xEA, 0x00, 0x00,
+            ],
+        );
+        let mut bytes = ipv4_with_tlvs.clone();
+        bytes.extend(b"GET / HTTP/1.1\r\n\r\n");
+        let (result, rest) = read(&bytes);
+        let proxy = result.unwrap();
+        assert_eq!(proxy.version(), 2);
+        assert_eq!(proxy.source(), addr("192.0.2.1:56324"));
+        assert_eq!(proxy.destination(), addr("198.51.100.1:443"));
+        assert_eq!(proxy.tlv(0x02), Some(&b"example.com"[..]));
+        assert_eq!(proxy.tlv(0xEA), Some(&b""[..]));
+        assert_eq!(proxy.tlv(0x01), None);
+        assert_eq!(proxy.tlvs().count(), 2);
+        assert_eq!(rest, b"GET / HTTP/1.1\r\n\r\n");
+
+        let mut ipv6 = Vec::new();
+        ipv6.extend("2001:db8::1".parse::<Ipv6Addr>().unwrap().octets());
+        ipv6.extend("2001:db8::2".parse::<Ipv6Addr>().unwrap().octets());
+        ipv6.extend([0xDC, 0x04, 0x01, 0xBB]);
+        let proxy = read(&header(0x1, 0x21, &ipv6)).0.unwrap();
+        assert_eq!(proxy.source(), addr("[2001:db8::1]:56324"));
+        assert_eq!(proxy.destination(), addr("[2001:db8::2]:443"));
+
+        // health check by the proxy itself
+        let proxy = read(&header(0x0, 0x00, &[])).0.unwrap();
+        assert_eq!((proxy.source(), proxy.destination()), (None, None));
+        let proxy = read(&header(0x0, 0x11, &ipv4_with_tlvs[16..])).0.unwrap();
+        assert_eq!((proxy.source(), proxy.destination()), (None, None));
+
+        // unsupported command, too short addresses, truncated TLV
+        assert!(read(&header(0x2, 0x11, &ipv4_with_tlvs[16..])).0.is_err());
+        assert!(read(&header(0x1, 0x21, &ipv4_with_tlvs[16..28])).0.is_err());
+        assert!(
+            read(&header(
+                0x1,
+                0x11,
+                &ipv4_with_tlvs[16..ipv4_with_tlvs.len() - 1]
+            ))
+            .0
+            .is_err()
+        );
+        // version 1 with v2 signature, truncated
+        let mut v1 = header(0x1, 0x11, &ipv4_with_tlvs[16..]);
+        v1[12] = 0x11;
+        assert!(read(&v1).0.is_err());
+        assert!(read(&ipv4_with_tlvs[..20]).0.is_err());
+    }
+}
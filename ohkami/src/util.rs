@@ -108,7 +108,7 @@ pub fn base64_url_encode(input: impl AsRef<[u8]>) -> String {
     URL_SAFE_NO_PAD.encode(input)
 }
 
-#[cfg(feature = "sse")]
+#[cfg(any(feature = "sse", feature = "__rt__"))]
 pub use ohkami_lib::stream::{self, Stream, StreamExt};
 
 #[cfg(not(feature = "rt_worker"))]
@@ -0,0 +1,325 @@
+use super::{Connection, Message};
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+/// # WebSocket connection of JSON messages
+///
+/// Wrapping a [`Connection`](crate::ws::Connection) to receive and send
+/// text messages of JSON as typed values. Binary messages are also decoded
+/// as JSON, and `Ping` / `Pong` messages are skipped.
+///
+/// *example.rs*
+/// ```
+/// use ohkami::ws::{WebSocketContext, WebSocket, JsonConnection, JsonError};
+/// use ohkami::serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// struct Ask {
+///     question: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Answer {
+///     answer: usize,
+/// }
+///
+/// async fn ws(ctx: WebSocketContext<'_>) -> WebSocket {
+///     ctx.upgrade(|conn| async move {
+///         let mut conn = JsonConnection::new(conn);
+///         loop {
+///             match conn.recv::<Ask>().await {
+///                 Ok(Some(Ask { question })) => {
+///                     let answer = Answer { answer: question.len() };
+///                     if conn.send(&answer).await.is_err() {
+///                         break
+///                     }
+///                 }
+///                 Err(JsonError::Decode(e)) => {
+///                     println!("invalid message: {e}");
+///                 }
+///                 Ok(None) | Err(_) => break,
+///             }
+///         }
+///     })
+/// }
+/// ```
+pub struct JsonConnection<C> {
+    conn: C,
+}
+
+/// Error of [`JsonConnection`]
+#[derive(Debug)]
+pub enum JsonError<E> {
+    /// failed to receive or send a message on the connection
+    Protocol(E),
+    /// a received message is not JSON of the expected type
+    Decode(serde_json::Error),
+    /// a value to send failed to be serialized into JSON
+    Encode(serde_json::Error),
+}
+
+const _: (/* trait impls */) = {
+    impl<E: std::fmt::Display> std::fmt::Display for JsonError<E> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            match self {
+                Self::Protocol(e) => write!(f, "WebSocket error: {e}"),
+                Self::Decode(e) => write!(f, "failed to decode a message: {e}"),
+                Self::Encode(e) => write!(f, "failed to encode a message: {e}"),
+            }
+        }
+    }
+
+    impl<E: std::error::Error + 'static> std::error::Error for JsonError<E> {
+        fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+            match self {
+                Self::Protocol(e) => Some(e),
+                Self::Decode(e) | Self::Encode(e) => Some(e),
+            }
+        }
+    }
+
+    impl<C: std::fmt::Debug> std::fmt::Debug for JsonConnection<C> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_tuple("JsonConnection").field(&self.conn).finish()
+        }
+    }
+};
+
+impl<C> JsonConnection<C> {
+    pub fn new(conn: C) -> Self {
+        Self { conn }
+    }
+
+    pub fn get_ref(&self) -> &C {
+        &self.conn
+    }
+
+    /// Get the inner connection to send or receive raw messages,
+    /// e.g. a `Close` message with a reason.
+    pub fn get_mut(&mut self) -> &mut C {
+        &mut self.conn
+    }
+
+    pub fn into_inner(self) -> C {
+        self.conn
+    }
+}
+
+/// `Some(decoded)`, or `None` for a message not to be decoded
+fn decode<T: for<'de> Deserialize<'de>>(
+    message: Message,
+) -> Option<Result<Option<T>, serde_json::Error>> {
+    match message {
+        Message::Text(text) => Some(serde_json::from_str(&text).map(Some)),
+        Message::Binary(binary) => Some(serde_json::from_slice(&binary).map(Some)),
+        Message::Close(_) => Some(Ok(None)),
+        _ => None,
+    }
+}
+
+#[cfg(feature = "__rt_native__")]
+impl<C: super::native::connection::UnderlyingConnection> JsonConnection<Connection<C>> {
+    /// Await a JSON message from the client and decode it into `T`.
+    /// `Ok(None)` is returned when the client has closed the connection.
+    pub async fn recv<T: for<'de> Deserialize<'de>>(
+        &mut self,
+    ) -> Result<Option<T>, JsonError<std::io::Error>> {
+        loop {
+            let Some(message) = (self.conn)
+                .recv_responding_ping()
+                .await
+                .map_err(JsonError::Protocol)?
+            else {
+                return Ok(None);
+            };
+            if let Some(decoded) = decode(message) {
+                return decoded.map_err(JsonError::Decode);
+            }
+        }
+    }
+
+    /// Send a value to the client as a text message of JSON.
+    pub async fn send(&mut self, value: &impl Serialize) -> Result<(), JsonError<std::io::Error>> {
+        let text = serde_json::to_string(value).map_err(JsonError::Encode)?;
+        (self.conn)
+            .send(Message::Text(text))
+            .await
+            .map_err(JsonError::Protocol)
+    }
+}
+
+#[cfg(feature = "rt_worker")]
+impl JsonConnection<Connection> {
+    /// Await a JSON message from the client and decode it into `T`.
+    /// `Ok(None)` is returned when the client has closed the connection.
+    pub async fn recv<T: for<'de> Deserialize<'de>>(
+        &mut self,
+    ) -> Result<Option<T>, JsonError<worker::Error>> {
+        loop {
+            let Some(message) = self.conn.recv().await.map_err(JsonError::Protocol)? else {
+                return Ok(None);
+            };
+            if let Some(decoded) = decode(message) {
+                return decoded.map_err(JsonError::Decode);
+            }
+        }
+    }
+
+    /// Send a value to the client as a text message of JSON.
+    pub async fn send(&mut self, value: &impl Serialize) -> Result<(), JsonError<worker::Error>> {
+        let text = serde_json::to_string(value).map_err(JsonError::Encode)?;
+        (self.conn)
+            .send(Message::Text(text))
+            .await
+            .map_err(JsonError::Protocol)
+    }
+}
+
+/// # Response for upgrading to WebSocket of JSON messages
+///
+/// A [`WebSocket`](crate::ws::WebSocket) documenting the messages received
+/// from the client as `Recv` and sent to the client as `Send`.
+/// When `openapi` feature is activated, their schemas are shown
+/// in the `101` response of the operation.
+///
+/// *example.rs*
+/// ```
+/// use ohkami::ws::{WebSocketContext, JsonWebSocket, JsonConnection};
+/// use ohkami::serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize)]
+/// struct Ask {
+///     question: String,
+/// }
+///
+/// #[derive(Serialize)]
+/// struct Answer {
+///     answer: usize,
+/// }
+///
+/// async fn ws(ctx: WebSocketContext<'_>) -> JsonWebSocket<Ask, Answer> {
+///     ctx.upgrade(|conn| async move {
+///         let mut conn = JsonConnection::new(conn);
+///         while let Ok(Some(Ask { question })) = conn.recv().await {
+///             conn.send(&Answer { answer: question.len() }).await.ok();
+///         }
+///     }).into()
+/// }
+/// ```
+pub struct JsonWebSocket<Recv, Send>(super::WebSocket, PhantomData<fn() -> (Recv, Send)>);
+
+impl<Recv, Send> From<super::WebSocket> for JsonWebSocket<Recv, Send> {
+    fn from(ws: super::WebSocket) -> Self {
+        Self(ws, PhantomData)
+    }
+}
+
+impl<Recv: bound::Message, Send: bound::Message> crate::IntoResponse for JsonWebSocket<Recv, Send> {
+    #[inline]
+    fn into_response(self) -> crate::Response {
+        self.0.into_response()
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi_responses() -> crate::openapi::Responses {
+        use crate::openapi;
+
+        openapi::Responses::new([(
+            101,
+            openapi::Response::when("Upgrade to WebSocket").content(
+                "application/json",
+                openapi::object()
+                    .description("JSON messages over the WebSocket connection")
+                    .property("recv", Recv::schema())
+                    .property("send", Send::schema()),
+            ),
+        )])
+    }
+}
+
+#[cfg(not(feature = "openapi"))]
+mod bound {
+    pub trait Message {}
+    impl<T> Message for T {}
+}
+#[cfg(feature = "openapi")]
+mod bound {
+    pub trait Message: crate::openapi::Schema {}
+    impl<T: crate::openapi::Schema> Message for T {}
+}
+
+#[cfg(test)]
+#[cfg(feature = "__rt_native__")]
+mod test {
+    use super::*;
+
+    #[test]
+    fn can_send_and_recv_json_over_websocket() {
+        use crate::__rt__::testing::serve;
+        use crate::ws::WebSocketContext;
+        use crate::ws::testing::{connect, recv, send};
+        use crate::{Ohkami, Route};
+
+        #[derive(Deserialize)]
+        struct Ask {
+            n: usize,
+        }
+        #[derive(Serialize)]
+        struct Answer {
+            twice: usize,
+        }
+        #[cfg(feature = "openapi")]
+        const _: () = {
+            impl crate::openapi::Schema for Ask {
+                fn schema() -> impl Into<crate::openapi::schema::SchemaRef> {
+                    crate::openapi::object().property("n", crate::openapi::integer())
+                }
+            }
+            impl crate::openapi::Schema for Answer {
+                fn schema() -> impl Into<crate::openapi::schema::SchemaRef> {
+                    crate::openapi::object().property("twice", crate::openapi::integer())
+                }
+            }
+        };
+
+        async fn json(ctx: WebSocketContext<'_>) -> JsonWebSocket<Ask, Answer> {
+            ctx.upgrade(|conn| async move {
+                let mut conn = JsonConnection::new(conn);
+                loop {
+                    match conn.recv::<Ask>().await {
+                        Ok(Some(Ask { n })) => conn.send(&Answer { twice: 2 * n }).await.unwrap(),
+                        Err(JsonError::Decode(_)) => {
+                            conn.get_mut().send("decode error").await.unwrap()
+                        }
+                        Ok(None) => break,
+                        Err(e) => panic!("unexpected error: {e}"),
+                    }
+                }
+            })
+            .into()
+        }
+
+        serve(
+            Ohkami::new(("/json".GET(json),)),
+            crate::Config::default(),
+            |addr, _| {
+                let (mut stream, _) = connect(addr, "/json", "");
+
+                send(&mut stream, 0x1, br#"{"n":21}"#);
+                assert_eq!(recv(&mut stream), (0x81, br#"{"twice":42}"#.to_vec()));
+
+                // a ping is responded and skipped by `recv`
+                send(&mut stream, 0x9, b"");
+                assert_eq!(recv(&mut stream), (0x8a, vec![]));
+                send(&mut stream, 0x2, br#"{"n":1}"#);
+                assert_eq!(recv(&mut stream), (0x81, br#"{"twice":2}"#.to_vec()));
+
+                send(&mut stream, 0x1, b"not json");
+                assert_eq!(recv(&mut stream), (0x81, b"decode error".to_vec()));
+
+                send(&mut stream, 0x8, b"\x03\xe8");
+                assert_eq!(recv(&mut stream), (0x88, b"\x03\xe8".to_vec()));
+            },
+        );
+    }
+}
@@ -10,6 +10,11 @@ mod worker;
 #[cfg(feature = "rt_worker")]
 pub use self::worker::*;
 
+#[cfg(any(feature = "__rt_native__", feature = "rt_worker"))]
+mod json;
+#[cfg(any(feature = "__rt_native__", feature = "rt_worker"))]
+pub use self::json::{JsonConnection, JsonError, JsonWebSocket};
+
 /// # Context for WebSocket handshake
 ///
 /// `.upgrade(~)` performs handshake and creates a WebSocket session.
@@ -17,8 +22,10 @@ pub use self::worker::*;
 /// ### note
 ///
 /// On native runtimes, the session is timeout in 3600 seconds ( = 1 hour )
-/// by default. This is configurable by `OHKAMI_WEBSOCKET_TIMEOUT`
-/// environment variable.
+/// by default. This is configurable by `websocket_timeout` of `Config` or
+/// `OHKAMI_WEBSOCKET_TIMEOUT` environment variable, and `0` disables it.
+/// Dead or idle connections can be closed by the heartbeat settings of
+/// `ws::Config` instead.
 ///
 /// <br>
 ///
@@ -36,6 +43,11 @@ pub use self::worker::*;
 pub struct WebSocketContext<'req> {
     #[allow(unused/* on rt_worker */)]
     sec_websocket_key: &'req str,
+    #[allow(unused/* on rt_worker */)]
+    sec_websocket_extensions: Option<&'req str>,
+    sec_websocket_protocol: Option<&'req str>,
+    /// subprotocol selected by `select_protocol`
+    protocol: Option<&'req str>,
 }
 
 impl<'req> crate::FromRequest<'req> for WebSocketContext<'req> {
@@ -65,15 +77,63 @@ impl<'req> crate::FromRequest<'req> for WebSocketContext<'req> {
             )));
         }
 
-        req.headers
-            .sec_websocket_key()
-            .map(|sec_websocket_key| Ok(Self { sec_websocket_key }))
+        req.headers.sec_websocket_key().map(|sec_websocket_key| {
+            Ok(Self {
+                sec_websocket_key,
+                sec_websocket_extensions: req.headers.sec_websocket_extensions(),
+                sec_websocket_protocol: req.headers.sec_websocket_protocol(),
+                protocol: None,
+            })
+        })
     }
 }
 
 impl<'req> WebSocketContext<'req> {
     pub fn new(sec_websocket_key: &'req str) -> Self {
-        Self { sec_websocket_key }
+        Self {
+            sec_websocket_key,
+            sec_websocket_extensions: None,
+            sec_websocket_protocol: None,
+            protocol: None,
+        }
+    }
+
+    /// Subprotocols offered by the client in `Sec-WebSocket-Protocol`,
+    /// in the order of the client's preference.
+    pub fn protocols(&self) -> impl Iterator<Item = &'req str> + 'req {
+        self.sec_websocket_protocol
+            .into_iter()
+            .flat_map(|protocols| protocols.split(','))
+            .map(str::trim)
+            .filter(|protocol| !protocol.is_empty())
+    }
+
+    /// Select the first one of the offered subprotocols that is in `supported`.
+    ///
+    /// The selected subprotocol is sent back in `Sec-WebSocket-Protocol` of
+    /// the handshake response, and available as `Connection::protocol` in the handler.
+    /// `None` is returned if no supported one is offered, then the response
+    /// has no `Sec-WebSocket-Protocol` and the client may close the connection.
+    ///
+    /// ```
+    /// use ohkami::ws::{WebSocketContext, WebSocket};
+    ///
+    /// async fn graphql_ws(mut ctx: WebSocketContext<'_>) -> WebSocket {
+    ///     ctx.select_protocol(&["graphql-transport-ws", "graphql-ws"]);
+    ///     ctx.upgrade(|mut conn| async move {
+    ///         match conn.protocol() {
+    ///             Some("graphql-transport-ws") => {/* ... */}
+    ///             Some("graphql-ws") => {/* ... */}
+    ///             _ => {/* ... */}
+    ///         }
+    ///     })
+    /// }
+    /// ```
+    pub fn select_protocol(&mut self, supported: &[&str]) -> Option<&'req str> {
+        self.protocol = self
+            .protocols()
+            .find(|protocol| supported.contains(protocol));
+        self.protocol
     }
 
     /*
@@ -81,3 +141,34 @@ impl<'req> WebSocketContext<'req> {
         `native` or `worker` submodule
     */
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_select_protocol() {
+        let mut ctx = WebSocketContext {
+            sec_websocket_protocol: Some("mqtt, graphql-transport-ws ,graphql-ws,"),
+            ..WebSocketContext::new("dGhlIHNhbXBsZSBub25jZQ==")
+        };
+        assert_eq!(
+            ctx.protocols().collect::<Vec<_>>(),
+            ["mqtt", "graphql-transport-ws", "graphql-ws"]
+        );
+
+        /* the client's preference comes first */
+        assert_eq!(
+            ctx.select_protocol(&["graphql-ws", "graphql-transport-ws"]),
+            Some("graphql-transport-ws")
+        );
+        assert_eq!(ctx.protocol, Some("graphql-transport-ws"));
+
+        assert_eq!(ctx.select_protocol(&["MQTT", "wamp"]), None);
+        assert_eq!(ctx.protocol, None);
+
+        let mut ctx = WebSocketContext::new("dGhlIHNhbXBsZSBub25jZQ==");
+        assert_eq!(ctx.protocols().count(), 0);
+        assert_eq!(ctx.select_protocol(&["mqtt"]), None);
+    }
+}
@@ -0,0 +1,410 @@
+use super::message::{Incoming, Outgoing};
+use super::{Config, Message};
+use crate::__rt__::{AsyncRead, AsyncWrite};
+use std::io::{Error, ErrorKind};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+pub trait UnderlyingConnection: AsyncRead + AsyncWrite + Unpin + 'static {}
+impl<T: AsyncRead + AsyncWrite + Unpin + 'static> UnderlyingConnection for T {}
+
+/// # WebSocket connection
+///
+/// Given to the handler of [`upgrade`](crate::ws::WebSocketContext::upgrade).
+/// When the handler finishes, a close frame is sent if not sent yet.
+///
+/// Messages are compressed and decompressed transparently if `permessage-deflate`
+/// is negotiated ( see [`PerMessageDeflate`](crate::ws::PerMessageDeflate) ).
+pub struct Connection<C: UnderlyingConnection> {
+    conn: Shared<C>,
+    closed: Arc<AtomicBool>,
+    config: Config,
+    protocol: Option<String>,
+    incoming: Incoming,
+    outgoing: Writer,
+}
+
+/// Another handle of the connection held by the session, sending pings
+/// of the heartbeat while the handler runs, and the close frame after that.
+pub(super) struct Handle<C: UnderlyingConnection> {
+    conn: Shared<C>,
+    closed: Arc<AtomicBool>,
+    outgoing: Writer,
+}
+
+/// The underlying connection shared by `Connection` ( or its halves ) and `Handle`,
+/// locked on each poll.
+struct Shared<C>(Arc<Mutex<C>>);
+
+/// `Outgoing` shared by `Connection` ( or `WriteHalf` ) and `Handle`,
+/// locked while writing a frame not to interleave frames.
+type Writer = Arc<futures_util::lock::Mutex<Outgoing>>;
+
+const _: (/* trait impls */) = {
+    // SAFETY: the underlying connection is accessed only under the lock
+    unsafe impl<C: UnderlyingConnection> Send for Shared<C> {}
+    unsafe impl<C: UnderlyingConnection> Sync for Shared<C> {}
+
+    impl<C> Clone for Shared<C> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+
+    impl<C: UnderlyingConnection> std::fmt::Debug for Connection<C> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("Connection")
+                .field("closed", &self.closed.load(Ordering::Acquire))
+                .field("config", &self.config)
+                .field("protocol", &self.protocol)
+                .finish_non_exhaustive()
+        }
+    }
+};
+
+impl<C: UnderlyingConnection> Connection<C> {
+    pub(super) fn new(
+        conn: C,
+        config: Config,
+        protocol: Option<String>,
+        incoming: Incoming,
+        outgoing: Outgoing,
+    ) -> (Self, Handle<C>) {
+        let conn = Shared(Arc::new(Mutex::new(conn)));
+        let closed = Arc::new(AtomicBool::new(false));
+        let outgoing = Arc::new(futures_util::lock::Mutex::new(outgoing));
+        (
+            Self {
+                conn: conn.clone(),
+                closed: closed.clone(),
+                config,
+                protocol,
+                incoming,
+                outgoing: outgoing.clone(),
+            },
+            Handle {
+                conn,
+                closed,
+                outgoing,
+            },
+        )
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// The subprotocol selected by [`select_protocol`](crate::ws::WebSocketContext::select_protocol)
+    /// on the handshake, if any.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
+    }
+
+    /// Await a message from the client and receive it.
+    /// `Ok(None)` is returned when the client has closed the connection.
+    ///
+    /// **note** : This automatically consumes a `Ping` message and responds with
+    /// a corresponded `Pong` message, and then returns `Ok(None)`.
+    pub async fn recv(&mut self) -> Result<Option<Message>, Error> {
+        check_open(&self.closed)?;
+        match self.incoming.read(&mut self.conn, &self.config).await? {
+            Some(Message::Ping(payload)) => {
+                self.send(Message::Pong(payload)).await?;
+                Ok(None)
+            }
+            other => Ok(other),
+        }
+    }
+
+    /// `recv` responding to `Ping` messages until another message comes,
+    /// where `Ok(None)` always means the client has closed the connection.
+    pub(crate) async fn recv_responding_ping(&mut self) -> Result<Option<Message>, Error> {
+        loop {
+            check_open(&self.closed)?;
+            match self.incoming.read(&mut self.conn, &self.config).await? {
+                Some(Message::Ping(payload)) => self.send(Message::Pong(payload)).await?,
+                other => return Ok(other),
+            }
+        }
+    }
+
+    /// Send a message to the client.
+    ///
+    /// **note** : When sending a `Close` message, this automatically close the
+    /// connection, then the connection is not available anymore.
+    pub async fn send(&mut self, message: impl Into<Message>) -> Result<(), Error> {
+        send(&self.outgoing, &mut self.conn, &self.closed, message.into()).await
+    }
+
+    /// Buffer a message to be written to the connection. The buffered messages
+    /// are written and flushed when they exceed `write_buffer_size` of the `Config`,
+    /// or by `flush` or `send`.
+    ///
+    /// **note** : When sending a `Close` message, this automatically close the
+    /// connection, then the connection is not available anymore.
+    pub async fn write(&mut self, message: impl Into<Message>) -> Result<usize, Error> {
+        write(
+            &self.outgoing,
+            &mut self.conn,
+            &self.closed,
+            &self.config,
+            message.into(),
+        )
+        .await
+    }
+
+    /// Flush the connection explicitly.
+    pub async fn flush(&mut self) -> Result<(), Error> {
+        check_open(&self.closed)?;
+        self.outgoing.lock().await.flush(&mut self.conn).await
+    }
+
+    /// Split into the reading half and the writing half to use them concurrently.
+    ///
+    /// ## Panics
+    ///
+    /// This panics if the connection is already closed.
+    pub fn split(self) -> (split::ReadHalf<C>, split::WriteHalf<C>) {
+        if self.is_closed() {
+            panic!("WebSocket connection is already closed")
+        }
+
+        (
+            split::ReadHalf {
+                conn: self.conn.clone(),
+                closed: self.closed.clone(),
+                config: self.config.clone(),
+                incoming: self.incoming,
+            },
+            split::WriteHalf {
+                conn: self.conn,
+                closed: self.closed,
+                config: self.config,
+                outgoing: self.outgoing,
+            },
+        )
+    }
+}
+
+impl<C: UnderlyingConnection> Handle<C> {
+    pub(super) fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Acquire)
+    }
+
+    /// send a ping of the heartbeat
+    pub(super) async fn ping(&self) -> Result<(), Error> {
+        self.outgoing
+            .lock()
+            .await
+            .send(Message::Ping(Vec::new()), &mut self.conn.clone())
+            .await
+    }
+
+    /// send the close frame if the connection is not closed yet
+    pub(super) async fn close_with(self, frame: super::CloseFrame) {
+        if self.closed.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        if let Err(_e) = (self.outgoing.lock().await)
+            .send(Message::Close(Some(frame)), &mut self.conn.clone())
+            .await
+        {
+            crate::DEBUG!("failed to send a close frame: {_e}")
+        }
+    }
+}
+
+async fn send<C: UnderlyingConnection>(
+    outgoing: &Writer,
+    conn: &mut Shared<C>,
+    closed: &AtomicBool,
+    message: Message,
+) -> Result<(), Error> {
+    check_open(closed)?;
+    let closing = matches!(message, Message::Close(_));
+    outgoing.lock().await.send(message, conn).await?;
+    if closing {
+        closed.store(true, Ordering::Release);
+    }
+    Ok(())
+}
+
+async fn write<C: UnderlyingConnection>(
+    outgoing: &Writer,
+    conn: &mut Shared<C>,
+    closed: &AtomicBool,
+    config: &Config,
+    message: Message,
+) -> Result<usize, Error> {
+    check_open(closed)?;
+    let closing = matches!(message, Message::Close(_));
+    let mut outgoing = outgoing.lock().await;
+    let n = outgoing.write(message, conn, config).await?;
+    if closing {
+        // not to leave the close frame in the buffer of the closed connection
+        outgoing.flush(conn).await?;
+        closed.store(true, Ordering::Release);
+    }
+    Ok(n)
+}
+
+fn check_open(closed: &AtomicBool) -> Result<(), Error> {
+    if closed.load(Ordering::Acquire) {
+        crate::WARNING!(
+            "\
+            WebSocket connection is already closed. \
+            Maybe you spawned tasks using the connection and NOT waiting them to finish? \
+            Tasks using the connection must be awaited ( joined, selected, ... ) \
+            in the handler.\
+        "
+        );
+        return Err(Error::new(
+            ErrorKind::ConnectionReset,
+            "WebSocket connection is already closed",
+        ));
+    }
+    Ok(())
+}
+
+pub mod split {
+    use super::*;
+
+    /// Reading half of a [`Connection`], created by [`Connection::split`]
+    pub struct ReadHalf<C: UnderlyingConnection> {
+        pub(super) conn: Shared<C>,
+        pub(super) closed: Arc<AtomicBool>,
+        pub(super) config: Config,
+        pub(super) incoming: Incoming,
+    }
+
+    /// Writing half of a [`Connection`], created by [`Connection::split`]
+    pub struct WriteHalf<C: UnderlyingConnection> {
+        pub(super) conn: Shared<C>,
+        pub(super) closed: Arc<AtomicBool>,
+        pub(super) config: Config,
+        pub(super) outgoing: Writer,
+    }
+
+    impl<C: UnderlyingConnection> ReadHalf<C> {
+        /// Await a message from the client and receive it.
+        /// `Ok(None)` is returned when the client has closed the connection.
+        ///
+        /// **note** : This doesn't automatically handle `Ping` message
+        /// (in contrast to `Connection::recv`).
+        pub async fn recv(&mut self) -> Result<Option<Message>, Error> {
+            check_open(&self.closed)?;
+            self.incoming.read(&mut self.conn, &self.config).await
+        }
+    }
+
+    impl<C: UnderlyingConnection> WriteHalf<C> {
+        /// Send a message to the client.
+        ///
+        /// **note** : When sending a `Close` message, this automatically close the
+        /// connection, then the connection is not available anymore.
+        pub async fn send(&mut self, message: impl Into<Message>) -> Result<(), Error> {
+            super::send(&self.outgoing, &mut self.conn, &self.closed, message.into()).await
+        }
+
+        /// Buffer a message to be written to the connection. The buffered messages
+        /// are written and flushed when they exceed `write_buffer_size` of the `Config`,
+        /// or by `flush` or `send`.
+        ///
+        /// **note** : When sending a `Close` message, this automatically close the
+        /// connection, then the connection is not available anymore.
+        pub async fn write(&mut self, message: impl Into<Message>) -> Result<usize, Error> {
+            super::write(
+                &self.outgoing,
+                &mut self.conn,
+                &self.closed,
+                &self.config,
+                message.into(),
+            )
+            .await
+        }
+
+        /// Flush the connection explicitly.
+        pub async fn flush(&mut self) -> Result<(), Error> {
+            check_open(&self.closed)?;
+            self.outgoing.lock().await.flush(&mut self.conn).await
+        }
+    }
+}
+
+impl<C: Unpin> Shared<C> {
+    /// Lock the underlying connection and poll IO on it
+    #[inline]
+    fn poll_with<T>(
+        &self,
+        cx: &mut Context<'_>,
+        f: impl FnOnce(Pin<&mut C>, &mut Context<'_>) -> Poll<T>,
+    ) -> Poll<T> {
+        f(Pin::new(&mut *self.0.lock().unwrap()), cx)
+    }
+}
+
+#[cfg(feature = "__io_tokio__")]
+const _: () = {
+    impl<C: tokio::io::AsyncRead + Unpin> tokio::io::AsyncRead for Shared<C> {
+        #[inline]
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut tokio::io::ReadBuf<'_>,
+        ) -> Poll<std::io::Result<()>> {
+            self.poll_with(cx, |c, cx| c.poll_read(cx, buf))
+        }
+    }
+    impl<C: tokio::io::AsyncWrite + Unpin> tokio::io::AsyncWrite for Shared<C> {
+        #[inline]
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.poll_with(cx, |c, cx| c.poll_write(cx, buf))
+        }
+        #[inline]
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            self.poll_with(cx, |c, cx| c.poll_flush(cx))
+        }
+        #[inline]
+        fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            self.poll_with(cx, |c, cx| c.poll_shutdown(cx))
+        }
+    }
+};
+
+#[cfg(feature = "__io_futures__")]
+const _: () = {
+    impl<C: futures_util::io::AsyncRead + Unpin> futures_util::io::AsyncRead for Shared<C> {
+        #[inline]
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.poll_with(cx, |c, cx| c.poll_read(cx, buf))
+        }
+    }
+    impl<C: futures_util::io::AsyncWrite + Unpin> futures_util::io::AsyncWrite for Shared<C> {
+        #[inline]
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<std::io::Result<usize>> {
+            self.poll_with(cx, |c, cx| c.poll_write(cx, buf))
+        }
+        #[inline]
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            self.poll_with(cx, |c, cx| c.poll_flush(cx))
+        }
+        #[inline]
+        fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+            self.poll_with(cx, |c, cx| c.poll_close(cx))
+        }
+    }
+};
@@ -0,0 +1,457 @@
+use super::frame::invalid;
+use miniz_oxide::deflate::core::{CompressorOxide, create_comp_flags_from_zip_params};
+use miniz_oxide::inflate::stream::InflateState;
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+use std::io::Error;
+
+/// # `permessage-deflate` extension ( RFC 7692 )
+///
+/// Set to [`Config::permessage_deflate`](crate::ws::Config::permessage_deflate)
+/// to negotiate the extension when the client offers it, then messages are
+/// compressed and decompressed transparently.
+///
+/// An offer requiring `server_max_window_bits` less than 15 is declined,
+/// as the server always compresses with the full 32 KiB window.
+///
+/// ## Example
+///
+/// ```
+/// use ohkami::ws::{WebSocketContext, WebSocket, Config, PerMessageDeflate};
+///
+/// async fn ws(ctx: WebSocketContext<'_>) -> WebSocket {
+///     ctx.upgrade_with(
+///         Config {
+///             permessage_deflate: Some(PerMessageDeflate {
+///                 threshold: 4096,
+///                 ..Default::default()
+///             }),
+///             ..Default::default()
+///         },
+///         |mut conn| async move {
+///             conn.send("large JSON...").await.expect("failed to send")
+///         },
+///     )
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct PerMessageDeflate {
+    /// Reset the compression context for each message sent, using less memory
+    /// at the cost of compression ratio. default: `false`
+    pub server_no_context_takeover: bool,
+    /// Request the client to reset its compression context for each message.
+    /// default: `false`
+    pub client_no_context_takeover: bool,
+    /// Request the client to use an LZ77 window of at most 2^n bytes ( `8..=15` ),
+    /// when the client allows it. default: `None` ( as the client likes )
+    pub client_max_window_bits: Option<u8>,
+    /// Compression level ( `0..=10` ). default: `6`
+    pub level: u8,
+    /// Messages smaller than this number of bytes are sent uncompressed.
+    /// default: `1024`
+    pub threshold: usize,
+}
+
+impl Default for PerMessageDeflate {
+    fn default() -> Self {
+        Self {
+            server_no_context_takeover: false,
+            client_no_context_takeover: false,
+            client_max_window_bits: None,
+            level: 6,
+            threshold: 1024,
+        }
+    }
+}
+
+/// parameters agreed on the handshake
+#[derive(Clone, Debug, PartialEq)]
+pub(super) struct Negotiated {
+    server_no_context_takeover: bool,
+    client_no_context_takeover: bool,
+    level: u8,
+    threshold: usize,
+}
+
+impl PerMessageDeflate {
+    /// Select the first acceptable offer in `Sec-WebSocket-Extensions` of the request,
+    /// returning `Sec-WebSocket-Extensions` of the response with the parameters agreed
+    pub(super) fn negotiate(&self, sec_websocket_extensions: &str) -> Option<(String, Negotiated)> {
+        sec_websocket_extensions
+            .split(',')
+            .find_map(|offer| self.accept(offer))
+    }
+
+    fn accept(&self, offer: &str) -> Option<(String, Negotiated)> {
+        let mut params = offer.split(';').map(str::trim);
+        if !params.next()?.eq_ignore_ascii_case("permessage-deflate") {
+            return None;
+        }
+
+        let mut server_no_context_takeover = self.server_no_context_takeover;
+        let mut client_no_context_takeover = self.client_no_context_takeover;
+        let mut client_max_window_bits = None;
+
+        let mut seen = Vec::with_capacity(4);
+        for param in params {
+            let (name, value) = match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            };
+            if seen.contains(&name) {
+                return None;
+            }
+            seen.push(name);
+
+            let window_bits = |value: Option<&str>| {
+                value
+                    .map(|v| v.parse::<u8>().ok().filter(|n| (8..=15).contains(n)))
+                    .unwrap_or(Some(15))
+            };
+            match name {
+                "server_no_context_takeover" if value.is_none() => {
+                    server_no_context_takeover = true
+                }
+                "client_no_context_takeover" if value.is_none() => {
+                    client_no_context_takeover = true
+                }
+                "server_max_window_bits" => {
+                    if window_bits(value)? != 15 {
+                        return None;
+                    }
+                }
+                "client_max_window_bits" => {
+                    let offered = window_bits(value)?;
+                    client_max_window_bits = self
+                        .client_max_window_bits
+                        .map(|bits| bits.clamp(8, offered))
+                        .or(value.is_some().then_some(offered));
+                }
+                _ => return None,
+            }
+        }
+
+        let mut response = String::from("permessage-deflate");
+        if server_no_context_takeover {
+            response.push_str("; server_no_context_takeover");
+        }
+        if client_no_context_takeover {
+            response.push_str("; client_no_context_takeover");
+        }
+        if let Some(bits) = client_max_window_bits {
+            response.push_str("; client_max_window_bits=");
+            response.push_str(&bits.to_string());
+        }
+
+        Some((
+            response,
+            Negotiated {
+                server_no_context_takeover,
+                client_no_context_takeover,
+                level: self.level,
+                threshold: self.threshold,
+            },
+        ))
+    }
+}
+
+/// the empty stored block at the end of each message, removed on the wire
+const TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+pub(super) struct Deflater {
+    compressor: Box<CompressorOxide>,
+    no_context_takeover: bool,
+    threshold: usize,
+}
+
+impl Deflater {
+    pub(super) fn new(negotiated: &Negotiated) -> Self {
+        let flags = create_comp_flags_from_zip_params(negotiated.level.into(), -15, 0);
+        Self {
+            compressor: Box::new(CompressorOxide::new(flags)),
+            no_context_takeover: negotiated.server_no_context_takeover,
+            threshold: negotiated.threshold,
+        }
+    }
+
+    /// compress `payload` if it's not smaller than the threshold, returning `None` if not
+    pub(super) fn compress(&mut self, payload: &[u8]) -> Option<Vec<u8>> {
+        if payload.len() < self.threshold {
+            return None;
+        }
+
+        let mut compressed = Vec::with_capacity(payload.len() / 2 + TAIL.len());
+        let mut chunk = vec![0; (payload.len() + 64).min(1 << 16)];
+        let mut input = payload;
+        loop {
+            let res = miniz_oxide::deflate::stream::deflate(
+                &mut self.compressor,
+                input,
+                &mut chunk,
+                MZFlush::Sync,
+            );
+            input = &input[res.bytes_consumed..];
+            compressed.extend_from_slice(&chunk[..res.bytes_written]);
+            // only parameter errors are possible, which are never made here
+            res.status.expect("Failed to compress a WebSocket message");
+            if input.is_empty() && res.bytes_written < chunk.len() {
+                break;
+            }
+        }
+        if compressed.ends_with(&TAIL) {
+            compressed.truncate(compressed.len() - TAIL.len());
+        }
+
+        if self.no_context_takeover {
+            self.compressor.reset();
+        }
+        Some(compressed)
+    }
+}
+
+pub(super) struct Inflater {
+    state: Box<InflateState>,
+    no_context_takeover: bool,
+}
+
+impl Inflater {
+    pub(super) fn new(negotiated: &Negotiated) -> Self {
+        Self {
+            state: InflateState::new_boxed(DataFormat::Raw),
+            no_context_takeover: negotiated.client_no_context_takeover,
+        }
+    }
+
+    /// decompress the payload of a message, failing when it gets larger than `limit`
+    pub(super) fn decompress(
+        &mut self,
+        mut payload: Vec<u8>,
+        limit: Option<usize>,
+    ) -> Result<Vec<u8>, Error> {
+        payload.extend_from_slice(&TAIL);
+
+        let mut decompressed = Vec::with_capacity(payload.len() * 2);
+        let mut chunk = vec![0; (payload.len() * 4).clamp(1024, 1 << 16)];
+        let mut input = &payload[..];
+        loop {
+            let res = miniz_oxide::inflate::stream::inflate(
+                &mut self.state,
+                input,
+                &mut chunk,
+                MZFlush::None,
+            );
+            input = &input[res.bytes_consumed..];
+            decompressed.extend_from_slice(&chunk[..res.bytes_written]);
+            if limit.is_some_and(|limit| decompressed.len() > limit) {
+                return Err(invalid("Incoming message is too large"));
+            }
+            match res.status {
+                // the client has finished the stream by a final block
+                Ok(MZStatus::StreamEnd) => {
+                    self.state.reset(DataFormat::Raw);
+                    break;
+                }
+                Ok(_) if input.is_empty() && res.bytes_written < chunk.len() => break,
+                Ok(_) => continue,
+                Err(MZError::Buf) if input.is_empty() => break,
+                Err(_) => return Err(invalid("Failed to decompress a message")),
+            }
+        }
+
+        if self.no_context_takeover {
+            self.state.reset(DataFormat::Raw);
+        }
+        Ok(decompressed)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_negotiate() {
+        let default = PerMessageDeflate::default();
+
+        assert_eq!(default.negotiate("x-webkit-deflate-frame"), None);
+        assert_eq!(
+            default
+                .negotiate("permessage-deflate; client_max_window_bits")
+                .map(|(res, _)| res)
+                .as_deref(),
+            Some("permessage-deflate")
+        );
+        assert_eq!(
+            default
+                .negotiate(
+                    "permessage-deflate; server_no_context_takeover; client_max_window_bits=10"
+                )
+                .map(|(res, _)| res)
+                .as_deref(),
+            Some("permessage-deflate; server_no_context_takeover; client_max_window_bits=10")
+        );
+
+        /* unsupported or invalid offers are skipped */
+        assert_eq!(
+            default
+                .negotiate("permessage-deflate; server_max_window_bits=10, permessage-deflate")
+                .map(|(res, _)| res)
+                .as_deref(),
+            Some("permessage-deflate")
+        );
+        assert_eq!(
+            default.negotiate("permessage-deflate; client_max_window_bits=16"),
+            None
+        );
+        assert_eq!(default.negotiate("permessage-deflate; unknown_param"), None);
+        assert_eq!(
+            default.negotiate(
+                "permessage-deflate; server_no_context_takeover; server_no_context_takeover"
+            ),
+            None
+        );
+
+        let custom = PerMessageDeflate {
+            client_no_context_takeover: true,
+            client_max_window_bits: Some(9),
+            ..Default::default()
+        };
+        assert_eq!(
+            custom
+                .negotiate("permessage-deflate; client_max_window_bits")
+                .map(|(res, _)| res)
+                .as_deref(),
+            Some("permessage-deflate; client_no_context_takeover; client_max_window_bits=9")
+        );
+        assert_eq!(
+            custom
+                .negotiate("permessage-deflate")
+                .map(|(res, _)| res)
+                .as_deref(),
+            Some("permessage-deflate; client_no_context_takeover")
+        );
+    }
+
+    #[test]
+    fn test_compress_decompress() {
+        for server_no_context_takeover in [false, true] {
+            let (_, negotiated) = PerMessageDeflate {
+                server_no_context_takeover,
+                threshold: 16,
+                ..Default::default()
+            }
+            .negotiate("permessage-deflate")
+            .unwrap();
+
+            let mut deflater = Deflater::new(&negotiated);
+            let mut inflater = Inflater::new(&Negotiated {
+                // the client doesn't reset its context unless the server does
+                client_no_context_takeover: server_no_context_takeover,
+                ..negotiated
+            });
+
+            assert_eq!(deflater.compress(b"too small"), None);
+
+            let message =
+                br#"{"id":1,"name":"ohkami","tags":["web","framework","web","framework"]}"#
+                    .repeat(100);
+            for _ in 0..3 {
+                let compressed = deflater.compress(&message).unwrap();
+                assert!(compressed.len() < message.len() / 10);
+                assert!(!compressed.ends_with(&TAIL));
+                assert_eq!(inflater.decompress(compressed, None).unwrap(), message);
+            }
+
+            let compressed = deflater.compress(&message).unwrap();
+            assert!(
+                inflater
+                    .decompress(compressed, Some(message.len() - 1))
+                    .is_err()
+            );
+        }
+    }
+
+    #[test]
+    fn can_negotiate_permessage_deflate() {
+        use crate::__rt__::testing::serve;
+        use crate::ws::{Config, Message, WebSocket, WebSocketContext, testing::connect};
+        use crate::{Ohkami, Route};
+        use std::io::{Read, Write};
+
+        async fn echo(ctx: WebSocketContext<'_>) -> WebSocket {
+            ctx.upgrade_with(
+                Config {
+                    permessage_deflate: Some(PerMessageDeflate::default()),
+                    ..Default::default()
+                },
+                |mut conn| async move {
+                    while let Ok(Some(Message::Text(text))) = conn.recv().await {
+                        conn.send(text).await.unwrap();
+                    }
+                },
+            )
+        }
+
+        let message = "Hello, ohkami! ".repeat(100);
+
+        let (handshake, first, echo) =
+            serve(Ohkami::new(("/ws".GET(echo),)), crate::Config::default(), {
+                let message = message.clone();
+                move |addr, _| {
+                    let (mut stream, handshake) = connect(
+                        addr,
+                        "/ws",
+                        "Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n",
+                    );
+
+                    /* a masked text frame with RSV1, compressed as a final deflate block */
+                    let compressed = miniz_oxide::deflate::compress_to_vec(message.as_bytes(), 6);
+                    let mut frame = vec![0b1100_0001];
+                    match compressed.len() {
+                        len @ ..=125 => frame.push(0x80 | len as u8),
+                        len => {
+                            frame.push(0x80 | 126);
+                            frame.extend_from_slice(&(len as u16).to_be_bytes());
+                        }
+                    }
+                    let mask = [0x12, 0x34, 0x56, 0x78];
+                    frame.extend_from_slice(&mask);
+                    frame.extend(compressed.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+                    stream.write_all(&frame).unwrap();
+
+                    let mut head = [0; 2];
+                    stream.read_exact(&mut head).unwrap();
+                    let len = match head[1] {
+                        126 => {
+                            let mut len = [0; 2];
+                            stream.read_exact(&mut len).unwrap();
+                            u16::from_be_bytes(len) as usize
+                        }
+                        len => len as usize,
+                    };
+                    let mut payload = vec![0; len];
+                    stream.read_exact(&mut payload).unwrap();
+                    // restore the tail removed by the server, then end the stream by an empty final block
+                    payload.extend_from_slice(&[0x00, 0x00, 0xff, 0xff, 0x03, 0x00]);
+                    let echo = miniz_oxide::inflate::decompress_to_vec(&payload).unwrap();
+
+                    stream.write_all(&[0b1000_1000, 0x80, 0, 0, 0, 0]).unwrap();
+
+                    (handshake, head[0], echo)
+                }
+            });
+
+        assert!(
+            handshake.contains("\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"),
+            "{handshake}"
+        );
+        assert!(
+            handshake.contains("\r\nSec-WebSocket-Extensions: permessage-deflate\r\n"),
+            "{handshake}"
+        );
+        assert_eq!(
+            first, 0b1100_0001,
+            "echo should be a final text frame with RSV1"
+        );
+        assert_eq!(echo, message.as_bytes());
+    }
+}
@@ -0,0 +1,159 @@
+use super::Config;
+use crate::__rt__::AsyncRead;
+use std::io::{Error, ErrorKind};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(super) enum OpCode {
+    /* data op codes */
+    Continue, /* 0x0 */
+    Text,     /* 0x1 */
+    Binary,   /* 0x2 */
+    /* control op codes */
+    Close, /* 0x8 */
+    Ping,  /* 0x9 */
+    Pong,  /* 0xa */
+}
+
+impl OpCode {
+    fn from_byte(byte: u8) -> Result<Self, Error> {
+        Ok(match byte {
+            0x0 => Self::Continue,
+            0x1 => Self::Text,
+            0x2 => Self::Binary,
+            0x8 => Self::Close,
+            0x9 => Self::Ping,
+            0xa => Self::Pong,
+            _ => return Err(invalid("Reserved opcode")),
+        })
+    }
+
+    const fn into_byte(self) -> u8 {
+        match self {
+            Self::Continue => 0x0,
+            Self::Text => 0x1,
+            Self::Binary => 0x2,
+            Self::Close => 0x8,
+            Self::Ping => 0x9,
+            Self::Pong => 0xa,
+        }
+    }
+
+    pub(super) const fn is_control(self) -> bool {
+        matches!(self, Self::Close | Self::Ping | Self::Pong)
+    }
+}
+
+#[derive(Debug)]
+pub(super) struct Frame {
+    pub(super) is_final: bool,
+    /// RSV1 bit, set on the first frame of a message compressed
+    /// by `permessage-deflate`
+    pub(super) compressed: bool,
+    pub(super) opcode: OpCode,
+    pub(super) payload: Vec<u8>,
+}
+
+impl Frame {
+    /// read a frame, or `None` if the connection is closed by the peer
+    pub(super) async fn read_from(
+        stream: &mut (impl AsyncRead + Unpin),
+        config: &Config,
+    ) -> Result<Option<Self>, Error> {
+        let [first, second] = {
+            let mut head = [0; 2];
+            if let Err(e) = stream.read_exact(&mut head).await {
+                return match e.kind() {
+                    ErrorKind::UnexpectedEof => Ok(None),
+                    _ => Err(e),
+                };
+            }
+            head
+        };
+
+        if first & 0b0011_0000 != 0 {
+            return Err(invalid("RSV2 or RSV3 is set without any extension for it"));
+        }
+        let is_final = first & 0b1000_0000 != 0;
+        let compressed = first & 0b0100_0000 != 0;
+        let opcode = OpCode::from_byte(first & 0x0F)?;
+
+        let payload_len = match second & 0x7F {
+            126 => {
+                let mut bytes = [0; 2];
+                stream.read_exact(&mut bytes).await?;
+                u16::from_be_bytes(bytes) as usize
+            }
+            127 => {
+                let mut bytes = [0; 8];
+                stream.read_exact(&mut bytes).await?;
+                usize::try_from(u64::from_be_bytes(bytes))
+                    .map_err(|_| invalid("Incoming frame is too large"))?
+            }
+            len => len as usize,
+        };
+        if config
+            .max_frame_size
+            .is_some_and(|limit| payload_len > limit)
+        {
+            return Err(invalid("Incoming frame is too large"));
+        }
+
+        let mask = if second & 0x80 != 0 {
+            let mut mask = [0; 4];
+            stream.read_exact(&mut mask).await?;
+            Some(mask)
+        } else if config.accept_unmasked_frames {
+            None
+        } else {
+            return Err(invalid("Client frame is unmasked"));
+        };
+
+        let mut payload = vec![0; payload_len];
+        stream.read_exact(&mut payload).await?;
+        if let Some(mask) = mask {
+            for (i, b) in payload.iter_mut().enumerate() {
+                *b ^= mask[i & 0b11];
+            }
+        }
+
+        Ok(Some(Self {
+            is_final,
+            compressed,
+            opcode,
+            payload,
+        }))
+    }
+
+    /// write the frame unmasked ( as a server ), returning the number of bytes written
+    /// append the encoded frame to `bytes`, returning its size
+    pub(super) fn encode_into(self, bytes: &mut Vec<u8>) -> usize {
+        let Self {
+            is_final,
+            compressed,
+            opcode,
+            payload,
+        } = self;
+
+        let start = bytes.len();
+        bytes.reserve(2 + 8 + payload.len());
+        bytes.push(((is_final as u8) << 7) | ((compressed as u8) << 6) | opcode.into_byte());
+        match payload.len() {
+            len @ ..=125 => bytes.push(len as u8),
+            len @ 126..=65535 => {
+                bytes.push(126);
+                bytes.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                bytes.push(127);
+                bytes.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        bytes.extend_from_slice(&payload);
+
+        bytes.len() - start
+    }
+}
+
+pub(super) fn invalid(message: &'static str) -> Error {
+    Error::new(ErrorKind::InvalidData, message)
+}
@@ -0,0 +1,162 @@
+use super::connection::{Handle, UnderlyingConnection};
+use super::{CloseCode, CloseFrame, Config};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Timestamps of the traffic on a connection, watched by the heartbeat
+pub(super) struct Activity {
+    start: Instant,
+    /// [micros since `start`] the last frame received
+    received: AtomicU64,
+    /// [micros since `start`] the last text or binary message received or sent
+    messaged: AtomicU64,
+}
+
+impl Activity {
+    pub(super) fn new() -> Self {
+        Self {
+            start: Instant::now(),
+            received: AtomicU64::new(0),
+            messaged: AtomicU64::new(0),
+        }
+    }
+
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_micros() as u64
+    }
+
+    pub(super) fn on_frame_received(&self) {
+        self.received.store(self.now(), Ordering::Relaxed);
+    }
+
+    pub(super) fn on_message(&self) {
+        self.messaged.store(self.now(), Ordering::Relaxed);
+    }
+}
+
+/// Send pings and watch the deadlines by the heartbeat settings of `config`,
+/// returning the close frame when a deadline has passed.
+///
+/// This never returns if no heartbeat setting is enabled or the connection is closed.
+pub(super) async fn watch<C: UnderlyingConnection>(
+    config: &Config,
+    activity: &Activity,
+    handle: &Handle<C>,
+) -> CloseFrame {
+    let micros = |d: Duration| d.as_micros() as u64;
+
+    let mut last_ping = None;
+    loop {
+        if handle.is_closed() || (config.ping_interval.is_none() && config.idle_timeout.is_none()) {
+            return std::future::pending().await;
+        }
+
+        let now = activity.now();
+        let received = activity.received.load(Ordering::Relaxed);
+        let messaged = activity.messaged.load(Ordering::Relaxed);
+
+        let mut next = u64::MAX;
+
+        if let Some(idle_timeout) = config.idle_timeout {
+            let deadline = messaged + micros(idle_timeout);
+            if now >= deadline {
+                return going_away("idle timeout");
+            }
+            next = next.min(deadline);
+        }
+
+        // any frame from the client after the ping is regarded as its pong
+        let waiting_pong = last_ping.filter(|&sent| sent > received);
+        if let Some(sent) = waiting_pong
+            && let Some(pong_timeout) = config.pong_timeout
+        {
+            let deadline = sent + micros(pong_timeout);
+            if now >= deadline {
+                return going_away("pong timeout");
+            }
+            next = next.min(deadline);
+        } else if let Some(ping_interval) = config.ping_interval {
+            let due = received.max(last_ping.unwrap_or(0)) + micros(ping_interval);
+            if now >= due {
+                // before sending, as the pong may be received before `ping` returns
+                last_ping = Some(activity.now());
+                if let Err(_e) = handle.ping().await {
+                    crate::DEBUG!("[ws] failed to send a ping: {_e}");
+                    return going_away("heartbeat failed");
+                }
+                continue;
+            }
+            next = next.min(due);
+        }
+
+        crate::__rt__::sleep(Duration::from_micros(next - now)).await;
+    }
+}
+
+fn going_away(reason: &'static str) -> CloseFrame {
+    CloseFrame {
+        code: CloseCode::Away,
+        reason: Some(reason.into()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn can_close_websocket_by_heartbeat() {
+        use crate::__rt__::testing::serve;
+        use crate::ws::testing::{connect, recv, send};
+        use crate::ws::{Message, WebSocket, WebSocketContext};
+        use crate::{Ohkami, Route};
+
+        async fn handle(mut conn: crate::ws::Connection<crate::session::Connection>) {
+            loop {
+                match conn.recv().await {
+                    Ok(Some(Message::Close(_))) | Err(_) => break,
+                    _ => (),
+                }
+            }
+        }
+        async fn heartbeat(ctx: WebSocketContext<'_>) -> WebSocket {
+            ctx.upgrade_with(
+                Config {
+                    ping_interval: Some(Duration::from_millis(100)),
+                    pong_timeout: Some(Duration::from_millis(200)),
+                    ..Default::default()
+                },
+                handle,
+            )
+        }
+        async fn idle(ctx: WebSocketContext<'_>) -> WebSocket {
+            ctx.upgrade_with(
+                Config {
+                    idle_timeout: Some(Duration::from_millis(200)),
+                    ..Default::default()
+                },
+                handle,
+            )
+        }
+
+        let (closed_alive, closed_idle) = serve(
+            Ohkami::new(("/heartbeat".GET(heartbeat), "/idle".GET(idle))),
+            crate::Config::default(),
+            |addr, _| {
+                let (mut alive, _) = connect(addr, "/heartbeat", "");
+                let (mut idle, _) = connect(addr, "/idle", "");
+
+                assert_eq!(recv(&mut alive), (0x89, vec![]), "expected a ping");
+                send(&mut alive, 0xa, &[]);
+                assert_eq!(recv(&mut alive), (0x89, vec![]), "expected a ping");
+                let closed_alive = recv(&mut alive);
+
+                let closed_idle = recv(&mut idle);
+                (closed_alive, closed_idle)
+            },
+        );
+
+        assert_eq!(closed_alive, (0x88, b"\x03\xe9pong timeout".to_vec()));
+        assert_eq!(closed_idle, (0x88, b"\x03\xe9idle timeout".to_vec()));
+    }
+}
@@ -0,0 +1,514 @@
+use super::connection::UnderlyingConnection;
+use super::{Connection, Message, ReadHalf};
+use std::borrow::Borrow;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// # Hub of WebSocket connections
+///
+/// Rooms and fan-out for WebSocket connections on native runtimes.
+///
+/// A connection [`attach`](Hub::attach)ed to a hub gets a [`SessionId`] and
+/// a bounded send queue, and then can join and leave rooms keyed by `K`,
+/// receive messages broadcast to the rooms, or be sent messages directly by the id.
+/// It's removed from the hub and all its rooms when the handler of `attach`
+/// finishes or the connection is broken.
+///
+/// Sending or broadcasting never waits for any connection: when a connection's
+/// queue is full ( a slow consumer ), the message is dropped for that connection.
+///
+/// `Hub` is cheaply cloneable, sharing the same rooms.
+///
+/// <br>
+///
+/// *example.rs*
+/// ```no_run
+/// use ohkami::prelude::*;
+/// use ohkami::ws::{WebSocketContext, WebSocket, Message, Hub};
+///
+/// #[tokio::main]
+/// async fn main() {
+///     Ohkami::new((
+///         Context::new(Hub::<String>::new()),
+///         "/chat/:room".GET(chat),
+///     )).howl("localhost:3000").await
+/// }
+///
+/// async fn chat(
+///     Path(room): Path<&str>,
+///     ctx: WebSocketContext<'_>,
+///     Context(hub): Context<'_, Hub>,
+/// ) -> WebSocket {
+///     let (room, hub) = (room.to_owned(), hub.clone());
+///     ctx.upgrade(|conn| async move {
+///         let hub = &hub;
+///         hub.attach(conn, |id, mut r| async move {
+///             hub.join(id, room.clone());
+///             while let Ok(Some(message)) = r.recv().await {
+///                 match message {
+///                     Message::Text(text) => {
+///                         hub.broadcast(&room, format!("{id}: {text}"));
+///                     }
+///                     Message::Ping(payload) => {
+///                         hub.send(id, Message::Pong(payload));
+///                     }
+///                     Message::Close(_) => break,
+///                     _ => (),
+///                 }
+///             }
+///         }).await
+///     })
+/// }
+/// ```
+pub struct Hub<K = String>(Arc<HubState<K>>);
+
+/// Identifier of a connection attached to a [`Hub`], unique in the process
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct SessionId(u64);
+
+struct HubState<K> {
+    queue_capacity: usize,
+    sessions: Mutex<Sessions<K>>,
+}
+
+struct Sessions<K> {
+    members: HashMap<SessionId, Member<K>>,
+    rooms: HashMap<K, HashSet<SessionId>>,
+}
+
+struct Member<K> {
+    queue: Arc<Queue>,
+    rooms: HashSet<K>,
+}
+
+/// Bounded queue of messages to a connection, drained by its sending loop
+struct Queue {
+    capacity: usize,
+    state: Mutex<QueueState>,
+}
+
+struct QueueState {
+    messages: VecDeque<Message>,
+    /// set when the session is removed, then the rest of messages are
+    /// still sent and the sending loop finishes
+    closed: bool,
+    waker: Option<std::task::Waker>,
+}
+
+const _: (/* trait impls */) = {
+    impl<K> Clone for Hub<K> {
+        fn clone(&self) -> Self {
+            Self(self.0.clone())
+        }
+    }
+
+    impl<K: Eq + Hash + Clone> Default for Hub<K> {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
+
+    impl<K> std::fmt::Debug for Hub<K> {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            let sessions = self.0.sessions.lock().unwrap();
+            f.debug_struct("Hub")
+                .field("queue_capacity", &self.0.queue_capacity)
+                .field("sessions", &sessions.members.len())
+                .field("rooms", &sessions.rooms.len())
+                .finish()
+        }
+    }
+
+    impl std::fmt::Display for SessionId {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+};
+
+impl<K: Eq + Hash + Clone> Hub<K> {
+    /// A hub with the default queue capacity of 128 messages per connection.
+    pub fn new() -> Self {
+        Self::with_queue_capacity(128)
+    }
+
+    /// A hub with the capacity of each connection's send queue.
+    ///
+    /// ## Panics
+    ///
+    /// This panics if `queue_capacity` is 0.
+    pub fn with_queue_capacity(queue_capacity: usize) -> Self {
+        assert!(queue_capacity > 0, "`queue_capacity` must be positive");
+        Self(Arc::new(HubState {
+            queue_capacity,
+            sessions: Mutex::new(Sessions {
+                members: HashMap::new(),
+                rooms: HashMap::new(),
+            }),
+        }))
+    }
+
+    /// Attach the connection to the hub and run `handler` with its `SessionId`
+    /// and reading half, while sending messages queued for it.
+    ///
+    /// The connection is removed from the hub and all its rooms when `handler`
+    /// finishes or sending fails, and this returns after the rest of queued
+    /// messages are sent.
+    ///
+    /// **note** : Messages to the connection itself, including `Pong`s for
+    /// `Ping`s ( not handled automatically by `ReadHalf` ), are to be
+    /// [`send`](Hub::send) via the hub.
+    ///
+    /// ## Panics
+    ///
+    /// This panics if the connection is already closed.
+    pub async fn attach<C, F>(
+        &self,
+        conn: Connection<C>,
+        handler: impl FnOnce(SessionId, ReadHalf<C>) -> F,
+    ) where
+        C: UnderlyingConnection,
+        F: std::future::Future<Output = ()>,
+    {
+        let (r, mut w) = conn.split();
+        let (id, queue) = self.register();
+
+        let handling = async {
+            handler(id, r).await;
+            self.remove(id);
+        };
+        let sending = async {
+            while let Some(message) = queue.pop().await {
+                let mut sent = w.write(message).await.map(drop);
+                if sent.is_ok() && queue.is_empty() {
+                    sent = w.flush().await;
+                }
+                if let Err(_e) = sent {
+                    crate::DEBUG!("[ws::Hub] failed to send to session {id}: {_e}");
+                    self.remove(id);
+                    break;
+                }
+            }
+        };
+        futures_util::future::join(handling, sending).await;
+    }
+
+    /// Let the session join the room. `false` if the session is not in the hub.
+    pub fn join(&self, id: SessionId, room: K) -> bool {
+        let sessions = &mut *self.0.sessions.lock().unwrap();
+        let Some(member) = sessions.members.get_mut(&id) else {
+            return false;
+        };
+        member.rooms.insert(room.clone());
+        sessions.rooms.entry(room).or_default().insert(id);
+        true
+    }
+
+    /// Let the session leave the room. `false` if the session is not in the room.
+    pub fn leave<Q>(&self, id: SessionId, room: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let sessions = &mut *self.0.sessions.lock().unwrap();
+        let Some(member) = sessions.members.get_mut(&id) else {
+            return false;
+        };
+        if !member.rooms.remove(room) {
+            return false;
+        }
+        leave_room(&mut sessions.rooms, id, room);
+        true
+    }
+
+    /// Queue the message to the session. `false` if the session is not in
+    /// the hub or its queue is full.
+    pub fn send(&self, id: SessionId, message: impl Into<Message>) -> bool {
+        let sessions = self.0.sessions.lock().unwrap();
+        sessions
+            .members
+            .get(&id)
+            .is_some_and(|member| member.queue.push(message.into()))
+    }
+
+    /// Queue the message to all sessions in the room, returning the number of
+    /// sessions it's queued to ( excluding ones whose queue is full ).
+    pub fn broadcast<Q>(&self, room: &Q, message: impl Into<Message>) -> usize
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let message = message.into();
+        let sessions = self.0.sessions.lock().unwrap();
+        let Some(ids) = sessions.rooms.get(room) else {
+            return 0;
+        };
+        ids.iter()
+            .filter(|id| sessions.members[id].queue.push(duplicate(&message)))
+            .count()
+    }
+
+    /// Sessions in the room
+    pub fn members<Q>(&self, room: &Q) -> Vec<SessionId>
+    where
+        K: Borrow<Q>,
+        Q: Eq + Hash + ?Sized,
+    {
+        let sessions = self.0.sessions.lock().unwrap();
+        sessions
+            .rooms
+            .get(room)
+            .map(|ids| ids.iter().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Whether the session is in the hub, i.e. attached and not closed yet
+    pub fn contains(&self, id: SessionId) -> bool {
+        self.0.sessions.lock().unwrap().members.contains_key(&id)
+    }
+
+    fn register(&self) -> (SessionId, Arc<Queue>) {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+        let id = SessionId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+        let queue = Arc::new(Queue::new(self.0.queue_capacity));
+        self.0.sessions.lock().unwrap().members.insert(
+            id,
+            Member {
+                queue: queue.clone(),
+                rooms: HashSet::new(),
+            },
+        );
+        (id, queue)
+    }
+
+    fn remove(&self, id: SessionId) {
+        let sessions = &mut *self.0.sessions.lock().unwrap();
+        if let Some(member) = sessions.members.remove(&id) {
+            for room in &member.rooms {
+                leave_room(&mut sessions.rooms, id, room);
+            }
+            member.queue.close();
+        }
+    }
+}
+
+/// `Message` is not `Clone`
+fn duplicate(message: &Message) -> Message {
+    match message {
+        Message::Text(text) => Message::Text(text.clone()),
+        Message::Binary(bytes) => Message::Binary(bytes.clone()),
+        Message::Ping(bytes) => Message::Ping(bytes.clone()),
+        Message::Pong(bytes) => Message::Pong(bytes.clone()),
+        Message::Close(frame) => Message::Close(frame.as_ref().map(|frame| super::CloseFrame {
+            code: super::CloseCode::from_u16(frame.code.as_u16()),
+            reason: frame.reason.clone(),
+        })),
+    }
+}
+
+/// remove `id` from the room, and the room itself if it gets empty
+fn leave_room<K, Q>(rooms: &mut HashMap<K, HashSet<SessionId>>, id: SessionId, room: &Q)
+where
+    K: Eq + Hash + Borrow<Q>,
+    Q: Eq + Hash + ?Sized,
+{
+    if let Some(ids) = rooms.get_mut(room) {
+        ids.remove(&id);
+        if ids.is_empty() {
+            rooms.remove(room);
+        }
+    }
+}
+
+impl Queue {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            state: Mutex::new(QueueState {
+                messages: VecDeque::new(),
+                closed: false,
+                waker: None,
+            }),
+        }
+    }
+
+    /// `false` if the queue is full or closed
+    fn push(&self, message: Message) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.closed || state.messages.len() >= self.capacity {
+            return false;
+        }
+        state.messages.push_back(message);
+        if let Some(waker) = state.waker.take() {
+            drop(state);
+            waker.wake();
+        }
+        true
+    }
+
+    fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        if let Some(waker) = state.waker.take() {
+            drop(state);
+            waker.wake();
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.state.lock().unwrap().messages.is_empty()
+    }
+
+    /// `None` when the queue is closed and empty
+    async fn pop(&self) -> Option<Message> {
+        std::future::poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            match state.messages.pop_front() {
+                Some(message) => std::task::Poll::Ready(Some(message)),
+                None if state.closed => std::task::Poll::Ready(None),
+                None => {
+                    state.waker = Some(cx.waker().clone());
+                    std::task::Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn drain(queue: &Queue) -> Vec<String> {
+        let mut state = queue.state.lock().unwrap();
+        (state.messages.drain(..))
+            .map(|message| match message {
+                Message::Text(text) => text,
+                other => panic!("unexpected message: {other:?}"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_rooms() {
+        let hub = Hub::<String>::with_queue_capacity(2);
+        let (a, qa) = hub.register();
+        let (b, qb) = hub.register();
+        let (c, qc) = hub.register();
+
+        assert!(hub.join(a, "lobby".into()));
+        assert!(hub.join(b, "lobby".into()));
+        assert!(hub.join(c, "games".into()));
+        assert!(hub.join(a, "games".into()));
+
+        let mut lobby = hub.members("lobby");
+        lobby.sort_by_key(|id| id.0);
+        assert_eq!(lobby, [a, b]);
+
+        assert_eq!(hub.broadcast("lobby", "hello"), 2);
+        assert_eq!(hub.broadcast("nowhere", "hello"), 0);
+        assert!(hub.send(c, "direct"));
+        assert_eq!(drain(&qa), ["hello"]);
+        assert_eq!(drain(&qb), ["hello"]);
+        assert_eq!(drain(&qc), ["direct"]);
+
+        assert!(hub.leave(b, "lobby"));
+        assert!(!hub.leave(b, "lobby"));
+        assert_eq!(hub.broadcast("lobby", "bye"), 1);
+        assert_eq!(drain(&qb), [""; 0]);
+
+        /* removal on close */
+        hub.remove(a);
+        assert!(!hub.contains(a));
+        assert!(!hub.send(a, "closed"));
+        assert!(!hub.join(a, "lobby".into()));
+        assert_eq!(hub.members("lobby"), []);
+        assert_eq!(hub.members("games"), [c]);
+        assert_eq!(hub.0.sessions.lock().unwrap().rooms.len(), 1);
+
+        /* closed queue sends the rest, then finishes */
+        assert_eq!(drain(&qa), ["bye"]);
+        assert!(qa.state.lock().unwrap().closed);
+    }
+
+    #[test]
+    fn test_slow_consumer() {
+        let hub = Hub::<&'static str>::with_queue_capacity(2);
+        let (slow, q_slow) = hub.register();
+        let (fast, q_fast) = hub.register();
+        hub.join(slow, "room");
+        hub.join(fast, "room");
+
+        assert_eq!(hub.broadcast("room", "1"), 2);
+        assert_eq!(hub.broadcast("room", "2"), 2);
+        drain(&q_fast);
+
+        // the slow one's queue is full, but the broadcast is not blocked
+        assert_eq!(hub.broadcast("room", "3"), 1);
+        assert!(!hub.send(slow, "4"));
+
+        assert_eq!(drain(&q_slow), ["1", "2"]);
+        assert_eq!(drain(&q_fast), ["3"]);
+    }
+
+    #[test]
+    fn can_broadcast_via_websocket_hub() {
+        use crate::__rt__::testing::serve;
+        use crate::fang::Context;
+        use crate::ws::testing::{connect, recv, send};
+        use crate::ws::{WebSocket, WebSocketContext};
+        use crate::{Ohkami, Route};
+        use std::io::Read;
+
+        async fn room(ctx: WebSocketContext<'_>, Context(hub): Context<'_, Hub>) -> WebSocket {
+            let hub = hub.clone();
+            ctx.upgrade(|conn| async move {
+                let hub = &hub;
+                hub.attach(conn, |id, mut r| async move {
+                    hub.join(id, "room".into());
+                    hub.send(id, "joined");
+                    while let Ok(Some(Message::Text(text))) = r.recv().await {
+                        hub.broadcast("room", text);
+                    }
+                })
+                .await
+            })
+        }
+
+        let hub = Hub::<String>::new();
+
+        let received = serve(
+            Ohkami::new((Context::new(hub.clone()), "/room".GET(room))),
+            crate::Config::default(),
+            |addr, _| {
+                let (mut a, _) = connect(addr, "/room", "");
+                let (mut b, _) = connect(addr, "/room", "");
+                assert_eq!(recv(&mut a), (0x81, b"joined".to_vec()));
+                assert_eq!(recv(&mut b), (0x81, b"joined".to_vec()));
+
+                send(&mut a, 0x1, b"hello");
+                let received = [recv(&mut a), recv(&mut b)];
+
+                send(&mut a, 0x8, &[]);
+                send(&mut b, 0x8, &[]);
+                for mut stream in [a, b] {
+                    // the close frame from the server
+                    let mut rest = Vec::new();
+                    stream.read_to_end(&mut rest).ok();
+                }
+                received
+            },
+        );
+
+        assert_eq!(
+            received,
+            [(0x81, b"hello".to_vec()), (0x81, b"hello".to_vec())]
+        );
+        // removed from the room on close
+        assert_eq!(hub.members("room"), []);
+    }
+}
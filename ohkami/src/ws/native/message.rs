@@ -0,0 +1,283 @@
+use super::deflate::{Deflater, Inflater};
+use super::frame::{Frame, OpCode, invalid};
+use super::heartbeat::Activity;
+use super::{CloseCode, CloseFrame, Config, Message};
+use crate::__rt__::{AsyncRead, AsyncWrite};
+use std::io::Error;
+use std::sync::Arc;
+
+const CONTROL_PAYLOAD_LIMIT: usize = 125;
+
+/// reading messages from the client
+pub(super) struct Incoming {
+    inflater: Option<Inflater>,
+    activity: Arc<Activity>,
+    /// opcode, compressed or not, and payload so far of a fragmented message,
+    /// between whose frames control frames may be interleaved
+    fragmented: Option<(OpCode, bool, Vec<u8>)>,
+}
+
+impl Incoming {
+    pub(super) fn new(inflater: Option<Inflater>, activity: Arc<Activity>) -> Self {
+        Self {
+            inflater,
+            activity,
+            fragmented: None,
+        }
+    }
+
+    /// read a message, or `None` if the connection is closed by the client
+    pub(super) async fn read(
+        &mut self,
+        stream: &mut (impl AsyncRead + Unpin),
+        config: &Config,
+    ) -> Result<Option<Message>, Error> {
+        loop {
+            let Some(frame) = Frame::read_from(stream, config).await? else {
+                return Ok(None);
+            };
+            self.activity.on_frame_received();
+
+            if frame.opcode.is_control() {
+                if !frame.is_final || frame.compressed {
+                    return Err(invalid("Control frame is fragmented or compressed"));
+                }
+                if frame.payload.len() > CONTROL_PAYLOAD_LIMIT {
+                    return Err(invalid("Control frame's payload is too large"));
+                }
+                return control_message(frame).map(Some);
+            }
+
+            if frame.compressed && (self.inflater.is_none() || frame.opcode == OpCode::Continue) {
+                return Err(invalid("RSV1 is set without `permessage-deflate`"));
+            }
+
+            let (opcode, compressed, payload) = match (frame.opcode, self.fragmented.take()) {
+                (OpCode::Continue, None) => {
+                    return Err(invalid("Unexpected continuation frame"));
+                }
+                (OpCode::Continue, Some((opcode, compressed, mut payload))) => {
+                    payload.extend_from_slice(&frame.payload);
+                    (opcode, compressed, payload)
+                }
+                (_, Some(_)) => return Err(invalid("Expected continuation frame")),
+                (opcode, None) => (opcode, frame.compressed, frame.payload),
+            };
+            if config
+                .max_message_size
+                .is_some_and(|limit| payload.len() > limit)
+            {
+                return Err(invalid("Incoming message is too large"));
+            }
+
+            if !frame.is_final {
+                self.fragmented = Some((opcode, compressed, payload));
+                continue;
+            }
+
+            self.activity.on_message();
+            let payload = match (compressed, &mut self.inflater) {
+                (true, Some(inflater)) => inflater.decompress(payload, config.max_message_size)?,
+                _ => payload,
+            };
+            return Ok(Some(match opcode {
+                OpCode::Text => Message::Text(
+                    String::from_utf8(payload)
+                        .map_err(|_| invalid("Text message is not valid UTF-8"))?,
+                ),
+                _ => Message::Binary(payload),
+            }));
+        }
+    }
+}
+
+fn control_message(frame: Frame) -> Result<Message, Error> {
+    Ok(match frame.opcode {
+        OpCode::Ping => Message::Ping(frame.payload),
+        OpCode::Pong => Message::Pong(frame.payload),
+        _ => Message::Close(match &*frame.payload {
+            [] => None,
+            [_] => return Err(invalid("Close frame's payload is too short")),
+            [c1, c2, reason @ ..] => Some(CloseFrame {
+                code: CloseCode::from_u16(u16::from_be_bytes([*c1, *c2])),
+                reason: (!reason.is_empty())
+                    .then(|| String::from_utf8(reason.to_vec()))
+                    .transpose()
+                    .map_err(|_| invalid("Close reason is not valid UTF-8"))?
+                    .map(Into::into),
+            }),
+        }),
+    })
+}
+
+/// writing messages to the client
+pub(super) struct Outgoing {
+    deflater: Option<Deflater>,
+    activity: Arc<Activity>,
+    /// frames written but not flushed yet
+    buf: Vec<u8>,
+}
+
+impl Outgoing {
+    pub(super) fn new(
+        deflater: Option<Deflater>,
+        activity: Arc<Activity>,
+        config: &Config,
+    ) -> Self {
+        Self {
+            deflater,
+            activity,
+            buf: Vec::with_capacity(config.write_buffer_size),
+        }
+    }
+
+    /// buffer a message as a frame, flushing when the buffered bytes exceed
+    /// `write_buffer_size` of the config
+    pub(super) async fn write(
+        &mut self,
+        message: Message,
+        stream: &mut (impl AsyncWrite + Unpin),
+        config: &Config,
+    ) -> Result<usize, Error> {
+        let n = self.frame(message).encode_into(&mut self.buf);
+        if self.buf.len() > config.write_buffer_size {
+            if self.buf.len() > config.max_write_buffer_size {
+                return Err(Error::other(
+                    "Buffered messages is larger than `max_write_buffer_size`",
+                ));
+            }
+            self.flush(stream).await?;
+        }
+        Ok(n)
+    }
+
+    /// write a message as a frame, together with the buffered ones, and flush
+    pub(super) async fn send(
+        &mut self,
+        message: Message,
+        stream: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<(), Error> {
+        self.frame(message).encode_into(&mut self.buf);
+        self.flush(stream).await
+    }
+
+    pub(super) async fn flush(
+        &mut self,
+        stream: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<(), Error> {
+        if !self.buf.is_empty() {
+            stream.write_all(&self.buf).await?;
+            self.buf.clear();
+        }
+        stream.flush().await
+    }
+
+    fn frame(&mut self, message: Message) -> Frame {
+        let (opcode, payload) = match message {
+            Message::Text(text) => (OpCode::Text, text.into_bytes()),
+            Message::Binary(bytes) => (OpCode::Binary, bytes),
+            Message::Ping(mut bytes) => {
+                bytes.truncate(CONTROL_PAYLOAD_LIMIT);
+                (OpCode::Ping, bytes)
+            }
+            Message::Pong(mut bytes) => {
+                bytes.truncate(CONTROL_PAYLOAD_LIMIT);
+                (OpCode::Pong, bytes)
+            }
+            Message::Close(frame) => (
+                OpCode::Close,
+                frame
+                    .map(|CloseFrame { code, reason }| {
+                        let mut payload = code.as_u16().to_be_bytes().to_vec();
+                        if let Some(reason) = reason {
+                            let mut end = reason.len().min(CONTROL_PAYLOAD_LIMIT - 2);
+                            while !reason.is_char_boundary(end) {
+                                end -= 1;
+                            }
+                            payload.extend_from_slice(&reason.as_bytes()[..end]);
+                        }
+                        payload
+                    })
+                    .unwrap_or_default(),
+            ),
+        };
+
+        if !opcode.is_control() {
+            self.activity.on_message();
+        }
+        let compressed = match &mut self.deflater {
+            Some(deflater) if !opcode.is_control() => deflater.compress(&payload),
+            _ => None,
+        };
+        match compressed {
+            Some(payload) => Frame {
+                is_final: true,
+                compressed: true,
+                opcode,
+                payload,
+            },
+            None => Frame {
+                is_final: true,
+                compressed: false,
+                opcode,
+                payload,
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_write_buffer() {
+        let config = Config {
+            write_buffer_size: 8,
+            max_write_buffer_size: 16,
+            ..Default::default()
+        };
+        let mut outgoing = Outgoing::new(None, Arc::new(Activity::new()), &config);
+        let mut stream = Vec::<u8>::new();
+
+        crate::__rt__::testing::block_on(async {
+            /* buffered until exceeding `write_buffer_size` */
+            assert_eq!(
+                outgoing
+                    .write("hi".into(), &mut stream, &config)
+                    .await
+                    .unwrap(),
+                4
+            );
+            assert!(stream.is_empty());
+            assert_eq!(
+                outgoing
+                    .write("hello".into(), &mut stream, &config)
+                    .await
+                    .unwrap(),
+                7
+            );
+            assert_eq!(stream, b"\x81\x02hi\x81\x05hello");
+
+            /* `send` writes the buffered ones together */
+            outgoing
+                .write("a".into(), &mut stream, &config)
+                .await
+                .unwrap();
+            outgoing
+                .send(Message::Ping(vec![]), &mut stream)
+                .await
+                .unwrap();
+            assert_eq!(&stream[11..], b"\x81\x01a\x89\x00");
+
+            /* exceeding `max_write_buffer_size` */
+            assert!(
+                outgoing
+                    .write("too large message".into(), &mut stream, &config)
+                    .await
+                    .is_err()
+            );
+            assert_eq!(stream.len(), 16);
+        });
+    }
+}
@@ -0,0 +1,302 @@
+#![cfg(feature = "__rt_native__")]
+
+pub mod connection;
+mod deflate;
+mod frame;
+mod heartbeat;
+mod hub;
+mod message;
+mod session;
+
+pub use self::connection::{
+    Connection,
+    split::{self, ReadHalf, WriteHalf},
+};
+pub use self::deflate::PerMessageDeflate;
+pub use self::hub::{Hub, SessionId};
+pub use mews::{CloseCode, CloseFrame, Message};
+
+/// used in `crate::response::content::Content::WebSocket`
+pub(crate) use self::session::Session;
+
+use std::time::Duration;
+
+/// # Configuration of WebSocket connections
+///
+/// Given to [`upgrade_with`](crate::ws::WebSocketContext::upgrade_with).
+///
+/// When a deadline of the heartbeat ( `pong_timeout` or `idle_timeout` ) has passed,
+/// the handler is aborted and the connection is closed with `CloseCode::Away`.
+///
+/// ```
+/// use ohkami::ws::{WebSocketContext, WebSocket, Config};
+/// use std::time::Duration;
+///
+/// async fn ws(ctx: WebSocketContext<'_>) -> WebSocket {
+///     ctx.upgrade_with(
+///         Config {
+///             ping_interval: Some(Duration::from_secs(30)),
+///             pong_timeout: Some(Duration::from_secs(10)),
+///             idle_timeout: Some(Duration::from_secs(10 * 60)),
+///             ..Default::default()
+///         },
+///         |mut conn| async move {
+///             while let Ok(Some(message)) = conn.recv().await {
+///                 /* ... */
+///             }
+///         },
+///     )
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct Config {
+    /// [bytes] size of the buffer for `write`, where messages are kept until
+    /// the buffered bytes exceed this and then written to the stream together.
+    /// `0` to write every message immediately.
+    /// - default: `128 KiB`
+    pub write_buffer_size: usize,
+    /// [bytes] `write` fails when the buffered bytes exceed this,
+    /// with the message left in the buffer.
+    /// - default: `usize::MAX`
+    pub max_write_buffer_size: usize,
+    /// accept frames from the client without masking, violating RFC 6455.
+    /// - default: `false`
+    pub accept_unmasked_frames: bool,
+    /// [bytes] limit of the size of incoming messages ( after decompression ).
+    /// - default: `Some(64 MiB)`
+    pub max_message_size: Option<usize>,
+    /// [bytes] limit of the size of incoming frames.
+    /// - default: `Some(16 MiB)`
+    pub max_frame_size: Option<usize>,
+    /// settings of `permessage-deflate` extension, negotiated when the client
+    /// offers it. `None` to disable compression.
+    /// - default: `None`
+    pub permessage_deflate: Option<PerMessageDeflate>,
+    /// send a ping when nothing has been received from the client for this duration.
+    /// - default: `None` ( no ping )
+    pub ping_interval: Option<Duration>,
+    /// close the connection when nothing has been received from the client for
+    /// this duration after a ping.
+    /// - default: `None` ( no deadline )
+    pub pong_timeout: Option<Duration>,
+    /// close the connection when no text or binary message has been received
+    /// nor sent for this duration.
+    /// - default: `None` ( no deadline )
+    pub idle_timeout: Option<Duration>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            write_buffer_size: 128 * 1024,
+            max_write_buffer_size: usize::MAX,
+            accept_unmasked_frames: false,
+            max_message_size: Some(64 << 20),
+            max_frame_size: Some(16 << 20),
+            permessage_deflate: None,
+            ping_interval: None,
+            pong_timeout: None,
+            idle_timeout: None,
+        }
+    }
+}
+
+impl<'ctx> super::WebSocketContext<'ctx> {
+    /// create a `WebSocket` with the handler and default `Config`.
+    /// use [`upgrade_with`](crate::ws::WebSocketContext::upgrade_with) to provide a custom config.
+    ///
+    /// ## handler
+    ///
+    /// any 'static `FnOnce(Connection) -> {impl Future<Output = ()> + Send} + Send + Sync`
+    pub fn upgrade<H, F>(self, handler: H) -> WebSocket
+    where
+        H: FnOnce(Connection<crate::session::Connection>) -> F + Send + Sync + 'static,
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        self.upgrade_with(Config::default(), handler)
+    }
+
+    /// create a `WebSocket` with the config and handler.
+    ///
+    /// ## handler
+    ///
+    /// any 'static `FnOnce(Connection) -> {impl Future<Output = ()> + Send} + Send + Sync`
+    pub fn upgrade_with<H, F>(self, config: Config, handler: H) -> WebSocket
+    where
+        H: FnOnce(Connection<crate::session::Connection>) -> F + Send + Sync + 'static,
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let (extensions, deflate) = config
+            .permessage_deflate
+            .as_ref()
+            .zip(self.sec_websocket_extensions)
+            .and_then(|(settings, offers)| settings.negotiate(offers))
+            .unzip();
+
+        let protocol = self.protocol.map(String::from);
+
+        WebSocket {
+            sign: sign(self.sec_websocket_key),
+            extensions,
+            protocol: protocol.clone(),
+            session: Session::new(config, deflate, protocol, handler),
+        }
+    }
+}
+
+/// `Sec-WebSocket-Accept` for `Sec-WebSocket-Key`
+fn sign(sec_websocket_key: &str) -> String {
+    use ::base64::engine::{Engine, general_purpose::STANDARD};
+    use ::sha1::{Digest, Sha1};
+
+    let mut sha1 = <Sha1 as Digest>::new();
+    sha1.update(sec_websocket_key.as_bytes());
+    sha1.update(b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11");
+
+    Engine::encode(&STANDARD, sha1.finalize())
+}
+
+/// # Response for upgrading to WebSocket
+///
+/// Perform handshake with a `WebSocketContext`,
+/// establish a WebSocket connection,
+/// and run the given handler.
+///
+/// <br>
+///
+/// *example.rs*
+/// ```
+/// use ohkami::ws::{WebSocketContext, WebSocket};
+///
+/// async fn ws(ctx: WebSocketContext<'_>) -> WebSocket {
+///     ctx.upgrade(|mut conn| async move {
+///         conn.send("Hello, WebSocket! and bye...").await
+///             .expect("failed to send")
+///     })
+/// }
+/// ```
+///
+/// <br>
+///
+/// *split_example.rs*
+/// ```
+/// # use tokio::{join, spawn};
+/// # use tokio::time::{Duration, sleep};
+/// #
+/// use ohkami::ws::{WebSocketContext, WebSocket, Message};
+///
+/// async fn ws(ctx: WebSocketContext<'_>) -> WebSocket {
+///     ctx.upgrade(|c| async {
+///         let (mut r, mut w) = c.split();
+///         tokio::join!( /* joining is necessary to prevent resource leak or unsafe situations */
+///             tokio::spawn(async move {
+///                 while let Some(Message::Text(
+///                     text
+///                 )) = r.recv().await.expect("failed to recieve") {
+///                     println!("[->] {text}");
+///                     if text == "close" {break}
+///                 }
+///             }),
+///             tokio::spawn(async move {
+///                 for text in [
+///                     "abc",
+///                     "def",
+///                     "ghi",
+///                     "jk",
+///                     "lmno",
+///                     "pqr",
+///                     "stuvw",
+///                     "xyz"
+///                 ] {
+///                     println!("[<-] {text}");
+///                     w.send(text).await.expect("failed to send text");
+///                     sleep(Duration::from_secs(1)).await;
+///                 }
+///             })
+///         );
+///     })
+/// }
+/// ```
+pub struct WebSocket {
+    sign: String,
+    extensions: Option<String>,
+    protocol: Option<String>,
+    session: Session,
+}
+impl crate::IntoResponse for WebSocket {
+    fn into_response(self) -> crate::Response {
+        let mut res = crate::Response::SwitchingProtocols();
+        res.content = crate::response::Content::WebSocket(self.session);
+        res.with_headers(|h| {
+            h.connection("Upgrade")
+                .upgrade("websocket")
+                .sec_websocket_accept(self.sign)
+                .sec_websocket_extensions(self.extensions.map(Into::into))
+                .sec_websocket_protocol(self.protocol.map(Into::into))
+        })
+    }
+
+    #[cfg(feature = "openapi")]
+    fn openapi_responses() -> crate::openapi::Responses {
+        crate::openapi::Responses::new([(
+            101,
+            crate::openapi::Response::when("Upgrade to WebSocket"),
+        )])
+    }
+}
+
+/// WebSocket client on a blocking `TcpStream` for tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+
+    /// connect to `path` and upgrade, with additional `headers` ( each ending with CRLF ),
+    /// returning the stream and the handshake response
+    pub(crate) fn connect(addr: SocketAddr, path: &str, headers: &str) -> (TcpStream, String) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(
+                format!(
+                    "GET {path} HTTP/1.1\r\n\
+                    Connection: Upgrade\r\n\
+                    Upgrade: websocket\r\n\
+                    Sec-WebSocket-Version: 13\r\n\
+                    Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                    {headers}\
+                    \r\n"
+                )
+                .as_bytes(),
+            )
+            .unwrap();
+        let mut handshake = Vec::new();
+        while !handshake.ends_with(b"\r\n\r\n") {
+            let mut byte = [0];
+            stream.read_exact(&mut byte).unwrap();
+            handshake.push(byte[0]);
+        }
+        let handshake = String::from_utf8(handshake).unwrap();
+        assert!(
+            handshake.starts_with("HTTP/1.1 101 Switching Protocols\r\n"),
+            "{handshake}"
+        );
+        (stream, handshake)
+    }
+
+    /// a final frame of `opcode` with a short payload, masked by zero key
+    /// ( that is, the same as unmasked )
+    pub(crate) fn send(stream: &mut TcpStream, opcode: u8, payload: &[u8]) {
+        let mut frame = vec![0x80 | opcode, 0x80 | payload.len() as u8, 0, 0, 0, 0];
+        frame.extend_from_slice(payload);
+        stream.write_all(&frame).unwrap();
+    }
+
+    /// (first byte, payload) of a short frame
+    pub(crate) fn recv(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let mut head = [0; 2];
+        stream.read_exact(&mut head).unwrap();
+        let mut payload = vec![0; head[1] as usize];
+        stream.read_exact(&mut payload).unwrap();
+        (head[0], payload)
+    }
+}
@@ -0,0 +1,100 @@
+use super::deflate::{Deflater, Inflater, Negotiated};
+use super::heartbeat::{self, Activity};
+use super::message::{Incoming, Outgoing};
+use super::{CloseCode, CloseFrame, Config, Connection};
+use std::sync::Arc;
+
+type Handler = Box<
+    dyn FnOnce(
+            Connection<crate::session::Connection>,
+        ) -> std::pin::Pin<Box<dyn std::future::Future<Output = ()> + Send + 'static>>
+        + Send
+        + Sync,
+>;
+
+/// WebSocket session to be managed on the connection after the handshake
+pub struct Session {
+    /// boxed not to bloat `Response` holding this
+    config: Box<Config>,
+    deflate: Option<Negotiated>,
+    protocol: Option<String>,
+    handler: Handler,
+}
+
+impl Session {
+    pub(super) fn new<H, F>(
+        config: Config,
+        deflate: Option<Negotiated>,
+        protocol: Option<String>,
+        handler: H,
+    ) -> Self
+    where
+        H: FnOnce(Connection<crate::session::Connection>) -> F + Send + Sync + 'static,
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        Self {
+            config: Box::new(config),
+            deflate,
+            protocol,
+            handler: Box::new(|c| Box::pin(handler(c))),
+        }
+    }
+
+    /// run the handler on the connection with the heartbeat, and send a close
+    /// frame if not sent yet.
+    ///
+    /// returns `true` if the session has been aborted by `timeout`.
+    pub(crate) async fn manage_with_timeout(
+        self,
+        timeout: impl std::future::Future,
+        conn: crate::session::Connection,
+    ) -> bool {
+        let (inflater, deflater) = match &self.deflate {
+            Some(negotiated) => (
+                Some(Inflater::new(negotiated)),
+                Some(Deflater::new(negotiated)),
+            ),
+            None => (None, None),
+        };
+        let activity = Arc::new(Activity::new());
+        let (conn, handle) = Connection::new(
+            conn,
+            (*self.config).clone(),
+            self.protocol,
+            Incoming::new(inflater, activity.clone()),
+            Outgoing::new(deflater, activity.clone(), &self.config),
+        );
+
+        // `Some(None)` when aborted by `timeout`
+        let mut stopped_by = None;
+        let stop = async {
+            stopped_by = Some(
+                crate::ohkami::sync::until(
+                    timeout,
+                    heartbeat::watch(&self.config, &activity, &handle),
+                )
+                .await,
+            );
+        };
+        crate::ohkami::sync::until(stop, (self.handler)(conn)).await;
+
+        let aborted = matches!(stopped_by, Some(None));
+        handle
+            .close_with(match stopped_by {
+                None => CloseFrame {
+                    code: CloseCode::Normal,
+                    reason: None,
+                },
+                Some(Some(frame)) => {
+                    crate::DEBUG!("WebSocket session closed by heartbeat: {frame:?}");
+                    frame
+                }
+                Some(None) => CloseFrame {
+                    code: CloseCode::Away,
+                    reason: Some("session timeout".into()),
+                },
+            })
+            .await;
+        aborted
+    }
+}
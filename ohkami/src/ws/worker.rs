@@ -19,11 +19,14 @@ impl<'req> super::WebSocketContext<'req> {
             server: ws,
         } = WebSocketPair::new().expect("failed to create WebSocketPair");
 
+        let protocol = self.protocol.map(String::from);
+
         ws.accept().ok();
         wasm_bindgen_futures::spawn_local({
             let ws = Rc::new(ws);
+            let protocol = protocol.clone();
             async move {
-                handler(Connection::new(ws.clone())).await;
+                handler(Connection::new(ws.clone(), protocol)).await;
                 // https://developer.mozilla.org/en-US/docs/Web/API/WebSocket/close
                 //
                 // > If the connection is already CLOSED, this method does nothing.
@@ -31,7 +34,7 @@ impl<'req> super::WebSocketContext<'req> {
             }
         });
 
-        WebSocket(session)
+        WebSocket(session, protocol)
     }
 
     /// WebSocket with a DurableObject.
@@ -133,11 +136,14 @@ impl<'req> super::WebSocketContext<'req> {
         self,
         durable_object: worker::Stub,
     ) -> Result<WebSocket, worker::Error> {
+        // let the Durable Object know the selected subprotocol
+        let headers = [("Upgrade", "websocket")]
+            .into_iter()
+            .chain(self.protocol.map(|p| ("Sec-WebSocket-Protocol", p)));
         self.upgrade_durable_with(
             worker::Request::new_with_init(
                 "http://ws",
-                worker::RequestInit::new()
-                    .with_headers(worker::Headers::from_iter([("Upgrade", "websocket")])),
+                worker::RequestInit::new().with_headers(worker::Headers::from_iter(headers)),
             )
             .unwrap(),
             durable_object,
@@ -151,11 +157,12 @@ impl<'req> super::WebSocketContext<'req> {
         req: worker::Request,
         durable_object: worker::Stub,
     ) -> Result<WebSocket, worker::Error> {
+        let protocol = self.protocol.map(String::from);
         durable_object
             .fetch_with_request(req)
             .await?
             .websocket()
-            .map(WebSocket)
+            .map(|session| WebSocket(session, protocol))
             .ok_or_else(|| {
                 worker::Error::RustError(format!(
                     "given Durable Object stub didn't respond with WebSocket"
@@ -167,10 +174,21 @@ impl<'req> super::WebSocketContext<'req> {
 pub struct Connection {
     ws: Rc<worker::WebSocket>,
     events: Option<EventStream<'static>>,
+    protocol: Option<String>,
 }
 impl Connection {
-    fn new(ws: Rc<worker::WebSocket>) -> Self {
-        Self { ws, events: None }
+    fn new(ws: Rc<worker::WebSocket>, protocol: Option<String>) -> Self {
+        Self {
+            ws,
+            events: None,
+            protocol,
+        }
+    }
+
+    /// The subprotocol selected by [`select_protocol`](crate::ws::WebSocketContext::select_protocol)
+    /// on the handshake, if any.
+    pub fn protocol(&self) -> Option<&str> {
+        self.protocol.as_deref()
     }
 
     pub async fn recv(&mut self) -> Result<Option<Message>, worker::Error> {
@@ -279,14 +297,14 @@ pub mod split {
     }
 }
 
-pub struct WebSocket(Session);
+pub struct WebSocket(Session, Option<String>);
 impl crate::IntoResponse for WebSocket {
     fn into_response(self) -> crate::Response {
         let mut res = crate::Response::SwitchingProtocols();
         res.content = crate::response::Content::WebSocket(self.0);
-        res /*        
+        res.with_headers(|h| h.sec_websocket_protocol(self.1.map(Into::into))) /*
         let `worker` crate and Cloudflare Workers to do around
-        headers and something other
+        other headers and something
          */
     }
 
@@ -327,7 +327,7 @@ mod ws {
                 lambda_runtime::LambdaEvent<internal::LambdaWebSocketRequest>,
                 Response = lambda_runtime::FunctionResponse<
                     internal::LambdaResponse,
-                    std::pin::Pin<Box<dyn ohkami_lib::Stream<Item = Result<String, std::convert::Infallible>> + Send>>
+                    std::pin::Pin<Box<dyn ohkami_lib::Stream<Item = Result<Vec<u8>, std::io::Error>> + Send>>
                 >
             >
         where
@@ -357,7 +357,7 @@ mod ws {
             {
                 type Response = lambda_runtime::FunctionResponse<
                     internal::LambdaResponse,
-                    std::pin::Pin<Box<dyn ohkami_lib::Stream<Item = Result<String, std::convert::Infallible>> + Send>>
+                    std::pin::Pin<Box<dyn ohkami_lib::Stream<Item = Result<Vec<u8>, std::io::Error>> + Send>>
                 >;
                 type Error = lambda_runtime::Error;
                 type Future = impl Future<Output = Result<(), lambda_runtime::Error>>;